@@ -0,0 +1,247 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::ptr;
+
+use lazy_static::lazy_static;
+use x86_64::PhysAddr;
+
+use crate::mem::phys_to_virt;
+
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+const EBDA_SEGMENT_PTR: u64 = 0x40E;
+const BIOS_AREA_START: u64 = 0xE0000;
+const BIOS_AREA_END: u64 = 0x100000;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct Rsdp {
+    signature: [u8; 8],
+    checksum: u8,
+    oem_id: [u8; 6],
+    revision: u8,
+    rsdt_address: u32,
+    // ACPI 2.0+
+    length: u32,
+    xsdt_address: u64,
+    extended_checksum: u8,
+    reserved: [u8; 3],
+}
+
+/// 所有 System Description Table 的公共表头
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    pub length: u32,
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: u32,
+    pub creator_revision: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RootTable {
+    Rsdt(PhysAddr),
+    Xsdt(PhysAddr),
+}
+
+lazy_static! {
+    static ref ROOT_TABLE: Option<RootTable> = unsafe { find_rsdp() }.map(|rsdp| {
+        if rsdp.revision >= 2 && rsdp.xsdt_address != 0 {
+            RootTable::Xsdt(PhysAddr::new(rsdp.xsdt_address))
+        } else {
+            RootTable::Rsdt(PhysAddr::new(rsdp.rsdt_address as u64))
+        }
+    });
+}
+
+unsafe fn read_phys<T: Copy>(addr: PhysAddr) -> T {
+    ptr::read_unaligned(phys_to_virt(addr).as_ptr::<T>())
+}
+
+unsafe fn checksum_ok(addr: PhysAddr, len: usize) -> bool {
+    let bytes = phys_to_virt(addr).as_ptr::<u8>();
+    (0..len).fold(0u8, |sum, i| sum.wrapping_add(*bytes.add(i))) == 0
+}
+
+/// 依次扫描 EBDA 的第一个 KiB 与 BIOS 只读区,按 16 字节对齐查找 RSDP
+unsafe fn find_rsdp() -> Option<Rsdp> {
+    let ebda = (read_phys::<u16>(PhysAddr::new(EBDA_SEGMENT_PTR)) as u64) << 4;
+    let areas = [(ebda, ebda + 1024), (BIOS_AREA_START, BIOS_AREA_END)];
+    for &(start, end) in areas.iter() {
+        if start == 0 {
+            continue;
+        }
+        for addr in (start..end).step_by(16) {
+            let addr = PhysAddr::new(addr);
+            let rsdp: Rsdp = read_phys(addr);
+            if &rsdp.signature == RSDP_SIGNATURE && checksum_ok(addr, 20) {
+                return Some(rsdp);
+            }
+        }
+    }
+    None
+}
+
+/// 按签名在 RSDT/XSDT 中查找表,返回表头的物理地址
+pub fn find_table(signature: &[u8; 4]) -> Option<PhysAddr> {
+    let (root, entry_size) = match (*ROOT_TABLE)? {
+        RootTable::Rsdt(addr) => (addr, size_of::<u32>()),
+        RootTable::Xsdt(addr) => (addr, size_of::<u64>()),
+    };
+    unsafe {
+        let header: SdtHeader = read_phys(root);
+        let entries = (header.length as usize - size_of::<SdtHeader>()) / entry_size;
+        let first_entry = root + size_of::<SdtHeader>();
+        for i in 0..entries {
+            let entry_addr = first_entry + (i * entry_size) as u64;
+            let table = match entry_size {
+                4 => PhysAddr::new(read_phys::<u32>(entry_addr) as u64),
+                _ => PhysAddr::new(read_phys::<u64>(entry_addr)),
+            };
+            let table_header: SdtHeader = read_phys(table);
+            if &table_header.signature == signature && checksum_ok(table, table_header.length as usize) {
+                return Some(table);
+            }
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoApicEntry {
+    pub id: u8,
+    pub address: PhysAddr,
+    pub gsi_base: u32,
+}
+
+/// ISA IRQ 到 GSI 的重定向,flags 中包含极性与触发方式
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptSourceOverride {
+    pub source: u8,
+    pub gsi: u32,
+    pub flags: u16,
+}
+
+impl InterruptSourceOverride {
+    pub fn active_low(&self) -> bool {
+        self.flags & 0b11 == 0b11
+    }
+
+    pub fn level_triggered(&self) -> bool {
+        (self.flags >> 2) & 0b11 == 0b11
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Madt {
+    pub local_apic_address: PhysAddr,
+    pub processors: Vec<u8>,
+    pub io_apics: Vec<IoApicEntry>,
+    pub overrides: Vec<InterruptSourceOverride>,
+}
+
+impl Madt {
+    pub fn isa_override(&self, irq: u8) -> Option<&InterruptSourceOverride> {
+        self.overrides.iter().find(|o| o.source == irq)
+    }
+}
+
+const MADT_LOCAL_APIC: u8 = 0;
+const MADT_IO_APIC: u8 = 1;
+const MADT_INTERRUPT_SOURCE_OVERRIDE: u8 = 2;
+const MADT_LOCAL_APIC_ADDRESS_OVERRIDE: u8 = 5;
+
+/// 解析 MADT(签名 "APIC"),获取 LAPIC 地址、IOAPIC 列表与中断重定向
+pub fn madt() -> Option<Madt> {
+    let table = find_table(b"APIC")?;
+    unsafe {
+        let header: SdtHeader = read_phys(table);
+        let mut madt = Madt {
+            local_apic_address: PhysAddr::new(read_phys::<u32>(table + size_of::<SdtHeader>()) as u64),
+            processors: Vec::new(),
+            io_apics: Vec::new(),
+            overrides: Vec::new(),
+        };
+        // 表头之后是 LAPIC 地址(u32)与 flags(u32),再之后是变长条目
+        let end = table + header.length as u64;
+        let mut entry = table + size_of::<SdtHeader>() + 8u64;
+        while entry < end {
+            let entry_type: u8 = read_phys(entry);
+            let entry_len: u8 = read_phys(entry + 1u64);
+            if entry_len < 2 {
+                break;
+            }
+            match entry_type {
+                MADT_LOCAL_APIC => {
+                    let apic_id: u8 = read_phys(entry + 3u64);
+                    let flags: u32 = read_phys(entry + 4u64);
+                    if flags & 0b1 != 0 {
+                        madt.processors.push(apic_id);
+                    }
+                }
+                MADT_IO_APIC => madt.io_apics.push(IoApicEntry {
+                    id: read_phys(entry + 2u64),
+                    address: PhysAddr::new(read_phys::<u32>(entry + 4u64) as u64),
+                    gsi_base: read_phys(entry + 8u64),
+                }),
+                MADT_INTERRUPT_SOURCE_OVERRIDE => madt.overrides.push(InterruptSourceOverride {
+                    source: read_phys(entry + 3u64),
+                    gsi: read_phys(entry + 4u64),
+                    flags: read_phys(entry + 8u64),
+                }),
+                MADT_LOCAL_APIC_ADDRESS_OVERRIDE => {
+                    madt.local_apic_address = PhysAddr::new(read_phys(entry + 4u64));
+                }
+                _ => {}
+            }
+            entry += entry_len as u64;
+        }
+        Some(madt)
+    }
+}
+
+/// ACPI Generic Address Structure
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct GenericAddress {
+    pub address_space_id: u8,
+    pub register_bit_width: u8,
+    pub register_bit_offset: u8,
+    pub reserved: u8,
+    pub address: u64,
+}
+
+pub const ADDRESS_SPACE_SYSTEM_MEMORY: u8 = 0;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct HpetTable {
+    header: SdtHeader,
+    event_timer_block_id: u32,
+    base_address: GenericAddress,
+    hpet_number: u8,
+    minimum_tick: u16,
+    page_protection: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Hpet {
+    pub base_address: GenericAddress,
+    pub hpet_number: u8,
+    pub minimum_tick: u16,
+}
+
+/// 解析 HPET 表(签名 "HPET")
+pub fn hpet() -> Option<Hpet> {
+    let table = find_table(b"HPET")?;
+    let hpet: HpetTable = unsafe { read_phys(table) };
+    Some(Hpet {
+        base_address: hpet.base_address,
+        hpet_number: hpet.hpet_number,
+        minimum_tick: hpet.minimum_tick,
+    })
+}
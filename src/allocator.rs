@@ -1,7 +1,10 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use spin::{Mutex, MutexGuard};
 use x86_64::structures::paging::{FrameAllocator, Mapper, Page, PageTableFlags, Size4KiB};
 use x86_64::structures::paging::mapper::MapToError;
 use x86_64::structures::paging::page::PageRange;
+use x86_64::instructions::interrupts::without_interrupts;
 use x86_64::VirtAddr;
 
 // use crate::allocator::bump::BumpAllocator;
@@ -16,6 +19,9 @@ pub mod buddy;
 pub const HEAP_BOTTOM: u64 = 0x4444_4444_0000;
 pub const HEAP_SIZE: u64 = 1024 * 1024;//1MiB
 
+/// 堆初始化完成,lockdep 在此之前不做记录
+static HEAP_READY: AtomicBool = AtomicBool::new(false);
+
 pub const BUDDY_ALLOCATOR_ORDER: usize = HEAP_SIZE.trailing_zeros() as usize + 1;
 #[global_allocator]
 static GLOBAL_ALLOCATOR: Locked<BuddyAllocator<BUDDY_ALLOCATOR_ORDER>> = Locked::new(BuddyAllocator::new());
@@ -36,10 +42,30 @@ pub fn init_heap(mapper: &mut impl Mapper<Size4KiB>, frame_allocator: &mut impl
         unsafe { mapper.map_to(page, frame, flags, frame_allocator)?.flush(); }
     }
     unsafe {
-        init_global_allocator(HEAP_BOTTOM, HEAP_SIZE)
+        init_global_allocator(HEAP_BOTTOM, HEAP_SIZE)?;
     }
+    HEAP_READY.store(true, Ordering::Release);
+    Ok(())
+}
+
+pub fn is_initialized() -> bool {
+    HEAP_READY.load(Ordering::Acquire)
+}
+
+/// 堆的使用情况,按伙伴块大小计,空闲部分可能因碎片无法整块分配
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    pub total: usize,
+    pub used: usize,
+    pub free: usize,
+}
+
+pub fn stats() -> HeapStats {
+    let used = without_interrupts(|| GLOBAL_ALLOCATOR.lock().allocated());
+    HeapStats { total: HEAP_SIZE as usize, used, free: (HEAP_SIZE as usize).saturating_sub(used) }
 }
 
+/// 全局分配器的锁不经过 lockdep:lockdep 记录时本身要分配内存
 pub struct Locked<A> {
     inner: Mutex<A>,
 }
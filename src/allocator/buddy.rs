@@ -4,8 +4,9 @@ use core::mem::size_of;
 use core::ptr;
 use core::ptr::NonNull;
 
+use x86_64::instructions::interrupts::without_interrupts;
+
 use crate::allocator::Locked;
-use crate::println;
 
 #[derive(Debug, Copy, Clone)]
 struct LinkedList {
@@ -62,12 +63,15 @@ unsafe impl Send for LinkedList {}
 
 pub struct BuddyAllocator<const ORDER: usize> {
     free_lists: [LinkedList; ORDER],
+    /// 已分配出去的字节数,按块大小计
+    allocated: usize,
 }
 
 impl<const ORDER: usize> BuddyAllocator<ORDER> {
     pub const fn new() -> Self {
         BuddyAllocator {
-            free_lists: [LinkedList::new(); ORDER]
+            free_lists: [LinkedList::new(); ORDER],
+            allocated: 0,
         }
     }
 
@@ -104,13 +108,13 @@ impl<const ORDER: usize> BuddyAllocator<ORDER> {
                 }
                 if let Some(block) = self.free_lists[bucket].pop() {
                     if let Some(result) = NonNull::new(block as *mut u8) {
+                        self.allocated += size;
                         return Ok(result);
                     }
                 }
                 break 'outer;
             }
         }
-        println!("alloc block in bucket {} failed!", bucket);
         return Err(());
     }
 
@@ -118,6 +122,7 @@ impl<const ORDER: usize> BuddyAllocator<ORDER> {
         let mut mut_ptr = ptr;
         let size = max(max(layout.size().next_power_of_two(), LAYOUT), layout.align());
         let mut bucket = size.trailing_zeros() as usize;
+        self.allocated -= size;
         for i in bucket..self.free_lists.len() {
             bucket = i;
             let mut block = self.free_lists[i].head;
@@ -139,10 +144,13 @@ impl<const ORDER: usize> BuddyAllocator<ORDER> {
             }
         }
         self.free_lists[bucket].push(mut_ptr);
-        println!("push => {} : {:x}", bucket, mut_ptr as usize);
         return;
     }
 
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
     fn prev_power_of_two(num: usize) -> usize {
         1 << (8 * size_of::<usize>() - num.leading_zeros() as usize - 1)
     }
@@ -150,14 +158,14 @@ impl<const ORDER: usize> BuddyAllocator<ORDER> {
 
 unsafe impl<const ORDER: usize> GlobalAlloc for Locked<BuddyAllocator<ORDER>> {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        match self.lock().alloc(layout) {
+        // 关中断持锁: softirq 中的定时器回调同样会分配内存
+        match without_interrupts(|| self.lock().alloc(layout)) {
             Ok(target) => target.as_ptr(),
             Err(()) => ptr::null_mut()
         }
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        self.lock().dealloc(ptr as usize as *mut usize, layout);
-        println!("dealloc => {:x} ({})", ptr as usize, layout.size());
+        without_interrupts(|| self.lock().dealloc(ptr as usize as *mut usize, layout));
     }
 }
\ No newline at end of file
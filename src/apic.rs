@@ -0,0 +1,72 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use x86_64::instructions::interrupts;
+
+use crate::idt::{InterruptIndex, PICS};
+use crate::{acpi, println, time};
+use crate::time::ClockSource;
+
+pub mod lapic;
+pub mod ioapic;
+
+pub const ISA_TIMER_IRQ: u8 = 0;
+pub const ISA_KEYBOARD_IRQ: u8 = 1;
+pub const ISA_SERIAL_IRQ: u8 = 4;
+pub const ISA_RTC_IRQ: u8 = 8;
+
+static APIC_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 中断是否已切换到 APIC,否则仍由 8259 PIC 处理
+pub fn is_enabled() -> bool {
+    APIC_ENABLED.load(Ordering::SeqCst)
+}
+
+/// CPUID.01H:EDX[9]
+#[allow(unused_unsafe)]
+pub fn is_supported() -> bool {
+    let cpuid = unsafe { core::arch::x86_64::__cpuid(1) };
+    cpuid.edx & (1 << 9) != 0
+}
+
+/// 检测并启用 LAPIC/IOAPIC,禁用 8259;不支持时保留 PIC 作为回退
+/// 需在 `mem::init` 与堆初始化之后调用
+pub fn init() {
+    println!("Init APIC ...");
+    if !is_supported() {
+        println!("APIC not supported, fallback to 8259 PIC");
+        return;
+    }
+    let madt = match acpi::madt() {
+        Some(madt) if !madt.io_apics.is_empty() => madt,
+        _ => {
+            println!("MADT/IOAPIC not found, fallback to 8259 PIC");
+            return;
+        }
+    };
+    interrupts::without_interrupts(|| {
+        if let Err(err) = unsafe { lapic::init(madt.local_apic_address) } {
+            println!("Map LAPIC failed, {:?}, fallback to 8259 PIC", err);
+            return;
+        }
+        if let Err(err) = unsafe { ioapic::init(&madt) } {
+            println!("Map IOAPIC failed, {:?}, fallback to 8259 PIC", err);
+            unsafe { lapic::disable(); }
+            return;
+        }
+        unsafe { PICS.lock().disable(); }
+        let apic_id = lapic::id();
+        // PIT 由 LAPIC timer 取代,IRQ0 保持屏蔽
+        ioapic::route_isa_irq(&madt, ISA_TIMER_IRQ, InterruptIndex::Timer.as_u8(), apic_id, true);
+        ioapic::route_isa_irq(&madt, ISA_KEYBOARD_IRQ, InterruptIndex::Keyboard.as_u8(), apic_id, false);
+        ioapic::route_isa_irq(&madt, ISA_SERIAL_IRQ, InterruptIndex::Serial.as_u8(), apic_id, false);
+        // RTC 周期中断按需开启
+        ioapic::route_isa_irq(&madt, ISA_RTC_IRQ, InterruptIndex::Rtc.as_u8(), apic_id, true);
+        APIC_ENABLED.store(true, Ordering::SeqCst);
+        let hz = time::tick_hz();
+        unsafe { lapic::start_periodic_timer(InterruptIndex::Timer.as_u8(), hz); }
+        time::set_clock_source(ClockSource::LapicTimer, hz);
+    });
+    if is_enabled() {
+        println!("APIC enabled, LAPIC id {}, {} IOAPIC(s)", lapic::id(), madt.io_apics.len());
+    }
+}
@@ -0,0 +1,124 @@
+use alloc::vec::Vec;
+
+use x86_64::VirtAddr;
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::structures::paging::Size4KiB;
+
+use crate::acpi::Madt;
+use crate::mem;
+use crate::sync::SpinLock;
+
+const IOREGSEL: u64 = 0x00;
+const IOWIN: u64 = 0x10;
+
+const REG_VERSION: u32 = 0x01;
+const REG_REDIRECTION_TABLE: u32 = 0x10;
+
+const REDIRECTION_ACTIVE_LOW: u64 = 1 << 13;
+const REDIRECTION_LEVEL_TRIGGERED: u64 = 1 << 15;
+const REDIRECTION_MASKED: u64 = 1 << 16;
+
+struct IoApic {
+    base: VirtAddr,
+    gsi_base: u32,
+    redirection_entries: u32,
+}
+
+impl IoApic {
+    unsafe fn read(&self, reg: u32) -> u32 {
+        core::ptr::write_volatile((self.base.as_u64() + IOREGSEL) as *mut u32, reg);
+        core::ptr::read_volatile((self.base.as_u64() + IOWIN) as *const u32)
+    }
+
+    unsafe fn write(&self, reg: u32, value: u32) {
+        core::ptr::write_volatile((self.base.as_u64() + IOREGSEL) as *mut u32, reg);
+        core::ptr::write_volatile((self.base.as_u64() + IOWIN) as *mut u32, value);
+    }
+
+    fn handles(&self, gsi: u32) -> bool {
+        gsi >= self.gsi_base && gsi < self.gsi_base + self.redirection_entries
+    }
+
+    unsafe fn read_redirection(&self, gsi: u32) -> u64 {
+        let reg = REG_REDIRECTION_TABLE + (gsi - self.gsi_base) * 2;
+        self.read(reg) as u64 | (self.read(reg + 1) as u64) << 32
+    }
+
+    unsafe fn write_redirection(&self, gsi: u32, entry: u64) {
+        let reg = REG_REDIRECTION_TABLE + (gsi - self.gsi_base) * 2;
+        self.write(reg, entry as u32);
+        self.write(reg + 1, (entry >> 32) as u32);
+    }
+}
+
+static IO_APICS: SpinLock<Vec<IoApic>> = SpinLock::new(Vec::new());
+
+pub unsafe fn init(madt: &Madt) -> Result<(), MapToError<Size4KiB>> {
+    let mut io_apics = IO_APICS.lock();
+    for entry in madt.io_apics.iter() {
+        let base = mem::map_mmio(entry.address, 4096)?;
+        let mut io_apic = IoApic { base, gsi_base: entry.gsi_base, redirection_entries: 0 };
+        io_apic.redirection_entries = ((io_apic.read(REG_VERSION) >> 16) & 0xFF) + 1;
+        // 先屏蔽所有输入
+        for gsi in io_apic.gsi_base..io_apic.gsi_base + io_apic.redirection_entries {
+            io_apic.write_redirection(gsi, REDIRECTION_MASKED);
+        }
+        io_apics.push(io_apic);
+    }
+    Ok(())
+}
+
+static ISA_IRQ_GSI: SpinLock<[Option<u32>; 16]> = SpinLock::new([None; 16]);
+
+/// 按 MADT 的 Interrupt Source Override 将 ISA IRQ 转换为 GSI 后写入重定向表
+pub fn route_isa_irq(madt: &Madt, irq: u8, vector: u8, apic_id: u8, masked: bool) -> Option<u32> {
+    let (gsi, active_low, level_triggered) = match madt.isa_override(irq) {
+        Some(iso) => (iso.gsi, iso.active_low(), iso.level_triggered()),
+        // ISA 默认:高电平有效,边沿触发
+        None => (irq as u32, false, false),
+    };
+    if !route_gsi(gsi, vector, apic_id, active_low, level_triggered, masked) {
+        return None;
+    }
+    ISA_IRQ_GSI.lock()[irq as usize] = Some(gsi);
+    Some(gsi)
+}
+
+pub fn isa_irq_gsi(irq: u8) -> Option<u32> {
+    ISA_IRQ_GSI.lock().get(irq as usize).copied().flatten()
+}
+
+pub fn route_gsi(gsi: u32, vector: u8, apic_id: u8, active_low: bool, level_triggered: bool, masked: bool) -> bool {
+    let mut entry = vector as u64 | (apic_id as u64) << 56;
+    if active_low {
+        entry |= REDIRECTION_ACTIVE_LOW;
+    }
+    if level_triggered {
+        entry |= REDIRECTION_LEVEL_TRIGGERED;
+    }
+    if masked {
+        entry |= REDIRECTION_MASKED;
+    }
+    let io_apics = IO_APICS.lock();
+    match io_apics.iter().find(|io_apic| io_apic.handles(gsi)) {
+        Some(io_apic) => {
+            unsafe { io_apic.write_redirection(gsi, entry); }
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn set_masked(gsi: u32, masked: bool) {
+    let io_apics = IO_APICS.lock();
+    if let Some(io_apic) = io_apics.iter().find(|io_apic| io_apic.handles(gsi)) {
+        unsafe {
+            let entry = io_apic.read_redirection(gsi);
+            let entry = match masked {
+                true => entry | REDIRECTION_MASKED,
+                false => entry & !REDIRECTION_MASKED,
+            };
+            io_apic.write_redirection(gsi, entry);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::registers::model_specific::Msr;
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::structures::paging::Size4KiB;
+
+use crate::idt::InterruptIndex;
+use crate::mem;
+use crate::time::pit;
+
+const IA32_APIC_BASE_MSR: u32 = 0x1B;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+
+const REG_ID: u32 = 0x20;
+const REG_TPR: u32 = 0x80;
+const REG_EOI: u32 = 0xB0;
+const REG_SVR: u32 = 0xF0;
+const REG_ISR: u32 = 0x100;
+const REG_LVT_TIMER: u32 = 0x320;
+const REG_LVT_LINT0: u32 = 0x350;
+const REG_LVT_LINT1: u32 = 0x360;
+const REG_LVT_ERROR: u32 = 0x370;
+const REG_TIMER_INITIAL_COUNT: u32 = 0x380;
+const REG_TIMER_CURRENT_COUNT: u32 = 0x390;
+const REG_TIMER_DIVIDE: u32 = 0x3E0;
+
+const SVR_APIC_ENABLE: u32 = 1 << 8;
+const LVT_MASKED: u32 = 1 << 16;
+const LVT_TIMER_PERIODIC: u32 = 1 << 17;
+const LVT_DELIVERY_NMI: u32 = 0b100 << 8;
+const TIMER_DIVIDE_BY_16: u32 = 0b0011;
+
+const CALIBRATE_MS: u32 = 10;
+
+static LAPIC_BASE: AtomicU64 = AtomicU64::new(0);
+static TIMER_TICKS_PER_MS: AtomicU32 = AtomicU32::new(0);
+
+unsafe fn read(reg: u32) -> u32 {
+    let base = LAPIC_BASE.load(Ordering::SeqCst);
+    core::ptr::read_volatile((base + reg as u64) as *const u32)
+}
+
+unsafe fn write(reg: u32, value: u32) {
+    let base = LAPIC_BASE.load(Ordering::SeqCst);
+    core::ptr::write_volatile((base + reg as u64) as *mut u32, value);
+}
+
+pub unsafe fn init(default_base: PhysAddr) -> Result<VirtAddr, MapToError<Size4KiB>> {
+    let mut msr = Msr::new(IA32_APIC_BASE_MSR);
+    let apic_base = msr.read();
+    let phys = match apic_base & 0x000F_FFFF_FFFF_F000 {
+        0 => default_base,
+        addr => PhysAddr::new(addr),
+    };
+    let virt = mem::map_mmio(phys, 4096)?;
+    LAPIC_BASE.store(virt.as_u64(), Ordering::SeqCst);
+    msr.write(apic_base | APIC_BASE_ENABLE);
+
+    write(REG_TPR, 0);
+    // LINT0 原本接 8259 的 ExtINT,PIC 禁用后屏蔽
+    write(REG_LVT_LINT0, LVT_MASKED);
+    write(REG_LVT_LINT1, LVT_DELIVERY_NMI);
+    write(REG_LVT_ERROR, LVT_MASKED);
+    write(REG_LVT_TIMER, LVT_MASKED);
+    write(REG_SVR, SVR_APIC_ENABLE | InterruptIndex::ApicSpurious.as_u8() as u32);
+    end_of_interrupt();
+    Ok(virt)
+}
+
+pub unsafe fn disable() {
+    write(REG_SVR, read(REG_SVR) & !SVR_APIC_ENABLE);
+    let mut msr = Msr::new(IA32_APIC_BASE_MSR);
+    let apic_base = msr.read();
+    msr.write(apic_base & !APIC_BASE_ENABLE);
+}
+
+pub fn id() -> u8 {
+    unsafe { (read(REG_ID) >> 24) as u8 }
+}
+
+pub fn end_of_interrupt() {
+    unsafe { write(REG_EOI, 0); }
+}
+
+/// ISR 共 256 位,分布在 8 个间隔 0x10 的 32 位寄存器中
+pub fn is_in_service(vector: u8) -> bool {
+    let reg = REG_ISR + (vector as u32 / 32) * 0x10;
+    unsafe { read(reg) & (1 << (vector as u32 % 32)) != 0 }
+}
+
+/// 以 PIT channel 2 单次计数 `CALIBRATE_MS` 毫秒,得到 LAPIC timer 每毫秒的计数
+unsafe fn calibrate_timer() -> u32 {
+    write(REG_TIMER_DIVIDE, TIMER_DIVIDE_BY_16);
+    pit::start_oneshot(CALIBRATE_MS);
+    write(REG_TIMER_INITIAL_COUNT, u32::MAX);
+    while !pit::oneshot_expired() {
+        core::hint::spin_loop();
+    }
+    let elapsed = u32::MAX - read(REG_TIMER_CURRENT_COUNT);
+    write(REG_TIMER_INITIAL_COUNT, 0);
+    elapsed / CALIBRATE_MS
+}
+
+pub unsafe fn start_periodic_timer(vector: u8, hz: u32) {
+    let mut ticks_per_ms = TIMER_TICKS_PER_MS.load(Ordering::SeqCst);
+    if ticks_per_ms == 0 {
+        ticks_per_ms = calibrate_timer();
+        TIMER_TICKS_PER_MS.store(ticks_per_ms, Ordering::SeqCst);
+    }
+    write(REG_TIMER_DIVIDE, TIMER_DIVIDE_BY_16);
+    write(REG_LVT_TIMER, vector as u32 | LVT_TIMER_PERIODIC);
+    write(REG_TIMER_INITIAL_COUNT, ticks_per_ms * 1000 / hz);
+}
+
+pub unsafe fn stop_timer() {
+    write(REG_LVT_TIMER, LVT_MASKED);
+    write(REG_TIMER_INITIAL_COUNT, 0);
+}
@@ -0,0 +1,143 @@
+use core::mem::size_of;
+
+pub use loader::{load, LoadedImage};
+
+pub mod loader;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u8 = 1;
+pub const ET_EXEC: u16 = 2;
+pub const EM_X86_64: u16 = 62;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_INTERP: u32 = 3;
+pub const PT_PHDR: u32 = 6;
+
+pub const PF_X: u32 = 0x1;
+pub const PF_W: u32 = 0x2;
+pub const PF_R: u32 = 0x4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    TooShort,
+    BadMagic,
+    NotElf64,
+    NotLittleEndian,
+    BadVersion,
+    /// 目前只支持静态链接的可执行文件(ET_EXEC)
+    UnsupportedType(u16),
+    UnsupportedMachine(u16),
+    /// 需要动态链接器
+    Interpreter,
+    BadProgramHeaders,
+    SegmentOutOfFile { offset: u64, size: u64 },
+    FileSizeExceedsMemSize { vaddr: u64 },
+    SegmentOutOfRange { vaddr: u64, size: u64 },
+    NoLoadableSegments,
+    EntryOutOfRange(u64),
+    ArgumentsTooLarge,
+    OutOfMemory,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ElfHeader {
+    pub ident: [u8; 16],
+    pub elf_type: u16,
+    pub machine: u16,
+    pub version: u32,
+    pub entry: u64,
+    pub phoff: u64,
+    pub shoff: u64,
+    pub flags: u32,
+    pub ehsize: u16,
+    pub phentsize: u16,
+    pub phnum: u16,
+    pub shentsize: u16,
+    pub shnum: u16,
+    pub shstrndx: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ProgramHeader {
+    pub p_type: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub paddr: u64,
+    pub filesz: u64,
+    pub memsz: u64,
+    pub align: u64,
+}
+
+/// 已校验过头部的 ELF64 文件
+pub struct ElfFile<'a> {
+    data: &'a [u8],
+    header: ElfHeader,
+}
+
+impl<'a> ElfFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        if data.len() < size_of::<ElfHeader>() {
+            return Err(ElfError::TooShort);
+        }
+        let header: ElfHeader = unsafe { core::ptr::read_unaligned(data.as_ptr() as *const ElfHeader) };
+        if header.ident[..4] != ELF_MAGIC {
+            return Err(ElfError::BadMagic);
+        }
+        if header.ident[4] != ELFCLASS64 {
+            return Err(ElfError::NotElf64);
+        }
+        if header.ident[5] != ELFDATA2LSB {
+            return Err(ElfError::NotLittleEndian);
+        }
+        if header.ident[6] != EV_CURRENT || header.version != EV_CURRENT as u32 {
+            return Err(ElfError::BadVersion);
+        }
+        if header.elf_type != ET_EXEC {
+            return Err(ElfError::UnsupportedType(header.elf_type));
+        }
+        if header.machine != EM_X86_64 {
+            return Err(ElfError::UnsupportedMachine(header.machine));
+        }
+        let table_size = header.phentsize as u64 * header.phnum as u64;
+        if header.phentsize as usize != size_of::<ProgramHeader>()
+            || header.phoff.checked_add(table_size).map_or(true, |end| end > data.len() as u64) {
+            return Err(ElfError::BadProgramHeaders);
+        }
+        let elf = ElfFile { data, header };
+        for ph in elf.program_headers() {
+            let end = ph.offset.checked_add(ph.filesz);
+            if ph.p_type == PT_LOAD && end.map_or(true, |end| end > data.len() as u64) {
+                return Err(ElfError::SegmentOutOfFile { offset: ph.offset, size: ph.filesz });
+            }
+            if ph.p_type == PT_INTERP {
+                return Err(ElfError::Interpreter);
+            }
+        }
+        Ok(elf)
+    }
+
+    pub fn header(&self) -> &ElfHeader {
+        &self.header
+    }
+
+    pub fn entry(&self) -> u64 {
+        self.header.entry
+    }
+
+    pub fn program_headers(&self) -> impl Iterator<Item=ProgramHeader> + 'a {
+        let (data, phoff, phnum) = (self.data, self.header.phoff as usize, self.header.phnum as usize);
+        (0..phnum).map(move |i| unsafe {
+            core::ptr::read_unaligned(data.as_ptr().add(phoff + i * size_of::<ProgramHeader>()) as *const ProgramHeader)
+        })
+    }
+
+    /// 段在文件中的内容
+    pub fn segment_data(&self, ph: &ProgramHeader) -> &'a [u8] {
+        &self.data[ph.offset as usize..(ph.offset + ph.filesz) as usize]
+    }
+}
@@ -0,0 +1,135 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use x86_64::structures::paging::PageTableFlags;
+use x86_64::VirtAddr;
+
+use crate::elf::{ElfError, ElfFile, PF_W, PF_X, PT_LOAD, PT_PHDR, ProgramHeader};
+use crate::mem::address_space::{AddressSpace, USER_MMAP_BASE, USER_REGION_BOTTOM, USER_STACK_SIZE};
+
+const AT_NULL: u64 = 0;
+const AT_PHDR: u64 = 3;
+const AT_PHENT: u64 = 4;
+const AT_PHNUM: u64 = 5;
+const AT_PAGESZ: u64 = 6;
+const AT_ENTRY: u64 = 9;
+
+/// 参数与环境变量字符串最多占用栈的四分之一
+const MAX_ARGS_SIZE: usize = (USER_STACK_SIZE / 4) as usize;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoadedImage {
+    pub entry: VirtAddr,
+    /// 指向 argc 的用户栈指针
+    pub stack_pointer: VirtAddr,
+    /// 镜像末尾,可作为堆的起点
+    pub image_end: VirtAddr,
+}
+
+fn segment_flags(ph: &ProgramHeader) -> PageTableFlags {
+    let mut flags = PageTableFlags::empty();
+    if ph.flags & PF_W != 0 {
+        flags |= PageTableFlags::WRITABLE;
+    }
+    if ph.flags & PF_X == 0 {
+        flags |= PageTableFlags::NO_EXECUTE;
+    }
+    flags
+}
+
+/// 将 PT_LOAD 段映射到 `space`,建立用户栈并按 System V ABI 放入 argv/envp/auxv
+pub fn load(space: &mut AddressSpace, elf: &ElfFile, argv: &[&str], envp: &[&str]) -> Result<LoadedImage, ElfError> {
+    let mut image_end = 0;
+    let mut phdr = None;
+    let segments: Vec<ProgramHeader> = elf.program_headers().filter(|ph| ph.p_type == PT_LOAD).collect();
+    if segments.is_empty() {
+        return Err(ElfError::NoLoadableSegments);
+    }
+    for ph in &segments {
+        if ph.filesz > ph.memsz {
+            return Err(ElfError::FileSizeExceedsMemSize { vaddr: ph.vaddr });
+        }
+        let end = ph.vaddr.checked_add(ph.memsz);
+        if ph.vaddr < USER_REGION_BOTTOM || end.map_or(true, |end| end > USER_MMAP_BASE) {
+            return Err(ElfError::SegmentOutOfRange { vaddr: ph.vaddr, size: ph.memsz });
+        }
+    }
+    for ph in &segments {
+        if ph.memsz == 0 {
+            continue;
+        }
+        space.map(VirtAddr::new(ph.vaddr), ph.memsz, segment_flags(ph)).map_err(|_| ElfError::OutOfMemory)?;
+        // 超出 filesz 的部分(.bss)在映射时已清零
+        if !space.write(VirtAddr::new(ph.vaddr), elf.segment_data(ph)) {
+            return Err(ElfError::OutOfMemory);
+        }
+        image_end = image_end.max(ph.vaddr + ph.memsz);
+        let phoff = elf.header().phoff;
+        if ph.offset <= phoff && phoff < ph.offset + ph.filesz {
+            phdr = Some(ph.vaddr + (phoff - ph.offset));
+        }
+    }
+    if let Some(ph) = elf.program_headers().find(|ph| ph.p_type == PT_PHDR) {
+        phdr = Some(ph.vaddr);
+    }
+    let entry = elf.entry();
+    if !segments.iter().any(|ph| ph.flags & PF_X != 0 && (ph.vaddr..ph.vaddr + ph.memsz).contains(&entry)) {
+        return Err(ElfError::EntryOutOfRange(entry));
+    }
+
+    let mut auxv = Vec::new();
+    if let Some(phdr) = phdr {
+        auxv.push((AT_PHDR, phdr));
+    }
+    auxv.push((AT_PHENT, size_of::<ProgramHeader>() as u64));
+    auxv.push((AT_PHNUM, elf.header().phnum as u64));
+    auxv.push((AT_PAGESZ, 4096));
+    auxv.push((AT_ENTRY, entry));
+    let stack_top = space.map_stack().map_err(|_| ElfError::OutOfMemory)?;
+    let stack_pointer = setup_stack(space, stack_top, argv, envp, &auxv)?;
+    Ok(LoadedImage {
+        entry: VirtAddr::new(entry),
+        stack_pointer,
+        image_end: VirtAddr::new(image_end),
+    })
+}
+
+/// 初始栈布局(由低到高):argc, argv[], NULL, envp[], NULL, auxv[], AT_NULL, 字符串
+pub fn setup_stack(space: &mut AddressSpace, stack_top: VirtAddr, argv: &[&str], envp: &[&str], auxv: &[(u64, u64)])
+                   -> Result<VirtAddr, ElfError> {
+    let strings_size: usize = argv.iter().chain(envp.iter()).map(|s| s.len() + 1).sum();
+    if strings_size > MAX_ARGS_SIZE {
+        return Err(ElfError::ArgumentsTooLarge);
+    }
+    let mut cursor = stack_top.as_u64();
+    let mut push_str = |space: &mut AddressSpace, s: &str| {
+        cursor -= s.len() as u64 + 1;
+        space.write(VirtAddr::new(cursor), s.as_bytes());
+        space.write(VirtAddr::new(cursor + s.len() as u64), &[0]);
+        cursor
+    };
+    let argv_ptrs: Vec<u64> = argv.iter().map(|s| push_str(space, s)).collect();
+    let envp_ptrs: Vec<u64> = envp.iter().map(|s| push_str(space, s)).collect();
+
+    let mut words: Vec<u64> = Vec::new();
+    words.push(argv.len() as u64);
+    words.extend(argv_ptrs);
+    words.push(0);
+    words.extend(envp_ptrs);
+    words.push(0);
+    for &(key, value) in auxv {
+        words.push(key);
+        words.push(value);
+    }
+    words.push(AT_NULL);
+    words.push(0);
+
+    // 进入程序时 rsp 指向 argc 且 16 字节对齐
+    let table_size = (words.len() * size_of::<u64>()) as u64;
+    let stack_pointer = (cursor - table_size) & !0xf;
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    if !space.write(VirtAddr::new(stack_pointer), &bytes) {
+        return Err(ElfError::ArgumentsTooLarge);
+    }
+    Ok(VirtAddr::new(stack_pointer))
+}
@@ -0,0 +1,123 @@
+//! 虚拟文件系统
+//!
+//! 具体文件系统实现 [`Inode`] 与 [`FileSystem`],挂载到 [`mount`] 表后即可按路径访问。
+//! 这里的路径都按绝对路径处理,相对路径由调用者先与工作目录拼接;打开得到的 [`OpenFile`] 可放进文件描述符表。
+//! [`init`] 以 [`ramfs`] 作为根文件系统,并在 `/dev` 挂载 [`devfs`]。
+
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use crate::println;
+use crate::fs::devfs::DevFs;
+use crate::fs::ramfs::RamFs;
+use crate::syscall::Errno;
+
+pub use file::{OpenFile, OpenFlags};
+pub use inode::{DirEntry, FileSystem, Inode, InodeKind, Metadata};
+pub use mount::{mount, mounts, unmount};
+
+pub mod inode;
+pub mod path;
+pub mod mount;
+pub mod file;
+pub mod devfs;
+pub mod ramfs;
+
+/// 挂载根文件系统,需在堆初始化之后调用
+pub fn init() {
+    println!("Init fs ...");
+    let root = RamFs::new();
+    println!("Root ramfs, {} KiB", root.capacity() / 1024);
+    mount("/", Arc::new(root)).expect("mount root failed");
+    mkdir("/dev").expect("create /dev failed");
+    mount("/dev", Arc::new(DevFs::new())).expect("mount devfs failed");
+    mkdir("/tmp").expect("create /tmp failed");
+}
+
+/// 按 `flags` 打开文件,带 `CREATE` 时不存在的文件被创建
+pub fn open(path: &str, flags: OpenFlags) -> Result<Arc<OpenFile>, Errno> {
+    let follow = !flags.contains(OpenFlags::NO_FOLLOW);
+    let resolved = match path::resolve(path, follow) {
+        Ok(_) if flags.contains(OpenFlags::CREATE | OpenFlags::EXCLUSIVE) => return Err(Errno::EEXIST),
+        Ok(resolved) => resolved,
+        Err(Errno::ENOENT) if flags.contains(OpenFlags::CREATE) => {
+            let (parent, name) = path::resolve_parent(path)?;
+            let inode = parent.inode.create(&name, InodeKind::File)?;
+            path::Resolved { inode, path: path::join(&parent.path, &name) }
+        }
+        Err(err) => return Err(err),
+    };
+    match resolved.inode.metadata().kind {
+        InodeKind::Symlink => return Err(Errno::ELOOP),
+        InodeKind::Directory if flags.writable() => return Err(Errno::EISDIR),
+        InodeKind::Directory => {}
+        _ if flags.contains(OpenFlags::DIRECTORY) => return Err(Errno::ENOTDIR),
+        InodeKind::File if flags.contains(OpenFlags::TRUNCATE) && flags.writable() => resolved.inode.truncate(0)?,
+        _ => {}
+    }
+    Ok(Arc::new(OpenFile::new(resolved.inode, resolved.path, flags)))
+}
+
+/// `follow` 为 false 时返回符号链接本身的信息
+pub fn stat(path: &str, follow: bool) -> Result<Metadata, Errno> {
+    Ok(path::resolve(path, follow)?.inode.metadata())
+}
+
+pub fn mkdir(path: &str) -> Result<(), Errno> {
+    let (parent, name) = path::resolve_parent(path)?;
+    parent.inode.create(&name, InodeKind::Directory).map(|_| ())
+}
+
+/// 删除文件或符号链接本身
+pub fn unlink(path: &str) -> Result<(), Errno> {
+    let (parent, name) = path::resolve_parent(path)?;
+    parent.inode.unlink(&name)
+}
+
+/// 删除空目录,挂载点不能删除
+pub fn rmdir(path: &str) -> Result<(), Errno> {
+    let (parent, name) = path::resolve_parent(path)?;
+    if mount::covering(&path::join(&parent.path, &name)).is_some() {
+        return Err(Errno::EBUSY);
+    }
+    parent.inode.rmdir(&name)
+}
+
+/// 改名或移动,目录不能移进自己的子目录;挂载点及包含挂载点的目录不能移动或被覆盖
+pub fn rename(old: &str, new: &str) -> Result<(), Errno> {
+    let (old_parent, old_name) = path::resolve_parent(old)?;
+    let (new_parent, new_name) = path::resolve_parent(new)?;
+    let old_path = path::join(&old_parent.path, &old_name);
+    let new_path = path::join(&new_parent.path, &new_name);
+    if old_path == new_path {
+        return Ok(());
+    }
+    if path::is_ancestor(&old_path, &new_path) {
+        return Err(Errno::EINVAL);
+    }
+    if mount::is_busy(&old_path) || mount::is_busy(&new_path) {
+        return Err(Errno::EBUSY);
+    }
+    old_parent.inode.rename(&old_name, &new_parent.inode, &new_name)
+}
+
+/// 在 `path` 创建指向 `target` 的符号链接,`target` 不必存在
+pub fn symlink(target: &str, path: &str) -> Result<(), Errno> {
+    if target.is_empty() || target.len() > path::PATH_MAX {
+        return Err(Errno::EINVAL);
+    }
+    let (parent, name) = path::resolve_parent(path)?;
+    parent.inode.symlink(&name, target).map(|_| ())
+}
+
+pub fn read_link(path: &str) -> Result<String, Errno> {
+    path::resolve(path, false)?.inode.read_link()
+}
+
+pub fn truncate(path: &str, size: u64) -> Result<(), Errno> {
+    let inode = path::resolve(path, true)?.inode;
+    match inode.metadata().kind {
+        InodeKind::Directory => Err(Errno::EISDIR),
+        _ => inode.truncate(size),
+    }
+}
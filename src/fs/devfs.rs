@@ -0,0 +1,183 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::any::Any;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::fs::inode::{DirEntry, FileSystem, Inode, InodeKind, Metadata};
+use crate::process::fd::{Console, FileObject};
+use crate::sync::SpinLock;
+use crate::syscall::Errno;
+use crate::time;
+
+/// 读到文件结束,写入的数据被丢弃
+pub struct Null;
+
+impl FileObject for Null {
+    fn type_name(&self) -> &'static str {
+        "null"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn read(&self, _buf: &mut [u8]) -> Result<usize, Errno> {
+        Ok(0)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, Errno> {
+        Ok(buf.len())
+    }
+}
+
+/// 读出全零,写入的数据被丢弃
+pub struct Zero;
+
+impl FileObject for Zero {
+    fn type_name(&self) -> &'static str {
+        "zero"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Errno> {
+        buf.fill(0);
+        Ok(buf.len())
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, Errno> {
+        Ok(buf.len())
+    }
+}
+
+/// 设备文件系统:只有一层目录,每一项把读写转给一个设备对象
+pub struct DevFs {
+    root: Arc<DevDir>,
+}
+
+struct DevDir {
+    devices: SpinLock<BTreeMap<String, Arc<DevNode>>>,
+    next_ino: AtomicU64,
+    created: u64,
+}
+
+struct DevNode {
+    ino: u64,
+    device: Arc<dyn FileObject>,
+    created: u64,
+}
+
+impl DevFs {
+    /// 预置 null、zero 与 console
+    pub fn new() -> Self {
+        let fs = DevFs {
+            root: Arc::new(DevDir {
+                devices: SpinLock::new(BTreeMap::new()),
+                next_ino: AtomicU64::new(2),
+                created: time::wall_clock().timestamp,
+            }),
+        };
+        for (name, device) in [("null", Arc::new(Null) as Arc<dyn FileObject>), ("zero", Arc::new(Zero)), ("console", Arc::new(Console))] {
+            fs.register(name, device).expect("duplicate device");
+        }
+        fs
+    }
+
+    /// 添加设备节点,同名节点已存在时返回 EEXIST
+    pub fn register(&self, name: &str, device: Arc<dyn FileObject>) -> Result<(), Errno> {
+        if name.is_empty() || name.contains('/') {
+            return Err(Errno::EINVAL);
+        }
+        let mut devices = self.root.devices.lock();
+        if devices.contains_key(name) {
+            return Err(Errno::EEXIST);
+        }
+        let ino = self.root.next_ino.fetch_add(1, Ordering::Relaxed);
+        devices.insert(String::from(name), Arc::new(DevNode { ino, device, created: time::wall_clock().timestamp }));
+        Ok(())
+    }
+}
+
+impl Default for DevFs {
+    fn default() -> Self {
+        DevFs::new()
+    }
+}
+
+impl FileSystem for DevFs {
+    fn name(&self) -> &'static str {
+        "devfs"
+    }
+
+    fn root(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+impl Inode for DevDir {
+    fn metadata(&self) -> Metadata {
+        let created = self.created;
+        Metadata { ino: 1, kind: InodeKind::Directory, size: 0, nlink: 2, atime: created, mtime: created, ctime: created }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, Errno> {
+        let node = self.devices.lock().get(name).cloned().ok_or(Errno::ENOENT)?;
+        Ok(node)
+    }
+
+    fn create(&self, _name: &str, _kind: InodeKind) -> Result<Arc<dyn Inode>, Errno> {
+        Err(Errno::EPERM)
+    }
+
+    fn symlink(&self, _name: &str, _target: &str) -> Result<Arc<dyn Inode>, Errno> {
+        Err(Errno::EPERM)
+    }
+
+    fn unlink(&self, _name: &str) -> Result<(), Errno> {
+        Err(Errno::EPERM)
+    }
+
+    fn rmdir(&self, _name: &str) -> Result<(), Errno> {
+        Err(Errno::EPERM)
+    }
+
+    fn rename(&self, _name: &str, _target: &Arc<dyn Inode>, _new_name: &str) -> Result<(), Errno> {
+        Err(Errno::EPERM)
+    }
+
+    fn read_dir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        Ok(self.devices.lock().iter().nth(index)
+            .map(|(name, node)| DirEntry { name: name.clone(), ino: node.ino, kind: InodeKind::CharDevice }))
+    }
+}
+
+impl Inode for DevNode {
+    fn metadata(&self) -> Metadata {
+        let created = self.created;
+        Metadata { ino: self.ino, kind: InodeKind::CharDevice, size: 0, nlink: 1, atime: created, mtime: created, ctime: created }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> Result<usize, Errno> {
+        self.device.read(buf)
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> Result<usize, Errno> {
+        self.device.write(buf)
+    }
+
+    /// 以 O_TRUNC 打开设备时不做任何事
+    fn truncate(&self, _size: u64) -> Result<(), Errno> {
+        Ok(())
+    }
+}
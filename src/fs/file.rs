@@ -0,0 +1,178 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::any::Any;
+use core::ops::BitOr;
+
+use crate::fs::inode::{DirEntry, Inode, InodeKind, Metadata};
+use crate::process::fd::{FileObject, SeekFrom};
+use crate::sync::Mutex;
+use crate::syscall::Errno;
+
+/// open 的标志,取值与 Linux 相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenFlags(u32);
+
+impl OpenFlags {
+    pub const READ_ONLY: OpenFlags = OpenFlags(0);
+    pub const WRITE_ONLY: OpenFlags = OpenFlags(0o1);
+    pub const READ_WRITE: OpenFlags = OpenFlags(0o2);
+    /// 不存在时创建
+    pub const CREATE: OpenFlags = OpenFlags(0o100);
+    /// 与 `CREATE` 一起使用,已存在时失败
+    pub const EXCLUSIVE: OpenFlags = OpenFlags(0o200);
+    pub const TRUNCATE: OpenFlags = OpenFlags(0o1000);
+    /// 每次写入前移到文件末尾
+    pub const APPEND: OpenFlags = OpenFlags(0o2000);
+    /// 必须是目录
+    pub const DIRECTORY: OpenFlags = OpenFlags(0o200000);
+    /// 最后一个分量不跟随符号链接
+    pub const NO_FOLLOW: OpenFlags = OpenFlags(0o400000);
+    const ACCESS_MODE: u32 = 0o3;
+    const ALL: u32 = 0o3 | 0o100 | 0o200 | 0o1000 | 0o2000 | 0o200000 | 0o400000;
+
+    /// 含有未知标志或无效的访问方式时返回 None
+    pub const fn from_bits(bits: u32) -> Option<OpenFlags> {
+        if bits & !OpenFlags::ALL != 0 || bits & OpenFlags::ACCESS_MODE == OpenFlags::ACCESS_MODE {
+            return None;
+        }
+        Some(OpenFlags(bits))
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(&self, other: OpenFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn readable(&self) -> bool {
+        self.0 & OpenFlags::ACCESS_MODE != OpenFlags::WRITE_ONLY.0
+    }
+
+    pub const fn writable(&self) -> bool {
+        self.0 & OpenFlags::ACCESS_MODE != OpenFlags::READ_ONLY.0
+    }
+}
+
+impl BitOr for OpenFlags {
+    type Output = OpenFlags;
+
+    fn bitor(self, rhs: OpenFlags) -> OpenFlags {
+        OpenFlags(self.0 | rhs.0)
+    }
+}
+
+/// 打开的文件:节点加上读写偏移,dup 与 fork 得到的描述符共享同一偏移
+///
+/// 目录的偏移是下一个要读取的目录项序号。
+pub struct OpenFile {
+    inode: Arc<dyn Inode>,
+    path: String,
+    flags: OpenFlags,
+    /// 读写期间一直持有,同一文件上的读写按顺序进行
+    offset: Mutex<u64>,
+}
+
+impl OpenFile {
+    pub fn new(inode: Arc<dyn Inode>, path: String, flags: OpenFlags) -> Self {
+        OpenFile { inode, path, flags, offset: Mutex::new(0) }
+    }
+
+    pub fn inode(&self) -> &Arc<dyn Inode> {
+        &self.inode
+    }
+
+    /// 打开时解析得到的绝对路径
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn flags(&self) -> OpenFlags {
+        self.flags
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        self.inode.metadata()
+    }
+
+    pub fn offset(&self) -> u64 {
+        *self.offset.lock()
+    }
+
+    /// 读取下一个目录项
+    pub fn read_dir(&self) -> Result<Option<DirEntry>, Errno> {
+        let mut offset = self.offset.lock();
+        let entry = self.inode.read_dir(*offset as usize)?;
+        if entry.is_some() {
+            *offset += 1;
+        }
+        Ok(entry)
+    }
+
+    /// 退回一个目录项,供调用者缓冲区放不下时下次重读
+    pub fn unread_dir(&self) {
+        let mut offset = self.offset.lock();
+        *offset = offset.saturating_sub(1);
+    }
+
+    pub fn truncate(&self, size: u64) -> Result<(), Errno> {
+        if !self.flags.writable() {
+            return Err(Errno::EINVAL);
+        }
+        self.inode.truncate(size)
+    }
+}
+
+impl FileObject for OpenFile {
+    fn type_name(&self) -> &'static str {
+        "file"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Errno> {
+        if !self.flags.readable() {
+            return Err(Errno::EBADF);
+        }
+        let mut offset = self.offset.lock();
+        let count = self.inode.read_at(*offset, buf)?;
+        if self.inode.metadata().kind != InodeKind::CharDevice {
+            *offset += count as u64;
+        }
+        Ok(count)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, Errno> {
+        if !self.flags.writable() {
+            return Err(Errno::EBADF);
+        }
+        let mut offset = self.offset.lock();
+        let metadata = self.inode.metadata();
+        if self.flags.contains(OpenFlags::APPEND) {
+            *offset = metadata.size;
+        }
+        let count = self.inode.write_at(*offset, buf)?;
+        if metadata.kind != InodeKind::CharDevice {
+            *offset += count as u64;
+        }
+        Ok(count)
+    }
+
+    fn seek(&self, pos: SeekFrom) -> Result<u64, Errno> {
+        let metadata = self.inode.metadata();
+        if metadata.kind == InodeKind::CharDevice {
+            return Err(Errno::ESPIPE);
+        }
+        let mut offset = self.offset.lock();
+        let target = match pos {
+            SeekFrom::Start(target) => Some(target),
+            SeekFrom::Current(delta) => offset.checked_add_signed(delta),
+            SeekFrom::End(delta) => metadata.size.checked_add_signed(delta),
+        };
+        *offset = target.filter(|target| *target <= i64::MAX as u64).ok_or(Errno::EINVAL)?;
+        Ok(*offset)
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::any::Any;
+
+use crate::syscall::Errno;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum InodeKind {
+    File = 1,
+    Directory = 2,
+    Symlink = 3,
+    /// 设备节点,读写转给驱动,没有偏移的概念
+    CharDevice = 4,
+}
+
+impl InodeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InodeKind::File => "file",
+            InodeKind::Directory => "dir",
+            InodeKind::Symlink => "symlink",
+            InodeKind::CharDevice => "chardev",
+        }
+    }
+}
+
+/// 时间均为 Unix 时间戳(秒)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub ino: u64,
+    pub kind: InodeKind,
+    pub size: u64,
+    pub nlink: u32,
+    /// 最后访问
+    pub atime: u64,
+    /// 内容最后修改
+    pub mtime: u64,
+    /// 元数据最后修改
+    pub ctime: u64,
+}
+
+/// 目录项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub ino: u64,
+    pub kind: InodeKind,
+}
+
+/// 文件系统中的一个节点,由具体文件系统实现;不支持的操作使用默认实现返回错误
+///
+/// 名字都是单个路径分量,由 VFS 保证非空、不含 `/` 且不是 `.` 或 `..`。
+pub trait Inode: Any + Send + Sync {
+    fn metadata(&self) -> Metadata;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn read_at(&self, _offset: u64, _buf: &mut [u8]) -> Result<usize, Errno> {
+        Err(Errno::EISDIR)
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> Result<usize, Errno> {
+        Err(Errno::EISDIR)
+    }
+
+    fn truncate(&self, _size: u64) -> Result<(), Errno> {
+        Err(Errno::EINVAL)
+    }
+
+    /// 在目录中查找
+    fn lookup(&self, _name: &str) -> Result<Arc<dyn Inode>, Errno> {
+        Err(Errno::ENOTDIR)
+    }
+
+    /// 在目录中创建普通文件或子目录,已存在时返回 EEXIST
+    fn create(&self, _name: &str, _kind: InodeKind) -> Result<Arc<dyn Inode>, Errno> {
+        Err(Errno::ENOTDIR)
+    }
+
+    fn symlink(&self, _name: &str, _target: &str) -> Result<Arc<dyn Inode>, Errno> {
+        Err(Errno::ENOTDIR)
+    }
+
+    /// 删除非目录项
+    fn unlink(&self, _name: &str) -> Result<(), Errno> {
+        Err(Errno::ENOTDIR)
+    }
+
+    /// 删除空目录
+    fn rmdir(&self, _name: &str) -> Result<(), Errno> {
+        Err(Errno::ENOTDIR)
+    }
+
+    /// 把本目录中的 `name` 移到 `target` 目录下并命名为 `new_name`,目标已存在时按 POSIX 规则替换;
+    /// `target` 属于其他文件系统时返回 EXDEV
+    fn rename(&self, _name: &str, _target: &Arc<dyn Inode>, _new_name: &str) -> Result<(), Errno> {
+        Err(Errno::ENOTDIR)
+    }
+
+    /// 按序号读取目录项,超出末尾返回 `None`;目录在两次调用之间被修改时可能跳过或重复
+    fn read_dir(&self, _index: usize) -> Result<Option<DirEntry>, Errno> {
+        Err(Errno::ENOTDIR)
+    }
+
+    fn read_link(&self) -> Result<String, Errno> {
+        Err(Errno::EINVAL)
+    }
+}
+
+/// 可挂载的文件系统
+pub trait FileSystem: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn root(&self) -> Arc<dyn Inode>;
+}
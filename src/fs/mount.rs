@@ -0,0 +1,66 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::fs::inode::{FileSystem, Inode, InodeKind};
+use crate::fs::path;
+use crate::sync::SpinLock;
+use crate::syscall::Errno;
+
+struct Mount {
+    /// 规范化的绝对路径
+    path: String,
+    fs: Arc<dyn FileSystem>,
+}
+
+static MOUNTS: SpinLock<Vec<Mount>> = SpinLock::new(Vec::new());
+
+/// 把 `fs` 挂载到 `path`,根目录可直接挂载,其他挂载点必须是已存在的目录且尚未被挂载
+pub fn mount(path: &str, fs: Arc<dyn FileSystem>) -> Result<(), Errno> {
+    let target = if path == "/" {
+        String::from("/")
+    } else {
+        let resolved = path::resolve(path, true)?;
+        if resolved.inode.metadata().kind != InodeKind::Directory {
+            return Err(Errno::ENOTDIR);
+        }
+        resolved.path
+    };
+    let mut mounts = MOUNTS.lock();
+    if mounts.iter().any(|mount| mount.path == target) {
+        return Err(Errno::EBUSY);
+    }
+    mounts.push(Mount { path: target, fs });
+    Ok(())
+}
+
+/// 卸载并返回文件系统,其下还有挂载点时返回 EBUSY;已打开的文件继续引用原文件系统
+pub fn unmount(path: &str) -> Result<Arc<dyn FileSystem>, Errno> {
+    let target = if path == "/" { String::from("/") } else { path::resolve(path, false)?.path };
+    let mut mounts = MOUNTS.lock();
+    let index = mounts.iter().position(|mount| mount.path == target).ok_or(Errno::EINVAL)?;
+    if mounts.iter().any(|mount| mount.path != target && path::is_ancestor(&target, &mount.path)) {
+        return Err(Errno::EBUSY);
+    }
+    Ok(mounts.remove(index).fs)
+}
+
+/// 根文件系统的根目录,尚未挂载时返回 ENOENT
+pub fn root() -> Result<Arc<dyn Inode>, Errno> {
+    covering("/").ok_or(Errno::ENOENT)
+}
+
+/// 挂载在 `path` 上的文件系统的根目录
+pub(super) fn covering(path: &str) -> Option<Arc<dyn Inode>> {
+    MOUNTS.lock().iter().rev().find(|mount| mount.path == path).map(|mount| mount.fs.root())
+}
+
+/// `path` 本身或其下有挂载点
+pub(super) fn is_busy(path: &str) -> bool {
+    MOUNTS.lock().iter().any(|mount| path::is_ancestor(path, &mount.path))
+}
+
+/// 当前的挂载点与文件系统名
+pub fn mounts() -> Vec<(String, &'static str)> {
+    MOUNTS.lock().iter().map(|mount| (mount.path.clone(), mount.fs.name())).collect()
+}
@@ -0,0 +1,156 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::fs::inode::{Inode, InodeKind};
+use crate::fs::mount;
+use crate::syscall::Errno;
+
+/// 路径与单个分量的最大长度
+pub const PATH_MAX: usize = 4096;
+pub const NAME_MAX: usize = 255;
+/// 一次解析中最多展开的符号链接数
+pub const MAX_SYMLINKS: usize = 16;
+
+/// 解析结果,`path` 为展开符号链接、去掉 `.` 与 `..` 后的绝对路径
+pub struct Resolved {
+    pub inode: Arc<dyn Inode>,
+    pub path: String,
+}
+
+/// 逐个分量向下查找,栈中为从根到当前目录经过的节点,`..` 出栈即回到上一级(包括跨越挂载点)
+struct Walk {
+    root: Arc<dyn Inode>,
+    stack: Vec<(String, Arc<dyn Inode>)>,
+    links: usize,
+}
+
+impl Walk {
+    fn new() -> Result<Self, Errno> {
+        Ok(Walk { root: mount::root()?, stack: Vec::new(), links: 0 })
+    }
+
+    fn current(&self) -> Arc<dyn Inode> {
+        self.stack.last().map_or_else(|| self.root.clone(), |(_, inode)| inode.clone())
+    }
+
+    fn path(&self) -> String {
+        if self.stack.is_empty() {
+            return String::from("/");
+        }
+        self.stack.iter().fold(String::new(), |mut path, (name, _)| {
+            path.push('/');
+            path.push_str(name);
+            path
+        })
+    }
+
+    fn walk(&mut self, path: &str, follow_last: bool) -> Result<(), Errno> {
+        if path.starts_with('/') {
+            self.stack.clear();
+        }
+        let components: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+        for (i, name) in components.iter().enumerate() {
+            if self.current().metadata().kind != InodeKind::Directory {
+                return Err(Errno::ENOTDIR);
+            }
+            match *name {
+                "." => continue,
+                ".." => {
+                    self.stack.pop();
+                    continue;
+                }
+                _ if name.len() > NAME_MAX => return Err(Errno::ENAMETOOLONG),
+                _ => {}
+            }
+            let inode = self.current().lookup(name)?;
+            let mut child = self.path();
+            if !self.stack.is_empty() {
+                child.push('/');
+            }
+            child.push_str(name);
+            let inode = mount::covering(&child).unwrap_or(inode);
+            let is_last = i + 1 == components.len();
+            if inode.metadata().kind == InodeKind::Symlink && (follow_last || !is_last) {
+                self.links += 1;
+                if self.links > MAX_SYMLINKS {
+                    return Err(Errno::ELOOP);
+                }
+                // 相对链接从链接所在的目录开始解析
+                let target = inode.read_link()?;
+                self.walk(&target, true)?;
+                continue;
+            }
+            self.stack.push((String::from(*name), inode));
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Resolved {
+        Resolved { inode: self.current(), path: self.path() }
+    }
+}
+
+/// 解析绝对路径;`follow` 为 false 时最后一个分量若是符号链接则返回链接本身
+///
+/// 以 `/` 结尾的路径要求结果是目录。
+pub fn resolve(path: &str, follow: bool) -> Result<Resolved, Errno> {
+    check(path)?;
+    let mut walk = Walk::new()?;
+    walk.walk(path, follow || path.ends_with('/'))?;
+    let resolved = walk.finish();
+    if path.ends_with('/') && resolved.inode.metadata().kind != InodeKind::Directory {
+        return Err(Errno::ENOTDIR);
+    }
+    Ok(resolved)
+}
+
+/// 解析除最后一个分量外的部分,返回父目录与最后一个分量,供创建、删除与改名使用
+///
+/// 最后一个分量为 `.`、`..` 或路径就是 `/` 时返回 EINVAL。
+pub fn resolve_parent(path: &str) -> Result<(Resolved, String), Errno> {
+    check(path)?;
+    let trimmed = path.trim_end_matches('/');
+    let (dir, name) = match trimmed.rfind('/') {
+        Some(index) => (&trimmed[..index + 1], &trimmed[index + 1..]),
+        None => ("", trimmed),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(Errno::EINVAL);
+    }
+    if name.len() > NAME_MAX {
+        return Err(Errno::ENAMETOOLONG);
+    }
+    let parent = resolve(if dir.is_empty() { "/" } else { dir }, true)?;
+    if parent.inode.metadata().kind != InodeKind::Directory {
+        return Err(Errno::ENOTDIR);
+    }
+    Ok((parent, String::from(name)))
+}
+
+fn check(path: &str) -> Result<(), Errno> {
+    if path.is_empty() {
+        return Err(Errno::ENOENT);
+    }
+    if path.len() > PATH_MAX {
+        return Err(Errno::ENAMETOOLONG);
+    }
+    Ok(())
+}
+
+/// 把 `path` 接在目录 `base` 之后,`path` 为绝对路径时原样返回
+pub fn join(base: &str, path: &str) -> String {
+    if path.starts_with('/') {
+        return String::from(path);
+    }
+    let mut joined = String::from(base.trim_end_matches('/'));
+    joined.push('/');
+    joined.push_str(path);
+    joined
+}
+
+/// `ancestor` 是否为 `path` 本身或其上级目录,两者都应是规范化的绝对路径
+pub fn is_ancestor(ancestor: &str, path: &str) -> bool {
+    ancestor == "/" || path == ancestor
+        || (path.starts_with(ancestor) && path.as_bytes().get(ancestor.len()) == Some(&b'/'))
+}
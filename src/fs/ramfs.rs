@@ -0,0 +1,436 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::allocator;
+use crate::fs::inode::{DirEntry, FileSystem, Inode, InodeKind, Metadata};
+use crate::sync::{Mutex, MutexGuard};
+use crate::syscall::Errno;
+use crate::time;
+
+/// 每个节点按固定开销计入用量,限制节点数量
+const INODE_COST: u64 = 128;
+/// 分配后堆中至少还要留下的空闲字节数,留给内核其他部分
+const HEAP_RESERVE: u64 = allocator::HEAP_SIZE / 4;
+
+/// 内容放在内核堆上的文件系统,不依赖任何磁盘驱动
+///
+/// 文件数据、符号链接与节点开销都计入用量,超过容量或堆中剩余空间不足时返回 ENOSPC。
+/// 被删除但仍被打开的文件在最后一个引用释放时才归还空间。
+pub struct RamFs {
+    shared: Arc<Shared>,
+    root: Arc<RamInode>,
+}
+
+struct Shared {
+    next_ino: AtomicU64,
+    used: AtomicU64,
+    capacity: u64,
+    /// 同时锁住两个节点的操作(删除、改名)先取得它,避免交叉加锁
+    tree_lock: Mutex<()>,
+}
+
+impl Shared {
+    fn charge(&self, bytes: u64) -> Result<(), Errno> {
+        if (allocator::stats().free as u64) < bytes.saturating_add(HEAP_RESERVE) {
+            return Err(Errno::ENOSPC);
+        }
+        self.used.fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+            used.checked_add(bytes).filter(|total| *total <= self.capacity)
+        }).map(|_| ()).map_err(|_| Errno::ENOSPC)
+    }
+
+    fn refund(&self, bytes: u64) {
+        self.used.fetch_sub(bytes, Ordering::AcqRel);
+    }
+}
+
+enum Content {
+    File(Vec<u8>),
+    Directory(BTreeMap<String, Arc<RamInode>>),
+    Symlink(String),
+}
+
+impl Content {
+    /// 计入用量的字节数,不含节点开销
+    fn charged(&self) -> u64 {
+        match self {
+            Content::File(data) => data.len() as u64,
+            Content::Directory(_) => 0,
+            Content::Symlink(target) => target.len() as u64,
+        }
+    }
+}
+
+struct Node {
+    content: Content,
+    nlink: u32,
+    atime: u64,
+    mtime: u64,
+    ctime: u64,
+}
+
+impl Node {
+    fn entries(&mut self) -> Result<&mut BTreeMap<String, Arc<RamInode>>, Errno> {
+        match &mut self.content {
+            Content::Directory(entries) => Ok(entries),
+            _ => Err(Errno::ENOTDIR),
+        }
+    }
+
+    fn touch(&mut self) {
+        let now = now();
+        self.mtime = now;
+        self.ctime = now;
+    }
+}
+
+struct RamInode {
+    ino: u64,
+    kind: InodeKind,
+    shared: Arc<Shared>,
+    node: Mutex<Node>,
+}
+
+fn now() -> u64 {
+    time::wall_clock().timestamp
+}
+
+/// 把文件数据扩到 `len` 字节,容量按倍增预留,用量仍只按逻辑长度计
+///
+/// 扩容时新旧缓冲区同时存在,按新缓冲区的大小检查堆中剩余空间;
+/// 倍增的容量分配不到时退回按需分配,碎片导致仍然失败时返回 ENOSPC。
+fn grow(data: &mut Vec<u8>, len: usize) -> Result<(), Errno> {
+    if len > data.capacity() {
+        let doubled = data.capacity().saturating_mul(2).max(len);
+        let fits = |capacity: usize| allocator::stats().free as u64 >= (capacity as u64).saturating_add(HEAP_RESERVE);
+        let reserved = [doubled, len].into_iter()
+            .any(|capacity| fits(capacity) && data.try_reserve_exact(capacity - data.len()).is_ok());
+        if !reserved {
+            return Err(Errno::ENOSPC);
+        }
+    }
+    data.resize(len, 0);
+    Ok(())
+}
+
+/// 缩短到 `len` 字节;容量远大于长度时换用较小的缓冲区,分配不到就保留原来的
+fn shrink(data: &mut Vec<u8>, len: usize) {
+    data.truncate(len);
+    if len == 0 {
+        *data = Vec::new();
+    } else if data.capacity() / 2 > len {
+        let mut smaller = Vec::new();
+        if smaller.try_reserve_exact(len).is_ok() {
+            smaller.extend_from_slice(data);
+            *data = smaller;
+        }
+    }
+}
+
+impl RamFs {
+    /// 容量默认为堆大小的四分之一
+    pub fn new() -> Self {
+        RamFs::with_capacity(allocator::HEAP_SIZE / 4)
+    }
+
+    pub fn with_capacity(capacity: u64) -> Self {
+        let shared = Arc::new(Shared {
+            next_ino: AtomicU64::new(1),
+            used: AtomicU64::new(0),
+            capacity,
+            tree_lock: Mutex::new(()),
+        });
+        let root = RamInode::new(&shared, Content::Directory(BTreeMap::new()));
+        RamFs { shared, root }
+    }
+
+    /// 已用字节数
+    pub fn used(&self) -> u64 {
+        self.shared.used.load(Ordering::Acquire)
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.shared.capacity
+    }
+}
+
+impl Default for RamFs {
+    fn default() -> Self {
+        RamFs::new()
+    }
+}
+
+impl FileSystem for RamFs {
+    fn name(&self) -> &'static str {
+        "ramfs"
+    }
+
+    fn root(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+impl RamInode {
+    /// 不计入用量,调用者负责预先扣除
+    fn new(shared: &Arc<Shared>, content: Content) -> Arc<RamInode> {
+        let kind = match content {
+            Content::File(_) => InodeKind::File,
+            Content::Directory(_) => InodeKind::Directory,
+            Content::Symlink(_) => InodeKind::Symlink,
+        };
+        let now = now();
+        Arc::new(RamInode {
+            ino: shared.next_ino.fetch_add(1, Ordering::Relaxed),
+            kind,
+            shared: shared.clone(),
+            node: Mutex::new(Node { content, nlink: 1, atime: now, mtime: now, ctime: now }),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Node> {
+        self.node.lock()
+    }
+
+    /// 在目录中加入新节点,名字已存在时退还预扣的用量
+    fn insert(&self, name: &str, content: Content) -> Result<Arc<dyn Inode>, Errno> {
+        let cost = INODE_COST + content.charged();
+        self.shared.charge(cost)?;
+        let mut node = self.lock();
+        let entries = match node.entries() {
+            Ok(entries) if !entries.contains_key(name) => entries,
+            Ok(_) => {
+                self.shared.refund(cost);
+                return Err(Errno::EEXIST);
+            }
+            Err(err) => {
+                self.shared.refund(cost);
+                return Err(err);
+            }
+        };
+        let child = RamInode::new(&self.shared, content);
+        entries.insert(String::from(name), child.clone());
+        node.touch();
+        Ok(child)
+    }
+
+    /// 删除 `name`,`dir` 指明要删的是否为目录
+    fn remove(&self, name: &str, dir: bool) -> Result<(), Errno> {
+        let _tree = self.shared.tree_lock.lock();
+        let mut node = self.lock();
+        let entries = node.entries()?;
+        let child = entries.get(name).ok_or(Errno::ENOENT)?;
+        match (child.kind, dir) {
+            (InodeKind::Directory, false) => return Err(Errno::EISDIR),
+            (InodeKind::Directory, true) => {
+                if !child.is_empty_dir() {
+                    return Err(Errno::ENOTEMPTY);
+                }
+            }
+            (_, true) => return Err(Errno::ENOTDIR),
+            (_, false) => {}
+        }
+        let child = entries.remove(name).expect("entry vanished");
+        node.touch();
+        drop(node);
+        let mut removed = child.lock();
+        removed.nlink = 0;
+        removed.ctime = now();
+        Ok(())
+    }
+
+    fn is_empty_dir(&self) -> bool {
+        matches!(&self.lock().content, Content::Directory(entries) if entries.is_empty())
+    }
+
+    fn subdirs(entries: &BTreeMap<String, Arc<RamInode>>) -> u32 {
+        entries.values().filter(|child| child.kind == InodeKind::Directory).count() as u32
+    }
+}
+
+impl Drop for RamInode {
+    fn drop(&mut self) {
+        let charged = self.lock().content.charged();
+        self.shared.refund(INODE_COST + charged);
+    }
+}
+
+impl Inode for RamInode {
+    fn metadata(&self) -> Metadata {
+        let node = self.lock();
+        let (size, nlink) = match &node.content {
+            Content::File(data) => (data.len() as u64, node.nlink),
+            Content::Symlink(target) => (target.len() as u64, node.nlink),
+            // 目录被自身的 `.`、父目录中的项与每个子目录的 `..` 引用
+            Content::Directory(entries) => (entries.len() as u64, if node.nlink == 0 { 0 } else { 2 + RamInode::subdirs(entries) }),
+        };
+        Metadata { ino: self.ino, kind: self.kind, size, nlink, atime: node.atime, mtime: node.mtime, ctime: node.ctime }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Errno> {
+        let mut node = self.lock();
+        let count = match &node.content {
+            Content::File(data) => {
+                let start = offset.min(data.len() as u64) as usize;
+                let count = buf.len().min(data.len() - start);
+                buf[..count].copy_from_slice(&data[start..start + count]);
+                count
+            }
+            Content::Directory(_) => return Err(Errno::EISDIR),
+            Content::Symlink(_) => return Err(Errno::EINVAL),
+        };
+        node.atime = now();
+        Ok(count)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> Result<usize, Errno> {
+        let mut node = self.lock();
+        let data = match &mut node.content {
+            Content::File(data) => data,
+            Content::Directory(_) => return Err(Errno::EISDIR),
+            Content::Symlink(_) => return Err(Errno::EINVAL),
+        };
+        let end = offset.checked_add(buf.len() as u64).ok_or(Errno::EINVAL)?;
+        if end > data.len() as u64 {
+            let grown = end - data.len() as u64;
+            self.shared.charge(grown)?;
+            if let Err(err) = grow(data, end as usize) {
+                self.shared.refund(grown);
+                return Err(err);
+            }
+        }
+        data[offset as usize..end as usize].copy_from_slice(buf);
+        node.touch();
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, size: u64) -> Result<(), Errno> {
+        let mut node = self.lock();
+        let data = match &mut node.content {
+            Content::File(data) => data,
+            Content::Directory(_) => return Err(Errno::EISDIR),
+            Content::Symlink(_) => return Err(Errno::EINVAL),
+        };
+        let len = data.len() as u64;
+        if size > len {
+            self.shared.charge(size - len)?;
+            if let Err(err) = grow(data, size as usize) {
+                self.shared.refund(size - len);
+                return Err(err);
+            }
+        } else {
+            shrink(data, size as usize);
+            self.shared.refund(len - size);
+        }
+        node.touch();
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, Errno> {
+        let mut node = self.lock();
+        let child = node.entries()?.get(name).cloned().ok_or(Errno::ENOENT)?;
+        Ok(child)
+    }
+
+    fn create(&self, name: &str, kind: InodeKind) -> Result<Arc<dyn Inode>, Errno> {
+        let content = match kind {
+            InodeKind::File => Content::File(Vec::new()),
+            InodeKind::Directory => Content::Directory(BTreeMap::new()),
+            _ => return Err(Errno::EINVAL),
+        };
+        self.insert(name, content)
+    }
+
+    fn symlink(&self, name: &str, target: &str) -> Result<Arc<dyn Inode>, Errno> {
+        self.insert(name, Content::Symlink(String::from(target)))
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        self.remove(name, false)
+    }
+
+    fn rmdir(&self, name: &str) -> Result<(), Errno> {
+        self.remove(name, true)
+    }
+
+    fn rename(&self, name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<(), Errno> {
+        let target = match target.as_any().downcast_ref::<RamInode>() {
+            Some(target) if Arc::ptr_eq(&target.shared, &self.shared) => target,
+            _ => return Err(Errno::EXDEV),
+        };
+        let _tree = self.shared.tree_lock.lock();
+        let same_dir = self.ino == target.ino;
+        let (mut source_node, mut target_node) = if same_dir {
+            (self.lock(), None)
+        } else if self.ino < target.ino {
+            let source = self.lock();
+            (source, Some(target.lock()))
+        } else {
+            let target = target.lock();
+            (self.lock(), Some(target))
+        };
+        let moved = source_node.entries()?.get(name).cloned().ok_or(Errno::ENOENT)?;
+        {
+            let target_entries = match target_node.as_mut() {
+                Some(node) => node.entries()?,
+                None => source_node.entries()?,
+            };
+            if let Some(existing) = target_entries.get(new_name) {
+                if Arc::ptr_eq(existing, &moved) {
+                    return Ok(());
+                }
+                // 目标是源所在的目录,它至少还包含被移动的项
+                if existing.ino == self.ino {
+                    return Err(Errno::ENOTEMPTY);
+                }
+                match (moved.kind, existing.kind) {
+                    (InodeKind::Directory, InodeKind::Directory) if !existing.is_empty_dir() => return Err(Errno::ENOTEMPTY),
+                    (InodeKind::Directory, InodeKind::Directory) => {}
+                    (InodeKind::Directory, _) => return Err(Errno::ENOTDIR),
+                    (_, InodeKind::Directory) => return Err(Errno::EISDIR),
+                    _ => {}
+                }
+            }
+        }
+        source_node.entries()?.remove(name);
+        source_node.touch();
+        let replaced = match target_node.as_mut() {
+            Some(node) => {
+                node.touch();
+                node.entries()?.insert(String::from(new_name), moved.clone())
+            }
+            None => source_node.entries()?.insert(String::from(new_name), moved.clone()),
+        };
+        drop(target_node);
+        drop(source_node);
+        moved.lock().ctime = now();
+        if let Some(replaced) = replaced {
+            let mut replaced = replaced.lock();
+            replaced.nlink = 0;
+            replaced.ctime = now();
+        }
+        Ok(())
+    }
+
+    fn read_dir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        let mut node = self.lock();
+        let entry = node.entries()?.iter().nth(index)
+            .map(|(name, child)| DirEntry { name: name.clone(), ino: child.ino, kind: child.kind });
+        node.atime = now();
+        Ok(entry)
+    }
+
+    fn read_link(&self) -> Result<String, Errno> {
+        match &self.lock().content {
+            Content::Symlink(target) => Ok(target.clone()),
+            _ => Err(Errno::EINVAL),
+        }
+    }
+}
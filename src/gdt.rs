@@ -1,3 +1,5 @@
+use core::ptr::{addr_of, addr_of_mut};
+
 use lazy_static::lazy_static;
 use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
 use x86_64::structures::tss::TaskStateSegment;
@@ -7,41 +9,75 @@ use crate::println;
 
 struct Selectors {
     code_selector: SegmentSelector,
+    data_selector: SegmentSelector,
+    user_data_selector: SegmentSelector,
+    user_code_selector: SegmentSelector,
     tss_selector: SegmentSelector,
 }
 
 lazy_static! {
+// 段的顺序满足 SYSCALL/SYSRET 的要求:内核代码、内核数据、用户数据、用户代码
 static ref GDT: (GlobalDescriptorTable, Selectors)={
     let mut gdt = GlobalDescriptorTable::new();
     let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
-    let tss_selector= gdt.add_entry(Descriptor::tss_segment(&TSS));
-    (gdt, Selectors{code_selector, tss_selector})
+    let data_selector = gdt.add_entry(Descriptor::kernel_data_segment());
+    let user_data_selector = gdt.add_entry(Descriptor::user_data_segment());
+    let user_code_selector = gdt.add_entry(Descriptor::user_code_segment());
+    let tss_selector= gdt.add_entry(Descriptor::tss_segment(unsafe { &*addr_of!(TSS) }));
+    (gdt, Selectors{code_selector, data_selector, user_data_selector, user_code_selector, tss_selector})
 };
 }
 
 pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
-lazy_static! {
-static ref TSS: TaskStateSegment = {
-    let mut tss = TaskStateSegment::new();
-    tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
-        const STACK_SIZE: usize = 4096 * 5;
-        static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-        let stack_start = VirtAddr::from_ptr(unsafe { &STACK });
-        let stack_end = stack_start + STACK_SIZE;
-        stack_end
-    };
-    tss
-};
-}
+/// privilege_stack_table[0] 在每次线程切换时更新为当前线程的内核栈,
+/// syscall 入口也从这里(偏移 4)取内核栈
+#[export_name = "mongo_os_tss"]
+static mut TSS: TaskStateSegment = TaskStateSegment::new();
 
+fn init_tss() {
+    const STACK_SIZE: usize = 4096 * 5;
+    static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+    let stack_start = VirtAddr::from_ptr(unsafe { addr_of!(STACK) });
+    let stack_end = stack_start + STACK_SIZE;
+    unsafe { (*addr_of_mut!(TSS)).interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = stack_end; }
+}
 
 pub fn init_gdt() {
     use x86_64::instructions::tables::load_tss;
-    use x86_64::instructions::segmentation::{CS, Segment};
+    use x86_64::instructions::segmentation::{CS, DS, ES, SS, Segment};
     println!("Init GDT ...");
+    init_tss();
     GDT.0.load();
     unsafe {
         CS::set_reg(GDT.1.code_selector);
+        DS::set_reg(GDT.1.data_selector);
+        ES::set_reg(GDT.1.data_selector);
+        SS::set_reg(GDT.1.data_selector);
         load_tss(GDT.1.tss_selector);
     }
 }
+
+/// 设置从 ring 3 进入内核时使用的栈,需在关中断状态下调用
+pub fn set_kernel_stack(stack_top: VirtAddr) {
+    unsafe { (*addr_of_mut!(TSS)).privilege_stack_table[0] = stack_top; }
+}
+
+pub fn kernel_stack() -> VirtAddr {
+    unsafe { (*addr_of!(TSS)).privilege_stack_table[0] }
+}
+
+pub fn kernel_code_selector() -> SegmentSelector {
+    GDT.1.code_selector
+}
+
+pub fn kernel_data_selector() -> SegmentSelector {
+    GDT.1.data_selector
+}
+
+pub fn user_code_selector() -> SegmentSelector {
+    GDT.1.user_code_selector
+}
+
+pub fn user_data_selector() -> SegmentSelector {
+    GDT.1.user_data_selector
+}
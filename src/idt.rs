@@ -1,39 +1,139 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use lazy_static::lazy_static;
 use pic8259::ChainedPics;
-use spin::Mutex;
+use x86_64::PrivilegeLevel;
 use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
 
-use crate::{gdt, hlt_loop, println};
+use crate::{apic, gdt, hlt_loop, println, process, syscall, thread, time, user};
+use crate::process::signal;
+use crate::sync::SpinLock;
 
 pub mod timer;
 pub mod keyboard;
+pub mod serial;
+pub mod spurious;
+pub mod stats;
 
 lazy_static! {
 static ref IDT: InterruptDescriptorTable = {
         let mut idt = InterruptDescriptorTable::new();
+        spurious::set_default_handlers(&mut idt);
+        idt.divide_error.set_handler_fn(divide_error_handler);
         idt.breakpoint.set_handler_fn(breakpoint_handler);
+        idt.invalid_opcode.set_handler_fn(invalid_opcode_handler);
         unsafe{
             idt.double_fault.set_handler_fn(double_fault_handler).set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
         }
+        idt.stack_segment_fault.set_handler_fn(stack_segment_fault_handler);
+        idt.general_protection_fault.set_handler_fn(general_protection_fault_handler);
         idt.page_fault.set_handler_fn(page_fault_handler);
         idt[InterruptIndex::Timer.as_usize()].set_handler_fn(timer::timer_interrupt_handler);
         idt[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard::keyboard_interrupt_handler);
+        idt[InterruptIndex::Serial.as_usize()].set_handler_fn(serial::serial_interrupt_handler);
+        idt[InterruptIndex::Rtc.as_usize()].set_handler_fn(time::rtc::rtc_interrupt_handler);
+        // 允许 ring 3 通过 int 0x80 发起系统调用
+        unsafe {
+            idt[syscall::SYSCALL_VECTOR as usize].set_handler_addr(syscall::entry::int80_entry_addr())
+                .set_privilege_level(PrivilegeLevel::Ring3);
+        }
         idt
     };
 }
 
+static INTERRUPT_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// 硬件中断处理期间持有,离开作用域即退出中断上下文
+pub struct InterruptContext(());
+
+impl Drop for InterruptContext {
+    fn drop(&mut self) {
+        INTERRUPT_DEPTH.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 硬件中断处理函数入口调用:记录统计并进入中断上下文
+pub fn enter_interrupt(vector: u8) -> InterruptContext {
+    stats::record(vector);
+    INTERRUPT_DEPTH.fetch_add(1, Ordering::Relaxed);
+    InterruptContext(())
+}
+
+pub fn in_interrupt() -> bool {
+    INTERRUPT_DEPTH.load(Ordering::Relaxed) > 0
+}
+
+const EXCEPTION_DIVIDE_ERROR: u8 = 0;
+const EXCEPTION_BREAKPOINT: u8 = 3;
+const EXCEPTION_INVALID_OPCODE: u8 = 6;
+const EXCEPTION_DOUBLE_FAULT: u8 = 8;
+const EXCEPTION_STACK_SEGMENT_FAULT: u8 = 12;
+const EXCEPTION_GENERAL_PROTECTION_FAULT: u8 = 13;
+const EXCEPTION_PAGE_FAULT: u8 = 14;
+
+extern "x86-interrupt" fn divide_error_handler(mut stack_frame: InterruptStackFrame) {
+    stats::record(EXCEPTION_DIVIDE_ERROR);
+    if user::raise_on_fault("DIVIDE ERROR", signal::SIGFPE, &mut stack_frame) {
+        return;
+    }
+    panic!("EXCEPTION: DIVIDE ERROR\n{:#?}", stack_frame);
+}
+
 extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
+    stats::record(EXCEPTION_BREAKPOINT);
     println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
 }
 
+extern "x86-interrupt" fn invalid_opcode_handler(mut stack_frame: InterruptStackFrame) {
+    stats::record(EXCEPTION_INVALID_OPCODE);
+    if user::raise_on_fault("INVALID OPCODE", signal::SIGILL, &mut stack_frame) {
+        return;
+    }
+    panic!("EXCEPTION: INVALID OPCODE\n{:#?}", stack_frame);
+}
+
 extern "x86-interrupt" fn double_fault_handler(stack_frame: InterruptStackFrame, _error_code: u64) -> ! {
+    use x86_64::registers::control::Cr2;
+    stats::record(EXCEPTION_DOUBLE_FAULT);
+    // 栈溢出时缺页处理函数无法在原栈上运行,最终落到 IST 上的双重错误
+    if thread::stack::is_guard_page(Cr2::read()) {
+        panic!("EXCEPTION: KERNEL STACK OVERFLOW at {:?}\n{:#?}", Cr2::read(), stack_frame);
+    }
     panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", stack_frame);
 }
 
-extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, error_code: PageFaultErrorCode) {
+extern "x86-interrupt" fn stack_segment_fault_handler(mut stack_frame: InterruptStackFrame, error_code: u64) {
+    stats::record(EXCEPTION_STACK_SEGMENT_FAULT);
+    if user::raise_on_fault("STACK SEGMENT FAULT", signal::SIGBUS, &mut stack_frame) {
+        return;
+    }
+    panic!("EXCEPTION: STACK SEGMENT FAULT ({:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn general_protection_fault_handler(mut stack_frame: InterruptStackFrame, error_code: u64) {
+    stats::record(EXCEPTION_GENERAL_PROTECTION_FAULT);
+    if user::raise_on_fault("GENERAL PROTECTION FAULT", signal::SIGSEGV, &mut stack_frame) {
+        return;
+    }
+    panic!("EXCEPTION: GENERAL PROTECTION FAULT ({:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn page_fault_handler(mut stack_frame: InterruptStackFrame, error_code: PageFaultErrorCode) {
     use x86_64::registers::control::Cr2;
+    stats::record(EXCEPTION_PAGE_FAULT);
+    let address = Cr2::read();
+    // 写时复制:用户态或内核代用户写入共享页
+    if error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION | PageFaultErrorCode::CAUSED_BY_WRITE)
+        && user::is_user_address(address) && process::resolve_cow(address) {
+        return;
+    }
+    if user::from_user_mode(&stack_frame) {
+        println!("USER PAGE FAULT: {:?} accessing {:?}", error_code, address);
+        user::raise_on_fault("PAGE FAULT", signal::SIGSEGV, &mut stack_frame);
+        return;
+    }
     println!("EXCEPTION: PAGE FAULT");
-    println!("Accessed Address: {:?}", Cr2::read());
+    println!("Accessed Address: {:?}", address);
     println!("Error Code: {:?}", error_code);
     println!("{:#?}", stack_frame);
     hlt_loop();
@@ -42,17 +142,18 @@ extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, e
 pub const PIC_1_OFFSET: u8 = 32;
 pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
 
-lazy_static! {
-    pub static ref PICS: Mutex<ChainedPics> = {
-    Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) })
-};
-}
+pub static PICS: SpinLock<ChainedPics> = SpinLock::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum InterruptIndex {
     Timer = PIC_1_OFFSET,
     Keyboard,
+    Serial = PIC_1_OFFSET + 4,
+    PicSpuriousPrimary = PIC_1_OFFSET + 7,
+    Rtc = PIC_2_OFFSET,
+    PicSpuriousSecondary = PIC_2_OFFSET + 7,
+    ApicSpurious = 0xFF,
 }
 
 impl InterruptIndex {
@@ -64,6 +165,25 @@ impl InterruptIndex {
     }
 }
 
+/// 根据当前中断控制器(APIC 或 8259 PIC)发送 EOI
+pub fn notify_end_of_interrupt(index: InterruptIndex) {
+    end_of_interrupt(index.as_u8());
+}
+
+/// 只对确实处于服务中的向量发送 EOI,软件触发的 `int n` 不应确认其它中断
+pub fn end_of_interrupt(vector: u8) {
+    if apic::is_enabled() {
+        if apic::lapic::is_in_service(vector) {
+            apic::lapic::end_of_interrupt();
+        }
+    } else {
+        let mut pics = PICS.lock();
+        if pics.handles_interrupt(vector) {
+            unsafe { pics.notify_end_of_interrupt(vector); }
+        }
+    }
+}
+
 pub fn init_idt() {
     println!("Init IDT ...");
     IDT.load();
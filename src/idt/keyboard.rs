@@ -1,39 +1,37 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use x86_64::instructions::port::Port;
 use x86_64::structures::idt::InterruptStackFrame;
 
-use crate::idt::{InterruptIndex, PICS};
-use crate::print;
+use crate::idt::{enter_interrupt, InterruptIndex, notify_end_of_interrupt};
+use crate::process::signal;
+use crate::{softirq, user};
+use crate::task::keyboard::add_scancode;
 
 const PS2_IO_PORT_ADDR: u16 = 0x60;
+/// 扫描码集 1:左右 Ctrl 按下/松开(右 Ctrl 带 0xE0 前缀)与 C 键按下
+const SCANCODE_CTRL_PRESSED: u8 = 0x1d;
+const SCANCODE_CTRL_RELEASED: u8 = 0x9d;
+const SCANCODE_C_PRESSED: u8 = 0x2e;
 
-pub extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    //todo
-    use lazy_static::lazy_static;
-    use spin::Mutex;
-    use x86_64::instructions::port::Port;
-    use pc_keyboard::{DecodedKey, HandleControl, Keyboard};
-lazy_static! {
-    static ref KEYBOARD: Mutex<Keyboard<pc_keyboard::layouts::Us104Key, pc_keyboard::ScancodeSet1>>={
-        Mutex::new(Keyboard::new(
-            pc_keyboard::ScancodeSet1::new(),
-            pc_keyboard::layouts::Us104Key,
-                HandleControl::Ignore
-        ))
-    };
-}
-    let mut keyboard = KEYBOARD.lock();
+static CTRL_HELD: AtomicBool = AtomicBool::new(false);
+
+/// 中断中只读取扫描码放入队列,解码由 `task::keyboard::KeyStream` 完成
+pub extern "x86-interrupt" fn keyboard_interrupt_handler(mut stack_frame: InterruptStackFrame) {
+    let irq = enter_interrupt(InterruptIndex::Keyboard.as_u8());
     let mut port = Port::new(PS2_IO_PORT_ADDR);
     let scan_code: u8 = unsafe { port.read() };
-    if let Ok(Some(key_event)) = keyboard.add_byte(scan_code) {
-        if let Some(key) = keyboard.process_keyevent(key_event) {
-            match key {
-                DecodedKey::Unicode(character) => print!("{}", character),
-                _ => {
-                    //DecodedKey::RawKey(key) => print!("{:?}", key)
-                },
-            }
+    match scan_code {
+        SCANCODE_CTRL_PRESSED => CTRL_HELD.store(true, Ordering::Relaxed),
+        SCANCODE_CTRL_RELEASED => CTRL_HELD.store(false, Ordering::Relaxed),
+        // Ctrl-C 向前台进程发送 SIGINT
+        SCANCODE_C_PRESSED if CTRL_HELD.load(Ordering::Relaxed) => {
+            softirq::queue(signal::interrupt_foreground, 0);
         }
+        _ => {}
     }
-    unsafe {
-        PICS.lock().notify_end_of_interrupt(InterruptIndex::Keyboard.as_u8());
-    }
-}
\ No newline at end of file
+    add_scancode(scan_code);
+    notify_end_of_interrupt(InterruptIndex::Keyboard);
+    drop(irq);
+    user::return_from_interrupt(&mut stack_frame);
+}
@@ -0,0 +1,90 @@
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use x86_64::instructions::port::Port;
+use x86_64::structures::idt::InterruptStackFrame;
+
+use crate::idt::{enter_interrupt, InterruptIndex, notify_end_of_interrupt};
+use crate::{print, softirq, user};
+use crate::sync::SpinLock;
+
+const COM1_IO_PORT_ADDR: u16 = 0x3F8;
+const LINE_STATUS_DATA_READY: u8 = 0x01;
+const INPUT_BUFFER_SIZE: usize = 256;
+
+/// 中断中收到的字节,由 softirq 回显;定长环形队列,中断上下文中不能分配堆内存
+struct InputBuffer {
+    bytes: [u8; INPUT_BUFFER_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl InputBuffer {
+    const fn new() -> Self {
+        InputBuffer { bytes: [0; INPUT_BUFFER_SIZE], head: 0, len: 0 }
+    }
+
+    fn push(&mut self, byte: u8) -> bool {
+        if self.len == INPUT_BUFFER_SIZE {
+            return false;
+        }
+        self.bytes[(self.head + self.len) % INPUT_BUFFER_SIZE] = byte;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.bytes[self.head];
+        self.head = (self.head + 1) % INPUT_BUFFER_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+static INPUT: SpinLock<InputBuffer> = SpinLock::new(InputBuffer::new());
+/// 回显已入队尚未执行
+static ECHO_QUEUED: AtomicBool = AtomicBool::new(false);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// 中断中只把收到的字节放入队列,回显在 softirq 中完成
+pub extern "x86-interrupt" fn serial_interrupt_handler(mut stack_frame: InterruptStackFrame) {
+    let irq = enter_interrupt(InterruptIndex::Serial.as_u8());
+    let mut data: Port<u8> = Port::new(COM1_IO_PORT_ADDR);
+    let mut line_status: Port<u8> = Port::new(COM1_IO_PORT_ADDR + 5);
+    {
+        let mut input = INPUT.lock();
+        unsafe {
+            while line_status.read() & LINE_STATUS_DATA_READY != 0 {
+                if !input.push(data.read()) {
+                    DROPPED.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+    if !ECHO_QUEUED.swap(true, Ordering::AcqRel) && !softirq::queue(echo_input, 0) {
+        ECHO_QUEUED.store(false, Ordering::Release);
+    }
+    notify_end_of_interrupt(InterruptIndex::Serial);
+    drop(irq);
+    user::return_from_interrupt(&mut stack_frame);
+}
+
+fn echo_input(_: usize) {
+    // 先清除标记,之后到达的字节要么在下面被取走,要么重新入队一次回显
+    ECHO_QUEUED.store(false, Ordering::Release);
+    loop {
+        // 打印时不持有队列锁
+        let byte = INPUT.lock().pop();
+        match byte {
+            Some(byte) => print!("{}", byte as char),
+            None => break,
+        }
+    }
+}
+
+/// 队列满时被丢弃的输入字节数
+pub fn dropped_bytes() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,110 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use x86_64::instructions::port::Port;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame};
+
+use crate::idt::{end_of_interrupt, enter_interrupt, InterruptIndex, PICS, stats};
+use crate::println;
+
+const PIC_1_COMMAND: u16 = 0x20;
+const PIC_2_COMMAND: u16 = 0xA0;
+/// OCW3: 下一次读命令端口返回 ISR
+const PIC_READ_ISR: u8 = 0x0B;
+const PIC_SPURIOUS_IRQ_BIT: u8 = 1 << 7;
+
+static PIC_SPURIOUS_COUNT: AtomicU64 = AtomicU64::new(0);
+static APIC_SPURIOUS_COUNT: AtomicU64 = AtomicU64::new(0);
+
+pub fn pic_spurious_count() -> u64 {
+    PIC_SPURIOUS_COUNT.load(Ordering::Relaxed)
+}
+
+pub fn apic_spurious_count() -> u64 {
+    APIC_SPURIOUS_COUNT.load(Ordering::Relaxed)
+}
+
+fn pic_in_service(command_port: u16) -> u8 {
+    let _pics = PICS.lock();
+    let mut command: Port<u8> = Port::new(command_port);
+    unsafe {
+        command.write(PIC_READ_ISR);
+        command.read()
+    }
+}
+
+/// IRQ7 的 ISR 位未置位即为伪中断,不能发送 EOI
+pub extern "x86-interrupt" fn pic_primary_spurious_handler(_stack_frame: InterruptStackFrame) {
+    let _irq = enter_interrupt(InterruptIndex::PicSpuriousPrimary.as_u8());
+    if pic_in_service(PIC_1_COMMAND) & PIC_SPURIOUS_IRQ_BIT == 0 {
+        PIC_SPURIOUS_COUNT.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    end_of_interrupt(InterruptIndex::PicSpuriousPrimary.as_u8());
+}
+
+/// IRQ15 的伪中断只需向主片发送 EOI(级联线 IRQ2 已被主片确认)
+pub extern "x86-interrupt" fn pic_secondary_spurious_handler(_stack_frame: InterruptStackFrame) {
+    let _irq = enter_interrupt(InterruptIndex::PicSpuriousSecondary.as_u8());
+    if pic_in_service(PIC_2_COMMAND) & PIC_SPURIOUS_IRQ_BIT == 0 {
+        PIC_SPURIOUS_COUNT.fetch_add(1, Ordering::Relaxed);
+        let mut command: Port<u8> = Port::new(PIC_1_COMMAND);
+        unsafe { command.write(0x20u8); }
+        return;
+    }
+    end_of_interrupt(InterruptIndex::PicSpuriousSecondary.as_u8());
+}
+
+/// LAPIC 伪中断不置位 ISR,不需要 EOI
+pub extern "x86-interrupt" fn apic_spurious_handler(_stack_frame: InterruptStackFrame) {
+    let _irq = enter_interrupt(InterruptIndex::ApicSpurious.as_u8());
+    APIC_SPURIOUS_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+extern "x86-interrupt" fn unhandled_interrupt_handler<const VECTOR: u8>(_stack_frame: InterruptStackFrame) {
+    let _irq = enter_interrupt(VECTOR);
+    let count = stats::count(VECTOR);
+    println!("UNHANDLED INTERRUPT: vector {} (count {})", VECTOR, count);
+    end_of_interrupt(VECTOR);
+}
+
+macro_rules! set_unhandled_row {
+    ($idt:ident, $row:literal) => {
+        $idt[$row * 16 + 0x0].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x0 }>);
+        $idt[$row * 16 + 0x1].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x1 }>);
+        $idt[$row * 16 + 0x2].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x2 }>);
+        $idt[$row * 16 + 0x3].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x3 }>);
+        $idt[$row * 16 + 0x4].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x4 }>);
+        $idt[$row * 16 + 0x5].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x5 }>);
+        $idt[$row * 16 + 0x6].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x6 }>);
+        $idt[$row * 16 + 0x7].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x7 }>);
+        $idt[$row * 16 + 0x8].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x8 }>);
+        $idt[$row * 16 + 0x9].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0x9 }>);
+        $idt[$row * 16 + 0xA].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0xA }>);
+        $idt[$row * 16 + 0xB].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0xB }>);
+        $idt[$row * 16 + 0xC].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0xC }>);
+        $idt[$row * 16 + 0xD].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0xD }>);
+        $idt[$row * 16 + 0xE].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0xE }>);
+        $idt[$row * 16 + 0xF].set_handler_fn(unhandled_interrupt_handler::<{ $row * 16 + 0xF }>);
+    };
+}
+
+/// 为 32-255 号向量安装默认处理函数,具体设备的处理函数随后覆盖
+pub fn set_default_handlers(idt: &mut InterruptDescriptorTable) {
+    set_unhandled_row!(idt, 2);
+    set_unhandled_row!(idt, 3);
+    set_unhandled_row!(idt, 4);
+    set_unhandled_row!(idt, 5);
+    set_unhandled_row!(idt, 6);
+    set_unhandled_row!(idt, 7);
+    set_unhandled_row!(idt, 8);
+    set_unhandled_row!(idt, 9);
+    set_unhandled_row!(idt, 10);
+    set_unhandled_row!(idt, 11);
+    set_unhandled_row!(idt, 12);
+    set_unhandled_row!(idt, 13);
+    set_unhandled_row!(idt, 14);
+    set_unhandled_row!(idt, 15);
+    idt[InterruptIndex::PicSpuriousPrimary.as_usize()].set_handler_fn(pic_primary_spurious_handler);
+    idt[InterruptIndex::PicSpuriousSecondary.as_usize()].set_handler_fn(pic_secondary_spurious_handler);
+    idt[InterruptIndex::ApicSpurious.as_usize()].set_handler_fn(apic_spurious_handler);
+}
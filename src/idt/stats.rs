@@ -0,0 +1,71 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::idt::InterruptIndex;
+use crate::println;
+
+pub const VECTOR_COUNT: usize = 256;
+
+const ZERO: AtomicU64 = AtomicU64::new(0);
+static COUNTS: [AtomicU64; VECTOR_COUNT] = [ZERO; VECTOR_COUNT];
+
+const EXCEPTION_NAMES: [&str; 21] = [
+    "Divide Error", "Debug", "NMI", "Breakpoint",
+    "Overflow", "Bound Range", "Invalid Opcode", "Device Not Available",
+    "Double Fault", "Coprocessor Overrun", "Invalid TSS", "Segment Not Present",
+    "Stack Fault", "General Protection", "Page Fault", "Reserved",
+    "x87 FPU", "Alignment Check", "Machine Check", "SIMD", "Virtualization",
+];
+
+/// 每个处理函数入口处调用一次
+pub fn record(vector: u8) {
+    COUNTS[vector as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn count(vector: u8) -> u64 {
+    COUNTS[vector as usize].load(Ordering::Relaxed)
+}
+
+pub fn total() -> u64 {
+    COUNTS.iter().map(|c| c.load(Ordering::Relaxed)).sum()
+}
+
+pub fn snapshot() -> [u64; VECTOR_COUNT] {
+    let mut counts = [0u64; VECTOR_COUNT];
+    for (i, c) in COUNTS.iter().enumerate() {
+        counts[i] = c.load(Ordering::Relaxed);
+    }
+    counts
+}
+
+pub fn reset() {
+    for c in COUNTS.iter() {
+        c.store(0, Ordering::Relaxed);
+    }
+}
+
+pub fn vector_name(vector: u8) -> &'static str {
+    match vector {
+        v if (v as usize) < EXCEPTION_NAMES.len() => EXCEPTION_NAMES[v as usize],
+        v if v < 32 => "Reserved",
+        v if v == InterruptIndex::Timer.as_u8() => "Timer",
+        v if v == InterruptIndex::Keyboard.as_u8() => "Keyboard",
+        v if v == InterruptIndex::Serial.as_u8() => "Serial",
+        v if v == InterruptIndex::Rtc.as_u8() => "RTC",
+        v if v == InterruptIndex::PicSpuriousPrimary.as_u8() => "PIC Spurious (IRQ7)",
+        v if v == InterruptIndex::PicSpuriousSecondary.as_u8() => "PIC Spurious (IRQ15)",
+        v if v == InterruptIndex::ApicSpurious.as_u8() => "APIC Spurious",
+        _ => "Unhandled",
+    }
+}
+
+/// 打印计数非零的向量
+pub fn print_table() {
+    let counts = snapshot();
+    println!("{:>6}  {:<24}{:>12}", "VECTOR", "NAME", "COUNT");
+    for (vector, &count) in counts.iter().enumerate() {
+        if count != 0 {
+            println!("{:>6}  {:<24}{:>12}", vector, vector_name(vector as u8), count);
+        }
+    }
+    println!("{:>6}  {:<24}{:>12}", "", "TOTAL", counts.iter().sum::<u64>());
+}
@@ -1,10 +1,18 @@
 use x86_64::structures::idt::InterruptStackFrame;
 
-use crate::idt::{InterruptIndex, PICS};
+use crate::idt::{enter_interrupt, InterruptIndex, notify_end_of_interrupt};
+use crate::{softirq, thread, time, user};
 
-pub extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame){
-    //todo
-    unsafe {
-        PICS.lock().notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
+pub extern "x86-interrupt" fn timer_interrupt_handler(mut stack_frame: InterruptStackFrame){
+    {
+        let _irq = enter_interrupt(InterruptIndex::Timer.as_u8());
+        time::tick();
+        time::timer::on_tick();
+        notify_end_of_interrupt(InterruptIndex::Timer);
     }
-}
\ No newline at end of file
+    // 延迟工作开中断执行,调度可能切换到其它线程,均不算中断上下文
+    softirq::run_pending();
+    thread::scheduler::on_tick();
+    // 打断用户态时,Ctrl-C 等异步信号在返回用户态的路径上处理
+    user::return_from_interrupt(&mut stack_frame);
+}
@@ -0,0 +1,13 @@
+//! 进程间通信
+//!
+//! [`channel`] 创建一对双向的消息端点,端点作为句柄放入进程的句柄表,可随消息传给其他进程。
+//! [`pipe`] 是单向的字节流,两端通过文件描述符读写。
+//! [`shm`] 提供可同时映射进多个地址空间的共享内存,适合大块数据的交换。
+
+pub use channel::{channel, ChannelError, Endpoint, Message, SendError};
+pub use pipe::{pipe, PipeError, PipeReader, PipeWriter};
+pub use shm::SharedMemory;
+
+pub mod channel;
+pub mod pipe;
+pub mod shm;
@@ -0,0 +1,398 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{Context, Poll};
+
+use x86_64::structures::paging::{PageTableFlags, PhysFrame};
+
+use crate::mem::frame_refs;
+use crate::process::fd::FileObject;
+use crate::process::handle::{HandleEntry, KernelObject};
+use crate::process::signal;
+use crate::sync::{SpinLock, WaitQueue};
+use crate::syscall::Errno;
+
+/// 每个方向默认可缓存的消息数
+pub const DEFAULT_CAPACITY: usize = 64;
+/// 单条消息的限制,更大的数据应以页的形式转移
+pub const MAX_DATA: usize = 64 * 1024;
+pub const MAX_HANDLES: usize = 16;
+pub const MAX_PAGES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelError {
+    /// 对端队列已满(非阻塞发送)
+    Full,
+    /// 没有消息(非阻塞接收)
+    Empty,
+    /// 对端已关闭
+    Closed,
+    /// 等待被取消,如收到信号
+    Interrupted,
+    /// 消息超过限制或接收方缓冲区
+    TooLarge,
+}
+
+impl From<ChannelError> for Errno {
+    fn from(err: ChannelError) -> Self {
+        match err {
+            ChannelError::Full | ChannelError::Empty => Errno::EAGAIN,
+            ChannelError::Closed => Errno::EPIPE,
+            ChannelError::Interrupted => Errno::EINTR,
+            ChannelError::TooLarge => Errno::EMSGSIZE,
+        }
+    }
+}
+
+/// 消息:数据按值复制,句柄与物理页随消息转移
+#[derive(Default)]
+pub struct Message {
+    pub data: Vec<u8>,
+    pub handles: Vec<HandleEntry>,
+    /// 从发送方地址空间取出的页,接收方映射后不再复制
+    pub pages: Vec<(PhysFrame, PageTableFlags)>,
+    /// 消息中端点的登记,消息销毁时撤销
+    pub transit: Option<Transit>,
+}
+
+impl Message {
+    pub fn new(data: Vec<u8>) -> Self {
+        Message { data, handles: Vec::new(), pages: Vec::new(), transit: None }
+    }
+
+    fn exceeds_limits(&self) -> bool {
+        self.data.len() > MAX_DATA || self.handles.len() > MAX_HANDLES || self.pages.len() > MAX_PAGES
+    }
+}
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Message")
+            .field("data", &self.data.len())
+            .field("handles", &self.handles.len())
+            .field("pages", &self.pages.len())
+            .finish()
+    }
+}
+
+/// 未被接收的页在消息销毁时释放
+impl Drop for Message {
+    fn drop(&mut self) {
+        for (frame, _) in self.pages.drain(..) {
+            frame_refs::free(frame);
+        }
+    }
+}
+
+/// 经某个通道传递中的端点数,随消息一起销毁
+#[derive(Debug)]
+pub struct Transit {
+    count: Arc<AtomicUsize>,
+    endpoints: usize,
+}
+
+impl Drop for Transit {
+    fn drop(&mut self) {
+        self.count.fetch_sub(self.endpoints, Ordering::AcqRel);
+    }
+}
+
+/// 端点转移的检查与登记必须原子地完成
+static TRANSFER: SpinLock<()> = SpinLock::new(());
+
+/// 发送失败时退回消息
+#[derive(Debug)]
+pub struct SendError {
+    pub kind: ChannelError,
+    pub message: Message,
+}
+
+/// 一个方向的消息队列
+struct Inbox {
+    messages: SpinLock<VecDeque<Message>>,
+    /// 等待消息的接收者
+    readers: WaitQueue,
+    /// 等待空位的发送者
+    writers: WaitQueue,
+}
+
+impl Inbox {
+    fn new() -> Self {
+        Inbox { messages: SpinLock::new(VecDeque::new()), readers: WaitQueue::new(), writers: WaitQueue::new() }
+    }
+}
+
+struct Shared {
+    inboxes: [Inbox; 2],
+    closed: [AtomicBool; 2],
+    capacity: usize,
+    /// 经本通道传递、尚未被接收的端点数,包括阻塞在发送中的消息
+    in_transit: Arc<AtomicUsize>,
+}
+
+/// 双向通道的一端,销毁时关闭
+pub struct Endpoint {
+    shared: Arc<Shared>,
+    side: usize,
+}
+
+/// 创建一对相连的端点,每个方向最多缓存 `capacity` 条消息
+pub fn channel(capacity: usize) -> (Endpoint, Endpoint) {
+    let shared = Arc::new(Shared {
+        inboxes: [Inbox::new(), Inbox::new()],
+        closed: [AtomicBool::new(false), AtomicBool::new(false)],
+        capacity: capacity.max(1),
+        in_transit: Arc::new(AtomicUsize::new(0)),
+    });
+    (Endpoint { shared: shared.clone(), side: 0 }, Endpoint { shared, side: 1 })
+}
+
+impl Endpoint {
+    fn inbox(&self) -> &Inbox {
+        &self.shared.inboxes[self.side]
+    }
+
+    fn peer_inbox(&self) -> &Inbox {
+        &self.shared.inboxes[1 - self.side]
+    }
+
+    pub fn is_peer_closed(&self) -> bool {
+        self.shared.closed[1 - self.side].load(Ordering::Acquire)
+    }
+
+    /// 两端是否属于同一通道
+    pub fn same_channel(&self, other: &Endpoint) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    /// 登记将经由本通道发送的端点,会形成环时返回 None
+    ///
+    /// 消息中的端点互相持有对方所在的通道会形成无法释放的环。每条"通道 A 的端点经通道 B 传递"的边
+    /// 加入时都要求 A 不同于 B 且 A 没有入边,检查与登记在同一把锁下完成,环无法闭合。
+    pub fn reserve_transfer<'a, I>(&self, endpoints: I) -> Option<Transit> where I: IntoIterator<Item = &'a Endpoint> {
+        let _transfer = TRANSFER.lock();
+        let mut count = 0;
+        for endpoint in endpoints {
+            if endpoint.same_channel(self) || endpoint.shared.in_transit.load(Ordering::Acquire) != 0 {
+                return None;
+            }
+            count += 1;
+        }
+        self.shared.in_transit.fetch_add(count, Ordering::AcqRel);
+        Some(Transit { count: self.shared.in_transit.clone(), endpoints: count })
+    }
+
+    /// 待接收的消息数
+    pub fn pending(&self) -> usize {
+        self.inbox().messages.lock().len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    pub fn try_send(&self, message: Message) -> Result<(), SendError> {
+        if message.exceeds_limits() {
+            return Err(SendError { kind: ChannelError::TooLarge, message });
+        }
+        if self.is_peer_closed() {
+            return Err(SendError { kind: ChannelError::Closed, message });
+        }
+        {
+            let mut messages = self.peer_inbox().messages.lock();
+            if messages.len() >= self.shared.capacity {
+                return Err(SendError { kind: ChannelError::Full, message });
+            }
+            messages.push_back(message);
+        }
+        self.peer_inbox().readers.notify_one();
+        Ok(())
+    }
+
+    /// 队首消息被 `accept` 拒绝时保留在队列中并返回 `TooLarge`
+    pub fn try_recv_with<F>(&self, accept: F) -> Result<Message, ChannelError> where F: FnOnce(&Message) -> bool {
+        let message = {
+            let mut messages = self.inbox().messages.lock();
+            match messages.front() {
+                Some(front) if !accept(front) => return Err(ChannelError::TooLarge),
+                Some(_) => messages.pop_front(),
+                None => None,
+            }
+        };
+        match message {
+            Some(message) => {
+                self.inbox().writers.notify_one();
+                Ok(message)
+            }
+            // 对端关闭后仍可收完已发送的消息
+            None if self.is_peer_closed() => Err(ChannelError::Closed),
+            None => Err(ChannelError::Empty),
+        }
+    }
+
+    /// 把已取出但未能交付的消息放回队首,可以暂时超出容量
+    pub fn unrecv(&self, message: Message) {
+        self.inbox().messages.lock().push_front(message);
+        self.inbox().readers.notify_one();
+    }
+
+    pub fn try_recv(&self) -> Result<Message, ChannelError> {
+        self.try_recv_with(|_| true)
+    }
+
+    /// 阻塞直到发送成功或对端关闭
+    #[track_caller]
+    pub fn send(&self, message: Message) -> Result<(), SendError> {
+        self.send_cancellable(message, || false)
+    }
+
+    /// 等待期间 `cancelled` 返回 true 时放弃发送并返回 `Interrupted`
+    #[track_caller]
+    pub fn send_cancellable<F>(&self, message: Message, mut cancelled: F) -> Result<(), SendError> where F: FnMut() -> bool {
+        let mut message = Some(message);
+        self.peer_inbox().writers.wait_until(|| {
+            let pending = message.take().expect("message already sent");
+            match self.try_send(pending) {
+                Ok(()) => Some(Ok(())),
+                Err(SendError { kind: ChannelError::Full, message: pending }) if !cancelled() => {
+                    message = Some(pending);
+                    None
+                }
+                Err(SendError { kind: ChannelError::Full, message: pending }) =>
+                    Some(Err(SendError { kind: ChannelError::Interrupted, message: pending })),
+                Err(err) => Some(Err(err)),
+            }
+        })
+    }
+
+    /// 阻塞直到收到消息;对端关闭且队列为空时返回 `Closed`
+    #[track_caller]
+    pub fn recv(&self) -> Result<Message, ChannelError> {
+        self.recv_cancellable(|_| true, || false)
+    }
+
+    #[track_caller]
+    pub fn recv_cancellable<A, F>(&self, accept: A, mut cancelled: F) -> Result<Message, ChannelError>
+        where A: Fn(&Message) -> bool, F: FnMut() -> bool {
+        self.inbox().readers.wait_until(|| {
+            match self.try_recv_with(&accept) {
+                Err(ChannelError::Empty) if cancelled() => Some(Err(ChannelError::Interrupted)),
+                Err(ChannelError::Empty) => None,
+                result => Some(result),
+            }
+        })
+    }
+
+    /// 供异步任务发送
+    pub fn send_async(&self, message: Message) -> SendFuture<'_> {
+        SendFuture { endpoint: self, message: Some(message) }
+    }
+
+    /// 供异步任务接收
+    pub fn recv_async(&self) -> RecvFuture<'_> {
+        RecvFuture { endpoint: self }
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        self.shared.closed[self.side].store(true, Ordering::Release);
+        // 没人会再接收的消息连同其中的句柄与页一起释放,释放时不持有队列锁
+        let orphaned = core::mem::take(&mut *self.inbox().messages.lock());
+        drop(orphaned);
+        self.peer_inbox().readers.notify_all();
+        self.inbox().writers.notify_all();
+    }
+}
+
+impl KernelObject for Endpoint {
+    fn type_name(&self) -> &'static str {
+        "channel"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// 以文件描述符使用时只收发不带句柄与页的消息,每次读写一条;阻塞等待可被信号打断
+impl FileObject for Endpoint {
+    fn type_name(&self) -> &'static str {
+        "channel"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Errno> {
+        let accept = |message: &Message| message.data.len() <= buf.len() && message.handles.is_empty() && message.pages.is_empty();
+        match self.recv_cancellable(accept, signal::has_deliverable) {
+            Ok(message) => {
+                buf[..message.data.len()].copy_from_slice(&message.data);
+                Ok(message.data.len())
+            }
+            // 对端关闭视为文件结束
+            Err(ChannelError::Closed) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, Errno> {
+        self.send_cancellable(Message::new(buf.to_vec()), signal::has_deliverable)
+            .map(|()| buf.len())
+            .map_err(|err| err.kind.into())
+    }
+}
+
+pub struct SendFuture<'a> {
+    endpoint: &'a Endpoint,
+    message: Option<Message>,
+}
+
+impl<'a> Future for SendFuture<'a> {
+    type Output = Result<(), SendError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let endpoint = self.endpoint;
+        let message = self.message.take().expect("SendFuture polled after completion");
+        let message = match endpoint.try_send(message) {
+            Err(SendError { kind: ChannelError::Full, message }) => message,
+            result => return Poll::Ready(result),
+        };
+        endpoint.peer_inbox().writers.register(cx.waker());
+        // 登记后再试一次,避免错过登记前腾出的空位
+        match endpoint.try_send(message) {
+            Err(SendError { kind: ChannelError::Full, message }) => {
+                self.message = Some(message);
+                Poll::Pending
+            }
+            result => Poll::Ready(result),
+        }
+    }
+}
+
+pub struct RecvFuture<'a> {
+    endpoint: &'a Endpoint,
+}
+
+impl<'a> Future for RecvFuture<'a> {
+    type Output = Result<Message, ChannelError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match self.endpoint.try_recv() {
+            Err(ChannelError::Empty) => {}
+            result => return Poll::Ready(result),
+        }
+        self.endpoint.inbox().readers.register(cx.waker());
+        match self.endpoint.try_recv() {
+            Err(ChannelError::Empty) => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+}
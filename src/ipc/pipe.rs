@@ -0,0 +1,226 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use core::any::Any;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::process::fd::FileObject;
+use crate::process::signal;
+use crate::sync::{SpinLock, WaitQueue};
+use crate::syscall::Errno;
+
+/// 管道缓冲区的字节数
+pub const PIPE_CAPACITY: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeError {
+    /// 缓冲区已满(非阻塞写)
+    Full,
+    /// 缓冲区为空而写端仍打开(非阻塞读)
+    Empty,
+    /// 读端已关闭
+    Closed,
+    /// 等待被取消,如收到信号
+    Interrupted,
+}
+
+impl From<PipeError> for Errno {
+    fn from(err: PipeError) -> Self {
+        match err {
+            PipeError::Full | PipeError::Empty => Errno::EAGAIN,
+            PipeError::Closed => Errno::EPIPE,
+            PipeError::Interrupted => Errno::EINTR,
+        }
+    }
+}
+
+/// 定长环形缓冲区
+struct RingBuffer {
+    data: Box<[u8]>,
+    head: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    fn new(capacity: usize) -> Self {
+        RingBuffer { data: vec![0; capacity].into_boxed_slice(), head: 0, len: 0 }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let count = buf.len().min(self.len);
+        for (i, byte) in buf[..count].iter_mut().enumerate() {
+            *byte = self.data[(self.head + i) % self.data.len()];
+        }
+        self.head = (self.head + count) % self.data.len();
+        self.len -= count;
+        count
+    }
+
+    fn write(&mut self, buf: &[u8]) -> usize {
+        let count = buf.len().min(self.data.len() - self.len);
+        let tail = self.head + self.len;
+        for (i, byte) in buf[..count].iter().enumerate() {
+            self.data[(tail + i) % self.data.len()] = *byte;
+        }
+        self.len += count;
+        count
+    }
+}
+
+struct Pipe {
+    buffer: SpinLock<RingBuffer>,
+    /// 等待数据的读者
+    readers: WaitQueue,
+    /// 等待空间的写者
+    writers: WaitQueue,
+    read_closed: AtomicBool,
+    write_closed: AtomicBool,
+}
+
+/// 管道的读端,销毁时关闭
+pub struct PipeReader {
+    pipe: Arc<Pipe>,
+}
+
+/// 管道的写端,销毁时关闭
+pub struct PipeWriter {
+    pipe: Arc<Pipe>,
+}
+
+/// 创建一个匿名管道
+pub fn pipe() -> (PipeReader, PipeWriter) {
+    let pipe = Arc::new(Pipe {
+        buffer: SpinLock::new(RingBuffer::new(PIPE_CAPACITY)),
+        readers: WaitQueue::new(),
+        writers: WaitQueue::new(),
+        read_closed: AtomicBool::new(false),
+        write_closed: AtomicBool::new(false),
+    });
+    (PipeReader { pipe: pipe.clone() }, PipeWriter { pipe })
+}
+
+impl PipeReader {
+    /// 写端关闭且缓冲区为空时返回 `Ok(0)`
+    pub fn try_read(&self, buf: &mut [u8]) -> Result<usize, PipeError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let count = self.pipe.buffer.lock().read(buf);
+        if count > 0 {
+            self.pipe.writers.notify_all();
+            return Ok(count);
+        }
+        // 先读缓冲区再看写端,关闭前写入的数据不会丢失
+        if self.pipe.write_closed.load(Ordering::Acquire) && self.pipe.buffer.lock().len == 0 {
+            Ok(0)
+        } else {
+            Err(PipeError::Empty)
+        }
+    }
+
+    /// 阻塞直到读到数据或写端关闭
+    #[track_caller]
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, PipeError> {
+        self.read_cancellable(buf, || false)
+    }
+
+    /// 等待期间 `cancelled` 返回 true 时返回 `Interrupted`
+    #[track_caller]
+    pub fn read_cancellable<F>(&self, buf: &mut [u8], mut cancelled: F) -> Result<usize, PipeError> where F: FnMut() -> bool {
+        self.pipe.readers.wait_until(|| {
+            match self.try_read(buf) {
+                Err(PipeError::Empty) if cancelled() => Some(Err(PipeError::Interrupted)),
+                Err(PipeError::Empty) => None,
+                result => Some(result),
+            }
+        })
+    }
+}
+
+impl PipeWriter {
+    /// 写入能放下的部分,一个字节也放不下时返回 `Full`
+    pub fn try_write(&self, buf: &[u8]) -> Result<usize, PipeError> {
+        if self.pipe.read_closed.load(Ordering::Acquire) {
+            return Err(PipeError::Closed);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let count = self.pipe.buffer.lock().write(buf);
+        if count == 0 {
+            return Err(PipeError::Full);
+        }
+        self.pipe.readers.notify_all();
+        Ok(count)
+    }
+
+    /// 阻塞直到全部写入
+    #[track_caller]
+    pub fn write(&self, buf: &[u8]) -> Result<usize, PipeError> {
+        self.write_cancellable(buf, || false)
+    }
+
+    /// 等待期间被取消或读端关闭时,已写入部分数据则返回已写入的字节数
+    #[track_caller]
+    pub fn write_cancellable<F>(&self, buf: &[u8], mut cancelled: F) -> Result<usize, PipeError> where F: FnMut() -> bool {
+        let mut written = 0;
+        while written < buf.len() {
+            let result = self.pipe.writers.wait_until(|| {
+                match self.try_write(&buf[written..]) {
+                    Err(PipeError::Full) if cancelled() => Some(Err(PipeError::Interrupted)),
+                    Err(PipeError::Full) => None,
+                    result => Some(result),
+                }
+            });
+            match result {
+                Ok(count) => written += count,
+                Err(_) if written > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(written)
+    }
+}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        self.pipe.read_closed.store(true, Ordering::Release);
+        self.pipe.writers.notify_all();
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        self.pipe.write_closed.store(true, Ordering::Release);
+        self.pipe.readers.notify_all();
+    }
+}
+
+/// 阻塞读可被信号打断
+impl FileObject for PipeReader {
+    fn type_name(&self) -> &'static str {
+        "pipe"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Errno> {
+        self.read_cancellable(buf, signal::has_deliverable).map_err(Errno::from)
+    }
+}
+
+impl FileObject for PipeWriter {
+    fn type_name(&self) -> &'static str {
+        "pipe"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, Errno> {
+        self.write_cancellable(buf, signal::has_deliverable).map_err(Errno::from)
+    }
+}
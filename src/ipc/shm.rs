@@ -0,0 +1,128 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+
+use x86_64::structures::paging::{FrameAllocator, PageTableFlags, PhysFrame};
+use x86_64::VirtAddr;
+
+use crate::mem;
+use crate::mem::address_space::{AddressSpace, SHARED};
+use crate::mem::frame_refs;
+use crate::process::handle::KernelObject;
+use crate::sync::SpinLock;
+use crate::syscall::Errno;
+
+const PAGE_SIZE: u64 = 4096;
+/// 单个共享内存对象的最大长度,帧表与映射时的临时表都来自内核堆
+pub const MAX_SIZE: u64 = 4 * 1024 * 1024;
+pub const NAME_MAX: usize = 64;
+
+/// 已命名的对象,最后一个引用释放后名字随之失效
+static NAMED: SpinLock<BTreeMap<String, Weak<SharedMemory>>> = SpinLock::new(BTreeMap::new());
+
+/// 共享内存对象:一组清零的物理帧,每个映射各持有一个帧引用
+pub struct SharedMemory {
+    frames: Vec<PhysFrame>,
+    name: Option<String>,
+}
+
+impl SharedMemory {
+    /// 创建匿名对象,长度向上取整到页
+    pub fn new(size: u64) -> Result<SharedMemory, Errno> {
+        Self::allocate(size)
+    }
+
+    /// 按名字打开已有对象,不存在时创建;已有对象比 `size` 小时返回 EINVAL
+    pub fn open(name: &str, size: u64) -> Result<Arc<SharedMemory>, Errno> {
+        if name.is_empty() || name.len() > NAME_MAX {
+            return Err(Errno::EINVAL);
+        }
+        let existing = |named: &BTreeMap<String, Weak<SharedMemory>>| named.get(name).and_then(Weak::upgrade);
+        if let Some(existing) = existing(&NAMED.lock()) {
+            return if existing.size() >= size { Ok(existing) } else { Err(Errno::EINVAL) };
+        }
+        // 分配与清零不持有名字表锁;半成品没有名字,销毁时不会访问名字表
+        let mut shm = Self::allocate(size)?;
+        let mut named = NAMED.lock();
+        // 分配期间可能已有同名对象被创建,以先创建的为准
+        if let Some(existing) = existing(&named) {
+            drop(named);
+            return if existing.size() >= size { Ok(existing) } else { Err(Errno::EINVAL) };
+        }
+        shm.name = Some(String::from(name));
+        let shm = Arc::new(shm);
+        named.insert(String::from(name), Arc::downgrade(&shm));
+        Ok(shm)
+    }
+
+    fn allocate(size: u64) -> Result<SharedMemory, Errno> {
+        if size == 0 || size > MAX_SIZE {
+            return Err(Errno::EINVAL);
+        }
+        let count = ((size + PAGE_SIZE - 1) / PAGE_SIZE) as usize;
+        let mut shm = SharedMemory { frames: Vec::new(), name: None };
+        shm.frames.try_reserve_exact(count).map_err(|_| Errno::ENOMEM)?;
+        mem::with_frame_allocator(|frame_allocator| {
+            for _ in 0..count {
+                shm.frames.push(frame_allocator.allocate_frame().ok_or(Errno::ENOMEM)?);
+            }
+            Ok::<_, Errno>(())
+        })?;
+        // 清零在帧分配器锁外进行,不长时间关中断
+        for frame in &shm.frames {
+            unsafe { core::ptr::write_bytes(mem::phys_to_virt(frame.start_address()).as_mut_ptr::<u8>(), 0, PAGE_SIZE as usize); }
+        }
+        Ok(shm)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.frames.len() as u64 * PAGE_SIZE
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// 映射到 `space` 的 mmap 区,返回起始地址;映射可用 munmap 解除
+    pub fn map_into(&self, space: &mut AddressSpace, writable: bool) -> Result<VirtAddr, Errno> {
+        let mut flags = PageTableFlags::NO_EXECUTE | SHARED;
+        if writable {
+            flags |= PageTableFlags::WRITABLE;
+        }
+        let mut frames = Vec::new();
+        frames.try_reserve_exact(self.frames.len()).map_err(|_| Errno::ENOMEM)?;
+        frames.extend(self.frames.iter().map(|frame| {
+            frame_refs::share(*frame);
+            (*frame, flags)
+        }));
+        space.map_frames(None, frames).map_err(|_| Errno::ENOMEM)
+    }
+}
+
+/// 对象本身持有的引用,仍被映射的帧在映射解除时才归还
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        if let Some(name) = &self.name {
+            let mut named = NAMED.lock();
+            // 同名的新对象可能已经取代了本对象
+            if named.get(name).map_or(false, |weak| weak.strong_count() == 0) {
+                named.remove(name);
+            }
+        }
+        for frame in self.frames.drain(..) {
+            frame_refs::free(frame);
+        }
+    }
+}
+
+impl KernelObject for SharedMemory {
+    fn type_name(&self) -> &'static str {
+        "shm"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -12,9 +12,25 @@ pub mod gdt;
 pub mod idt;
 pub mod mem;
 pub mod allocator;
+pub mod acpi;
+pub mod apic;
+pub mod softirq;
+pub mod time;
+pub mod task;
+pub mod thread;
+pub mod sync;
+pub mod user;
+pub mod process;
+pub mod ipc;
+pub mod fs;
+pub mod syscall;
+pub mod elf;
 
 #[panic_handler]
 pub fn panic(info: &PanicInfo) -> ! {
+    // lockdep 可能在持有 WRITER 时 panic
+    #[cfg(feature = "lock-debug")]
+    unsafe { vga_buffer::WRITER.force_unlock() };
     println!("{}", info);
     hlt_loop()
 }
@@ -22,6 +38,8 @@ pub fn panic(info: &PanicInfo) -> ! {
 pub fn init() {
     gdt::init_gdt();
     idt::init_idt();
+    syscall::init();
+    time::init(time::DEFAULT_TICK_HZ);
 }
 
 pub fn hlt_loop() ->!{
@@ -6,23 +6,29 @@ extern crate alloc;
 use alloc::boxed::Box;
 
 use bootloader::{BootInfo, entry_point};
-use x86_64::VirtAddr;
 
-use mongo_os::{allocator, mem, println};
-use mongo_os::mem::BootInfoFrameAllocator;
+use mongo_os::{allocator, apic, fs, mem, println, process, thread, time};
+use mongo_os::task::executor::Executor;
+use mongo_os::task::{keyboard, Task};
 
 entry_point!(kernel_main);
 
 fn kernel_main(boot_info: &'static BootInfo) -> ! {
     println!("Welcome to MongoOS");
     mongo_os::init();
-    let mut offset_page_table = unsafe { mem::init_offset_page_table(VirtAddr::new(boot_info.physical_memory_offset)) };
-    let mut frame_allocator = BootInfoFrameAllocator::init(boot_info);
-    let init_heap_result = allocator::init_heap(&mut offset_page_table, &mut frame_allocator);
+    unsafe { mem::init(boot_info); }
+    let init_heap_result = mem::with_mapper(|mapper, frame_allocator| allocator::init_heap(mapper, frame_allocator));
     match init_heap_result {
         Ok(_) => println!("Init heap OK!"),
         Err(err) => panic!("Init heap failed, {:?}", err)
     };
+    fs::init();
+    apic::init();
+    #[cfg(feature = "hpet")]
+    match time::use_hpet() {
+        Ok(_) => println!("Timer backed by HPET, {} Hz", time::hpet::frequency()),
+        Err(err) => println!("HPET unavailable, falling back to {:?}, {:?}", time::clock_source(), err)
+    };
     // example_create_page_map_to_0xb8000(&mut offset_page_table, &mut frame_allocator);
 
     unsafe {
@@ -36,7 +42,45 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
         let boxed8 = alloc_test();
     }
 
-    mongo_os::hlt_loop()
+    thread::init();
+    thread::spawn("init", init_processes).expect("spawn init thread failed");
+
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(example_task()));
+    executor.spawn(Task::new(keyboard::print_keypresses()));
+    executor.run()
+}
+
+/// 启动用户进程并回收它们,充当 init
+fn init_processes() {
+    // hlt 在 ring 3 触发 #GP,只结束该进程
+    if let Err(err) = process::spawn("user-hlt", &[0xf4]) {
+        println!("Spawn process failed, {:?}", err);
+    }
+    if let Err(err) = process::spawn("user-hello", HELLO_PROGRAM) {
+        println!("Spawn process failed, {:?}", err);
+    }
+    process::print_processes();
+    while let Ok(Some((pid, status))) = process::waitpid(None, false) {
+        println!("Reaped process {}, {:?}", pid.as_u64(), status);
+    }
+}
+
+/// write(1, "Hello from ring 3\n", 18); exit(0)
+const HELLO_PROGRAM: &[u8] = &[
+    0x48, 0x8d, 0x35, 0x17, 0x00, 0x00, 0x00, 0xbf, 0x01, 0x00, 0x00, 0x00,
+    0xba, 0x12, 0x00, 0x00, 0x00, 0x31, 0xc0, 0x0f, 0x05, 0x31, 0xff, 0xb8,
+    0x01, 0x00, 0x00, 0x00, 0x0f, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x20,
+    0x66, 0x72, 0x6f, 0x6d, 0x20, 0x72, 0x69, 0x6e, 0x67, 0x20, 0x33, 0x0a,
+];
+
+async fn async_number() -> u32 {
+    42
+}
+
+async fn example_task() {
+    let number = async_number().await;
+    println!("async number: {}", number);
 }
 
 /// 32 bytes
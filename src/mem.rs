@@ -1,6 +1,83 @@
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
 use bootloader::BootInfo;
 use x86_64::{PhysAddr, VirtAddr};
-use x86_64::structures::paging::{FrameAllocator, OffsetPageTable, PhysFrame, Size4KiB};
+use x86_64::structures::paging::{FrameAllocator, FrameDeallocator, Mapper, OffsetPageTable, Page, PageTableFlags, PhysFrame, Size4KiB};
+use x86_64::structures::paging::mapper::MapToError;
+
+use crate::sync::SpinLock;
+
+pub mod address_space;
+pub mod frame_refs;
+
+/// MMIO 映射区(LAPIC/IOAPIC 等设备寄存器)
+pub const MMIO_BOTTOM: u64 = 0x5555_0000_0000;
+pub const MMIO_SIZE: u64 = 0x1_0000_0000;
+
+static PHYSICAL_MEMORY_OFFSET: AtomicU64 = AtomicU64::new(0);
+/// 启动时的 P4 页表,内核映射都建立在这里
+static KERNEL_PAGE_TABLE: AtomicU64 = AtomicU64::new(0);
+static NEXT_MMIO_ADDR: AtomicU64 = AtomicU64::new(MMIO_BOTTOM);
+static MAPPER: SpinLock<Option<OffsetPageTable<'static>>> = SpinLock::new(None);
+static FRAME_ALLOCATOR: SpinLock<Option<BootInfoFrameAllocator>> = SpinLock::new(None);
+
+/// 保存页表与物理帧分配器,供 apic 等子系统在启动后继续建立映射
+pub unsafe fn init(boot_info: &'static BootInfo) {
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    PHYSICAL_MEMORY_OFFSET.store(phys_mem_offset.as_u64(), Ordering::SeqCst);
+    let (level_4_table_frame, _) = x86_64::registers::control::Cr3::read();
+    KERNEL_PAGE_TABLE.store(level_4_table_frame.start_address().as_u64(), Ordering::SeqCst);
+    *MAPPER.lock() = Some(init_offset_page_table(phys_mem_offset));
+    *FRAME_ALLOCATOR.lock() = Some(BootInfoFrameAllocator::init(boot_info));
+}
+
+/// 在内核页表上建立映射;当前运行在用户地址空间时,随后同步新增的内核 P4 项
+pub fn with_mapper<F, R>(f: F) -> R
+    where F: FnOnce(&mut OffsetPageTable<'static>, &mut BootInfoFrameAllocator) -> R {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut mapper = MAPPER.lock();
+        let mut frame_allocator = FRAME_ALLOCATOR.lock();
+        let result = f(mapper.as_mut().expect("mem not initialized"), frame_allocator.as_mut().expect("mem not initialized"));
+        address_space::sync_current();
+        result
+    })
+}
+
+pub fn with_frame_allocator<F, R>(f: F) -> R where F: FnOnce(&mut BootInfoFrameAllocator) -> R {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        f(FRAME_ALLOCATOR.lock().as_mut().expect("mem not initialized"))
+    })
+}
+
+pub fn kernel_page_table() -> PhysFrame {
+    PhysFrame::containing_address(PhysAddr::new(KERNEL_PAGE_TABLE.load(Ordering::SeqCst)))
+}
+
+pub fn physical_memory_offset() -> VirtAddr {
+    VirtAddr::new(PHYSICAL_MEMORY_OFFSET.load(Ordering::SeqCst))
+}
+
+pub fn phys_to_virt(phys: PhysAddr) -> VirtAddr {
+    VirtAddr::new(PHYSICAL_MEMORY_OFFSET.load(Ordering::SeqCst) + phys.as_u64())
+}
+
+/// 将一段设备寄存器以 uncached 方式映射到 MMIO 区,返回对应虚拟地址
+pub fn map_mmio(phys: PhysAddr, size: u64) -> Result<VirtAddr, MapToError<Size4KiB>> {
+    let start_frame: PhysFrame<Size4KiB> = PhysFrame::containing_address(phys);
+    let end_frame: PhysFrame<Size4KiB> = PhysFrame::containing_address(phys + size - 1u64);
+    let pages = end_frame - start_frame + 1;
+    let virt_start = NEXT_MMIO_ADDR.fetch_add(pages * 4096, Ordering::SeqCst);
+    assert!(virt_start + pages * 4096 <= MMIO_BOTTOM + MMIO_SIZE, "MMIO region exhausted");
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE | PageTableFlags::WRITE_THROUGH;
+    with_mapper(|mapper, frame_allocator| {
+        for (i, frame) in PhysFrame::range_inclusive(start_frame, end_frame).enumerate() {
+            let page: Page<Size4KiB> = Page::containing_address(VirtAddr::new(virt_start + i as u64 * 4096));
+            unsafe { mapper.map_to(page, frame, flags, frame_allocator)?.flush(); }
+        }
+        Ok(VirtAddr::new(virt_start + (phys.as_u64() - start_frame.start_address().as_u64())))
+    })
+}
 
 pub unsafe fn init_offset_page_table(phys_mem_offset: VirtAddr) -> OffsetPageTable<'static> {
     use x86_64::structures::paging::PageTable;
@@ -14,6 +91,8 @@ pub unsafe fn init_offset_page_table(phys_mem_offset: VirtAddr) -> OffsetPageTab
 pub struct BootInfoFrameAllocator {
     boot_info: &'static BootInfo,
     next: usize,
+    /// 归还的物理帧,优先分配
+    free: Vec<PhysFrame>,
 }
 
 impl BootInfoFrameAllocator {
@@ -21,6 +100,7 @@ impl BootInfoFrameAllocator {
         BootInfoFrameAllocator {
             boot_info: boot_info,
             next: 0,
+            free: Vec::new(),
         }
     }
 
@@ -39,6 +119,9 @@ impl BootInfoFrameAllocator {
 
 unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
     fn allocate_frame(&mut self) -> Option<PhysFrame<Size4KiB>> {
+        if let Some(frame) = self.free.pop() {
+            return Some(frame);
+        }
         let frame = self.usable_frames().nth(self.next);
         self.next += 1;
         frame
@@ -47,3 +130,9 @@ unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
 
 
 
+
+impl FrameDeallocator<Size4KiB> for BootInfoFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size4KiB>) {
+        self.free.push(frame);
+    }
+}
@@ -0,0 +1,454 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::registers::control::{Cr3, Cr3Flags};
+use x86_64::structures::paging::{FrameAllocator, FrameDeallocator, Mapper, OffsetPageTable, Page, PageTable, PageTableFlags, PhysFrame, Size4KiB, Translate};
+use x86_64::structures::paging::mapper::{MapToError, TranslateResult};
+
+use crate::mem;
+use crate::mem::frame_refs;
+
+/// 用户态地址区间(P4 第 16~31 项),各地址空间独立;其余 P4 项与内核页表共享
+pub const USER_REGION_BOTTOM: u64 = 0x0000_0800_0000_0000;
+pub const USER_REGION_SIZE: u64 = 0x0000_0800_0000_0000;
+pub const USER_REGION_TOP: u64 = USER_REGION_BOTTOM + USER_REGION_SIZE;
+/// 程序镜像位于用户区下半部分,mmap 从中间开始向上分配
+pub const USER_MMAP_BASE: u64 = USER_REGION_BOTTOM + USER_REGION_SIZE / 2;
+/// 用户栈位于用户区顶端,下方留一页不映射
+pub const USER_STACK_SIZE: u64 = 16 * PAGE_SIZE;
+pub const USER_STACK_TOP: u64 = USER_REGION_TOP;
+const USER_STACK_BOTTOM: u64 = USER_STACK_TOP - USER_STACK_SIZE;
+const PAGE_SIZE: u64 = 4096;
+/// 写时复制页:共享帧以只读映射,首次写入时复制
+pub const COW: PageTableFlags = PageTableFlags::BIT_9;
+/// 共享映射(如共享内存):fork 时保持共享,不转为写时复制
+pub const SHARED: PageTableFlags = PageTableFlags::BIT_10;
+/// 中间页表总是可写、用户可访问,权限只由最后一级页表项决定
+const TABLE_FLAGS: PageTableFlags = PageTableFlags::from_bits_truncate(
+    PageTableFlags::PRESENT.bits() | PageTableFlags::WRITABLE.bits() | PageTableFlags::USER_ACCESSIBLE.bits());
+
+const USER_P4_START: usize = (USER_REGION_BOTTOM >> 39) as usize;
+const USER_P4_END: usize = (USER_REGION_TOP >> 39) as usize;
+
+pub fn is_user_address(addr: VirtAddr) -> bool {
+    (USER_REGION_BOTTOM..USER_REGION_TOP).contains(&addr.as_u64())
+}
+
+/// 检查来自用户的原始地址;不能先 `new_truncate`,否则非规范地址会被符号扩展成合法的用户地址
+pub fn is_user_pointer(addr: u64) -> bool {
+    VirtAddr::try_new(addr).is_ok() && (USER_REGION_BOTTOM..USER_REGION_TOP).contains(&addr)
+}
+
+/// 一段连续的、权限相同的用户映射
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub start: u64,
+    pub end: u64,
+    pub flags: PageTableFlags,
+}
+
+/// 用户进程的页表:用户区独占,内核部分从内核页表复制
+pub struct AddressSpace {
+    page_table: PhysFrame,
+    regions: BTreeMap<u64, Region>,
+    mmap_next: u64,
+}
+
+unsafe fn table_mut<'a>(frame: PhysFrame) -> &'a mut PageTable {
+    &mut *mem::phys_to_virt(frame.start_address()).as_mut_ptr::<PageTable>()
+}
+
+/// 将内核页表的非用户 P4 项复制到 `page_table`
+fn sync_kernel_entries(page_table: PhysFrame) {
+    let kernel = mem::kernel_page_table();
+    if page_table == kernel {
+        return;
+    }
+    unsafe {
+        let (kernel, table) = (table_mut(kernel), table_mut(page_table));
+        for i in (0..512).filter(|i| !(USER_P4_START..USER_P4_END).contains(i)) {
+            table[i] = kernel[i].clone();
+        }
+    }
+}
+
+/// 内核页表新增 P4 项后,让当前地址空间也能看到
+pub(crate) fn sync_current() {
+    sync_kernel_entries(Cr3::read().0);
+}
+
+/// 切换到指定页表,需在关中断状态下调用
+pub fn switch_to(page_table: PhysFrame) {
+    sync_kernel_entries(page_table);
+    if Cr3::read().0 != page_table {
+        unsafe { Cr3::write(page_table, Cr3Flags::empty()) };
+    }
+}
+
+/// 在当前页表中查询用户地址的映射
+pub fn translate_current(addr: VirtAddr) -> Option<(PhysAddr, PageTableFlags)> {
+    let mapper = unsafe { OffsetPageTable::new(table_mut(Cr3::read().0), mem::physical_memory_offset()) };
+    match mapper.translate(addr) {
+        TranslateResult::Mapped { frame, offset, flags } => Some((frame.start_address() + offset, flags)),
+        _ => None,
+    }
+}
+
+impl AddressSpace {
+    pub fn new() -> Result<Self, MapToError<Size4KiB>> {
+        let page_table = mem::with_frame_allocator(|frame_allocator| frame_allocator.allocate_frame())
+            .ok_or(MapToError::FrameAllocationFailed)?;
+        unsafe { table_mut(page_table).zero(); }
+        sync_kernel_entries(page_table);
+        Ok(AddressSpace {
+            page_table,
+            regions: BTreeMap::new(),
+            mmap_next: USER_MMAP_BASE,
+        })
+    }
+
+    pub fn page_table(&self) -> PhysFrame {
+        self.page_table
+    }
+
+    fn mapper(&mut self) -> OffsetPageTable<'_> {
+        unsafe { OffsetPageTable::new(table_mut(self.page_table), mem::physical_memory_offset()) }
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item=&Region> {
+        self.regions.values()
+    }
+
+    pub fn region_containing(&self, addr: VirtAddr) -> Option<&Region> {
+        self.regions.range(..=addr.as_u64()).next_back()
+            .map(|(_, region)| region)
+            .filter(|region| addr.as_u64() < region.end)
+    }
+
+    /// 映射 [start, start+len) 并清零;与已有映射重叠的页保留内容并合并权限
+    pub fn map(&mut self, start: VirtAddr, len: u64, flags: PageTableFlags) -> Result<(), MapToError<Size4KiB>> {
+        let first = Page::<Size4KiB>::containing_address(start);
+        let last = Page::<Size4KiB>::containing_address(start + len.max(1) - 1u64);
+        let end = last.start_address().as_u64() + PAGE_SIZE;
+        assert!(is_user_address(first.start_address()) && end <= USER_REGION_TOP, "mapping outside user region");
+        let flags = flags | PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        let mut mapper = self.mapper();
+        mem::with_frame_allocator(|frame_allocator| {
+            for page in Page::range_inclusive(first, last) {
+                match mapper.translate(page.start_address()) {
+                    TranslateResult::Mapped { flags: old, .. } => {
+                        let mut merged = old | flags;
+                        if !old.contains(PageTableFlags::NO_EXECUTE) || !flags.contains(PageTableFlags::NO_EXECUTE) {
+                            merged.remove(PageTableFlags::NO_EXECUTE);
+                        }
+                        // 共享中的页保持只读,写入时再复制
+                        if old.contains(COW) {
+                            merged.remove(PageTableFlags::WRITABLE);
+                        }
+                        if let Ok(flush) = unsafe { mapper.update_flags(page, merged) } {
+                            flush.ignore();
+                        }
+                    }
+                    _ => {
+                        let frame = frame_allocator.allocate_frame().ok_or(MapToError::FrameAllocationFailed)?;
+                        unsafe {
+                            core::ptr::write_bytes(mem::phys_to_virt(frame.start_address()).as_mut_ptr::<u8>(), 0, PAGE_SIZE as usize);
+                            mapper.map_to_with_table_flags(page, frame, flags, TABLE_FLAGS, frame_allocator)?.ignore();
+                        }
+                    }
+                }
+            }
+            Ok::<(), MapToError<Size4KiB>>(())
+        })?;
+        self.insert_region(Region { start: first.start_address().as_u64(), end, flags });
+        self.flush_if_active();
+        Ok(())
+    }
+
+    /// 解除映射并归还物理帧,未映射的页被忽略
+    pub fn unmap(&mut self, start: VirtAddr, len: u64) {
+        if len == 0 {
+            return;
+        }
+        let first = Page::<Size4KiB>::containing_address(start);
+        let last = Page::<Size4KiB>::containing_address(start + len - 1u64);
+        let mut mapper = self.mapper();
+        mem::with_frame_allocator(|frame_allocator| {
+            for page in Page::range_inclusive(first, last) {
+                if let Ok((frame, flush)) = mapper.unmap(page) {
+                    flush.ignore();
+                    if frame_refs::release(frame) {
+                        unsafe { frame_allocator.deallocate_frame(frame); }
+                    }
+                }
+            }
+        });
+        self.remove_regions(first.start_address().as_u64(), last.start_address().as_u64() + PAGE_SIZE);
+        self.flush_if_active();
+    }
+
+    /// 在 mmap 区分配一段清零的内存,长度溢出或超出 mmap 区时与分配失败一样返回错误
+    pub fn map_anonymous(&mut self, len: u64, flags: PageTableFlags) -> Result<VirtAddr, MapToError<Size4KiB>> {
+        let len = len.checked_add(PAGE_SIZE - 1).ok_or(MapToError::FrameAllocationFailed)? & !(PAGE_SIZE - 1);
+        let end = self.mmap_next.checked_add(len).ok_or(MapToError::FrameAllocationFailed)?;
+        if len == 0 || end > USER_STACK_BOTTOM - PAGE_SIZE {
+            return Err(MapToError::FrameAllocationFailed);
+        }
+        let start = VirtAddr::new(self.mmap_next);
+        // mmap_next 之上还没有映射,中途失败时整段解除即可归还已分配的帧
+        if let Err(err) = self.map(start, len, flags) {
+            self.unmap(start, len);
+            return Err(err);
+        }
+        self.mmap_next = end;
+        Ok(start)
+    }
+
+    /// 从地址空间中取出 [start, start+count 页) 的物理帧,映射解除但帧不归还;有未映射的页时不做修改并返回 None
+    pub fn take_pages(&mut self, start: VirtAddr, count: usize) -> Option<Vec<(PhysFrame, PageTableFlags)>> {
+        if count == 0 || !start.is_aligned(PAGE_SIZE) {
+            return None;
+        }
+        let first = Page::<Size4KiB>::containing_address(start);
+        let pages = Page::range(first, first + count as u64);
+        let mut mapper = self.mapper();
+        let mut frames = Vec::with_capacity(count);
+        for page in pages {
+            match mapper.translate(page.start_address()) {
+                TranslateResult::Mapped { frame, flags, .. } =>
+                    frames.push((PhysFrame::containing_address(frame.start_address()), flags)),
+                _ => return None,
+            }
+        }
+        for page in pages {
+            if let Ok((_, flush)) = mapper.unmap(page) {
+                flush.ignore();
+            }
+        }
+        self.remove_regions(start.as_u64(), start.as_u64() + count as u64 * PAGE_SIZE);
+        self.flush_if_active();
+        Some(frames)
+    }
+
+    /// 将物理帧依次映射到 `start`(为 None 时在 mmap 区分配),帧的引用随映射转入本地址空间
+    ///
+    /// 失败时已映射的部分被解除,所有帧都已释放。
+    pub fn map_frames(&mut self, start: Option<VirtAddr>, frames: Vec<(PhysFrame, PageTableFlags)>) -> Result<VirtAddr, MapToError<Size4KiB>> {
+        let len = frames.len() as u64 * PAGE_SIZE;
+        let start = match start {
+            Some(start) => start,
+            None => {
+                if self.mmap_next + len > USER_STACK_BOTTOM - PAGE_SIZE {
+                    frames.into_iter().for_each(|(frame, _)| frame_refs::free(frame));
+                    return Err(MapToError::FrameAllocationFailed);
+                }
+                let start = VirtAddr::new(self.mmap_next);
+                self.mmap_next += len;
+                start
+            }
+        };
+        let first = Page::<Size4KiB>::containing_address(start);
+        let mut mapper = self.mapper();
+        let mut mapped = 0;
+        let result = mem::with_frame_allocator(|frame_allocator| {
+            for (i, (frame, flags)) in frames.iter().enumerate() {
+                let flags = *flags | PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+                unsafe { mapper.map_to_with_table_flags(first + i as u64, *frame, flags, TABLE_FLAGS, frame_allocator)?.ignore(); }
+                mapped += 1;
+            }
+            Ok::<(), MapToError<Size4KiB>>(())
+        });
+        if let Err(err) = result {
+            frames[mapped..].iter().for_each(|(frame, _)| frame_refs::free(*frame));
+            self.unmap(start, mapped as u64 * PAGE_SIZE);
+            return Err(err);
+        }
+        let flags = frames.first().map_or(PageTableFlags::empty(), |(_, flags)| *flags)
+            | PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        self.insert_region(Region { start: start.as_u64(), end: start.as_u64() + len, flags });
+        self.flush_if_active();
+        Ok(start)
+    }
+
+    /// 映射用户栈,返回栈顶
+    pub fn map_stack(&mut self) -> Result<VirtAddr, MapToError<Size4KiB>> {
+        self.map(VirtAddr::new(USER_STACK_BOTTOM), USER_STACK_SIZE, PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE)?;
+        Ok(VirtAddr::new(USER_STACK_TOP))
+    }
+
+    pub fn translate(&mut self, addr: VirtAddr) -> Option<(PhysAddr, PageTableFlags)> {
+        match self.mapper().translate(addr) {
+            TranslateResult::Mapped { frame, offset, flags } => Some((frame.start_address() + offset, flags)),
+            _ => None,
+        }
+    }
+
+    /// 经物理内存映射写入,不要求该地址空间处于活动状态;遇到未映射的页返回 false
+    pub fn write(&mut self, addr: VirtAddr, data: &[u8]) -> bool {
+        let mut written = 0;
+        while written < data.len() {
+            let current = addr + written as u64;
+            self.resolve_cow(current);
+            let (phys, _) = match self.translate(current) {
+                Some(mapping) => mapping,
+                None => return false,
+            };
+            let chunk = ((PAGE_SIZE - u64::from(current.page_offset())) as usize).min(data.len() - written);
+            unsafe {
+                core::ptr::copy_nonoverlapping(data[written..].as_ptr(), mem::phys_to_virt(phys).as_mut_ptr::<u8>(), chunk);
+            }
+            written += chunk;
+        }
+        true
+    }
+
+    /// 复制地址空间:可写页在双方都改为只读并标记 COW,物理帧共享到首次写入;共享映射保持原样
+    pub fn fork(&mut self) -> Result<AddressSpace, MapToError<Size4KiB>> {
+        let mut child = AddressSpace::new()?;
+        child.regions = self.regions.clone();
+        child.mmap_next = self.mmap_next;
+        let regions: Vec<Region> = self.regions.values().copied().collect();
+        let mut parent_mapper = self.mapper();
+        let mut child_mapper = child.mapper();
+        mem::with_frame_allocator(|frame_allocator| {
+            for region in regions {
+                let first = Page::<Size4KiB>::containing_address(VirtAddr::new(region.start));
+                let last = Page::<Size4KiB>::containing_address(VirtAddr::new(region.end - 1));
+                for page in Page::range_inclusive(first, last) {
+                    let (frame, mut flags) = match parent_mapper.translate(page.start_address()) {
+                        TranslateResult::Mapped { frame, flags, .. } => (PhysFrame::containing_address(frame.start_address()), flags),
+                        _ => continue,
+                    };
+                    if flags.contains(PageTableFlags::WRITABLE) && !flags.contains(SHARED) {
+                        flags.remove(PageTableFlags::WRITABLE);
+                        flags.insert(COW);
+                        if let Ok(flush) = unsafe { parent_mapper.update_flags(page, flags) } {
+                            flush.ignore();
+                        }
+                    }
+                    unsafe {
+                        child_mapper.map_to_with_table_flags(page, frame, flags, TABLE_FLAGS, frame_allocator)?.ignore();
+                    }
+                    frame_refs::share(frame);
+                }
+            }
+            Ok::<(), MapToError<Size4KiB>>(())
+        })?;
+        self.flush_if_active();
+        Ok(child)
+    }
+
+    /// 处理对写时复制页的写入:帧仍被共享则复制一份,否则直接恢复可写;不是 COW 页返回 false
+    pub fn resolve_cow(&mut self, addr: VirtAddr) -> bool {
+        let page = Page::<Size4KiB>::containing_address(addr);
+        let mut mapper = self.mapper();
+        let (frame, mut flags) = match mapper.translate(page.start_address()) {
+            TranslateResult::Mapped { frame, flags, .. } if flags.contains(COW) =>
+                (PhysFrame::containing_address(frame.start_address()), flags),
+            _ => return false,
+        };
+        flags.remove(COW);
+        flags.insert(PageTableFlags::WRITABLE);
+        let resolved = mem::with_frame_allocator(|frame_allocator| {
+            if frame_refs::count(frame) == 1 {
+                return unsafe { mapper.update_flags(page, flags) }.map(|flush| flush.ignore()).is_ok();
+            }
+            let copy = match frame_allocator.allocate_frame() {
+                Some(copy) => copy,
+                None => return false,
+            };
+            unsafe {
+                core::ptr::copy_nonoverlapping(mem::phys_to_virt(frame.start_address()).as_ptr::<u8>(),
+                                               mem::phys_to_virt(copy.start_address()).as_mut_ptr::<u8>(), PAGE_SIZE as usize);
+                if let Ok((_, flush)) = mapper.unmap(page) {
+                    flush.ignore();
+                }
+                if mapper.map_to_with_table_flags(page, copy, flags, TABLE_FLAGS, frame_allocator).is_err() {
+                    frame_allocator.deallocate_frame(copy);
+                    return false;
+                }
+            }
+            if frame_refs::release(frame) {
+                unsafe { frame_allocator.deallocate_frame(frame); }
+            }
+            true
+        });
+        if resolved && self.is_active() {
+            x86_64::instructions::tlb::flush(page.start_address());
+        }
+        resolved
+    }
+
+    pub fn is_active(&self) -> bool {
+        Cr3::read().0 == self.page_table
+    }
+
+    pub fn activate(&self) {
+        x86_64::instructions::interrupts::without_interrupts(|| switch_to(self.page_table));
+    }
+
+    fn flush_if_active(&self) {
+        if self.is_active() {
+            x86_64::instructions::tlb::flush_all();
+        }
+    }
+
+    fn insert_region(&mut self, region: Region) {
+        self.remove_regions(region.start, region.end);
+        self.regions.insert(region.start, region);
+    }
+
+    /// 移除 [start, end) 与已有区间的交集,保留两端剩余部分
+    fn remove_regions(&mut self, start: u64, end: u64) {
+        let overlapping: Vec<Region> = self.regions.values()
+            .filter(|region| region.start < end && start < region.end)
+            .copied()
+            .collect();
+        for region in overlapping {
+            self.regions.remove(&region.start);
+            if region.start < start {
+                self.regions.insert(region.start, Region { end: start, ..region });
+            }
+            if end < region.end {
+                self.regions.insert(end, Region { start: end, ..region });
+            }
+        }
+    }
+
+    /// 释放用户区的所有页表帧
+    fn free_page_tables(&mut self) {
+        fn free_table(frame: PhysFrame, level: u8, frame_allocator: &mut mem::BootInfoFrameAllocator) {
+            if level > 1 {
+                let table = unsafe { table_mut(frame) };
+                for entry in table.iter().filter(|entry| !entry.is_unused()) {
+                    free_table(PhysFrame::containing_address(entry.addr()), level - 1, frame_allocator);
+                }
+            }
+            unsafe { frame_allocator.deallocate_frame(frame); }
+        }
+        let table = unsafe { table_mut(self.page_table) };
+        mem::with_frame_allocator(|frame_allocator| {
+            for i in USER_P4_START..USER_P4_END {
+                if !table[i].is_unused() {
+                    free_table(PhysFrame::containing_address(table[i].addr()), 3, frame_allocator);
+                    table[i].set_unused();
+                }
+            }
+        });
+    }
+}
+
+impl Drop for AddressSpace {
+    fn drop(&mut self) {
+        if self.is_active() {
+            x86_64::instructions::interrupts::without_interrupts(|| switch_to(mem::kernel_page_table()));
+        }
+        let regions: Vec<Region> = self.regions.values().copied().collect();
+        for region in regions {
+            self.unmap(VirtAddr::new(region.start), region.end - region.start);
+        }
+        self.free_page_tables();
+        mem::with_frame_allocator(|frame_allocator| unsafe { frame_allocator.deallocate_frame(self.page_table) });
+    }
+}
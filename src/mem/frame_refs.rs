@@ -0,0 +1,46 @@
+use alloc::collections::BTreeMap;
+
+use x86_64::structures::paging::{FrameDeallocator, PhysFrame};
+
+use crate::mem;
+use crate::sync::SpinLock;
+
+/// 被多个映射共享的物理帧的引用计数,未登记的帧视为只有一个引用
+static SHARED: SpinLock<BTreeMap<PhysFrame, usize>> = SpinLock::new(BTreeMap::new());
+
+/// 增加一个引用
+pub fn share(frame: PhysFrame) {
+    *SHARED.lock().entry(frame).or_insert(1) += 1;
+}
+
+pub fn count(frame: PhysFrame) -> usize {
+    SHARED.lock().get(&frame).copied().unwrap_or(1)
+}
+
+/// 释放一个引用,返回 true 表示这是最后一个引用,调用者应归还该帧
+pub fn release(frame: PhysFrame) -> bool {
+    let mut shared = SHARED.lock();
+    match shared.get_mut(&frame) {
+        Some(count) if *count > 2 => {
+            *count -= 1;
+            false
+        }
+        Some(_) => {
+            shared.remove(&frame);
+            false
+        }
+        None => true,
+    }
+}
+
+/// 共享中的帧数量
+pub fn shared_frames() -> usize {
+    SHARED.lock().len()
+}
+
+/// 释放一个引用,最后一个引用时归还给帧分配器
+pub fn free(frame: PhysFrame) {
+    if release(frame) {
+        mem::with_frame_allocator(|frame_allocator| unsafe { frame_allocator.deallocate_frame(frame) });
+    }
+}
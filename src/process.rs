@@ -0,0 +1,514 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use x86_64::structures::paging::{PageTableFlags, Size4KiB};
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::VirtAddr;
+
+use crate::{elf, fs, mem, println, thread, user};
+use crate::elf::{ElfError, ElfFile, LoadedImage};
+use crate::fs::{InodeKind, OpenFlags};
+use crate::mem::address_space::{AddressSpace, USER_REGION_BOTTOM};
+use crate::process::fd::FdTable;
+use crate::process::handle::HandleTable;
+use crate::process::signal::SignalState;
+use crate::sync::{SpinLock, WaitQueue};
+use crate::syscall::{Errno, SyscallFrame};
+use crate::thread::ThreadId;
+
+pub mod fd;
+pub mod handle;
+pub mod signal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pid(u64);
+
+impl Pid {
+    /// 内核自身,内核线程创建的进程与孤儿进程都以它为父进程
+    pub const KERNEL: Pid = Pid(0);
+
+    fn new() -> Self {
+        static NEXT_PID: AtomicU64 = AtomicU64::new(1);
+        Pid(NEXT_PID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn from_u64(pid: u64) -> Self {
+        Pid(pid)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// 调用 exit 正常结束
+    Exited(i32),
+    /// 被信号结束
+    Signaled(u32),
+}
+
+impl ExitStatus {
+    /// wait 返回给用户态的状态字,布局同 Linux:正常退出码在 8..16 位,被信号结束时低 7 位为信号编号
+    pub fn wait_status(&self) -> u32 {
+        match self {
+            ExitStatus::Exited(code) => ((*code as u32) & 0xff) << 8,
+            ExitStatus::Signaled(sig) => sig & 0x7f,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    /// 已退出、等待父进程回收
+    Zombie(ExitStatus),
+}
+
+impl ProcessState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessState::Running => "running",
+            ProcessState::Zombie(_) => "zombie",
+        }
+    }
+}
+
+pub struct Process {
+    pid: Pid,
+    parent: Pid,
+    name: String,
+    state: ProcessState,
+    threads: Vec<ThreadId>,
+    /// 退出后即释放,僵尸进程只保留退出状态
+    address_space: Option<AddressSpace>,
+    handles: HandleTable,
+    files: FdTable,
+    /// 规范化的绝对路径,相对路径从这里开始解析
+    cwd: String,
+    signals: SignalState,
+    /// 父进程已退出,由内核收养;没有人会等待它,退出时直接回收
+    orphaned: bool,
+}
+
+impl Process {
+    fn new(pid: Pid, parent: Pid, name: &str, space: AddressSpace) -> Self {
+        Process {
+            pid,
+            parent,
+            name: name.to_string(),
+            state: ProcessState::Running,
+            threads: Vec::new(),
+            address_space: Some(space),
+            handles: HandleTable::new(),
+            files: FdTable::new(),
+            cwd: String::from("/"),
+            signals: SignalState::new(),
+            orphaned: false,
+        }
+    }
+
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    pub fn parent(&self) -> Pid {
+        self.parent
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> ProcessState {
+        self.state
+    }
+
+    pub fn threads(&self) -> &[ThreadId] {
+        &self.threads
+    }
+
+    pub fn address_space(&mut self) -> Option<&mut AddressSpace> {
+        self.address_space.as_mut()
+    }
+
+    pub fn handles(&mut self) -> &mut HandleTable {
+        &mut self.handles
+    }
+
+    pub fn files(&mut self) -> &mut FdTable {
+        &mut self.files
+    }
+
+    pub fn cwd(&self) -> &str {
+        &self.cwd
+    }
+
+    pub fn set_cwd(&mut self, cwd: String) {
+        self.cwd = cwd;
+    }
+
+    pub fn signals(&self) -> &SignalState {
+        &self.signals
+    }
+}
+
+struct ProcessTable {
+    processes: BTreeMap<Pid, Process>,
+    /// 用户线程所属的进程
+    threads: BTreeMap<ThreadId, Pid>,
+}
+
+static TABLE: SpinLock<ProcessTable> = SpinLock::new(ProcessTable {
+    processes: BTreeMap::new(),
+    threads: BTreeMap::new(),
+});
+
+/// 有进程退出时唤醒所有 wait 中的父进程,由它们各自检查
+static CHILD_EXITED: WaitQueue = WaitQueue::new();
+
+#[derive(Debug)]
+pub enum SpawnError {
+    MapFailed(MapToError<Size4KiB>),
+    Elf(ElfError),
+    /// 读取可执行文件失败
+    Io(Errno),
+}
+
+impl From<SpawnError> for Errno {
+    fn from(err: SpawnError) -> Self {
+        match err {
+            SpawnError::MapFailed(_) | SpawnError::Elf(ElfError::OutOfMemory) => Errno::ENOMEM,
+            SpawnError::Elf(ElfError::ArgumentsTooLarge) => Errno::E2BIG,
+            SpawnError::Elf(_) => Errno::ENOEXEC,
+            SpawnError::Io(err) => err,
+        }
+    }
+}
+
+impl From<MapToError<Size4KiB>> for SpawnError {
+    fn from(err: MapToError<Size4KiB>) -> Self {
+        SpawnError::MapFailed(err)
+    }
+}
+
+impl From<ElfError> for SpawnError {
+    fn from(err: ElfError) -> Self {
+        SpawnError::Elf(err)
+    }
+}
+
+impl From<Errno> for SpawnError {
+    fn from(err: Errno) -> Self {
+        SpawnError::Io(err)
+    }
+}
+
+/// 以 ring 3 运行一段与位置无关的机器码,程序从用户区起始处开始执行
+pub fn spawn(name: &str, program: &[u8]) -> Result<Pid, SpawnError> {
+    let mut space = AddressSpace::new()?;
+    let entry = VirtAddr::new(USER_REGION_BOTTOM);
+    space.map(entry, program.len() as u64, PageTableFlags::empty())?;
+    space.write(entry, program);
+    let stack_top = space.map_stack()?;
+    start(name, space, Inherited::new(), move || unsafe { user::enter(entry, stack_top) })
+}
+
+/// 加载静态链接的 ELF64 可执行文件并在新进程中运行
+pub fn spawn_elf(name: &str, data: &[u8], argv: &[&str], envp: &[&str]) -> Result<Pid, SpawnError> {
+    let (space, image) = load_elf(data, argv, envp)?;
+    start(name, space, Inherited::new(), move || unsafe { user::enter(image.entry, image.stack_pointer) })
+}
+
+/// 按绝对路径从文件系统读取 ELF 并运行,供内核中的 shell 使用
+pub fn spawn_program(path: &str, argv: &[&str], envp: &[&str]) -> Result<Pid, SpawnError> {
+    let data = read_image(path)?;
+    spawn_elf(program_name(path), &data, argv, envp)
+}
+
+/// 可执行文件整个读进内核堆,超过上限返回 ENOMEM
+const IMAGE_MAX: u64 = 256 * 1024;
+
+/// 通过 VFS 读出整个可执行文件
+fn read_image(path: &str) -> Result<Vec<u8>, SpawnError> {
+    let file = fs::open(path, OpenFlags::READ_ONLY)?;
+    let metadata = file.metadata();
+    if metadata.kind != InodeKind::File {
+        return Err(Errno::EACCES.into());
+    }
+    if metadata.size > IMAGE_MAX {
+        return Err(Errno::ENOMEM.into());
+    }
+    let mut data = Vec::new();
+    data.try_reserve_exact(metadata.size as usize).map_err(|_| Errno::ENOMEM)?;
+    data.resize(metadata.size as usize, 0);
+    let mut len = 0;
+    while len < data.len() {
+        match file.inode().read_at(len as u64, &mut data[len..])? {
+            0 => break,
+            count => len += count,
+        }
+    }
+    data.truncate(len);
+    Ok(data)
+}
+
+fn program_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn load_elf(data: &[u8], argv: &[&str], envp: &[&str]) -> Result<(AddressSpace, LoadedImage), SpawnError> {
+    let elf = ElfFile::parse(data)?;
+    let mut space = AddressSpace::new()?;
+    let image = elf::load(&mut space, &elf, argv, envp)?;
+    Ok((space, image))
+}
+
+/// 新进程从创建者继承的状态
+struct Inherited {
+    handles: HandleTable,
+    files: FdTable,
+    cwd: String,
+    signals: SignalState,
+}
+
+impl Inherited {
+    /// 内核创建的进程:标准输入输出指向控制台,工作目录为根目录
+    fn new() -> Self {
+        Inherited { handles: HandleTable::new(), files: FdTable::with_console(), cwd: String::from("/"), signals: SignalState::new() }
+    }
+}
+
+/// 登记进程后创建其主线程,线程进入 `entry` 前切换到进程的地址空间,`entry` 不返回
+fn start<F>(name: &str, space: AddressSpace, inherited: Inherited, entry: F) -> Result<Pid, SpawnError>
+    where F: FnOnce() + Send + 'static {
+    let pid = Pid::new();
+    let parent = current_pid();
+    let page_table = space.page_table();
+    let mut process = Process::new(pid, parent, name, space);
+    process.handles = inherited.handles;
+    process.files = inherited.files;
+    process.cwd = inherited.cwd;
+    process.signals = inherited.signals;
+    TABLE.lock().processes.insert(pid, process);
+    let spawned = thread::spawn(name, move || {
+        attach_current(pid);
+        thread::set_page_table(page_table);
+        entry()
+    });
+    if let Err(err) = spawned {
+        let process = TABLE.lock().processes.remove(&pid);
+        drop(process);
+        return Err(err.into());
+    }
+    Ok(pid)
+}
+
+/// 复制当前进程:地址空间写时复制,句柄表与文件描述符表逐项复制,继承工作目录与信号处理方式;子进程从同一系统调用返回 0
+pub fn fork(frame: &SyscallFrame) -> Result<Pid, Errno> {
+    let space = with_address_space(|space| space.fork().map_err(|_| Errno::ENOMEM))??;
+    let (name, inherited) = with_current(|process| {
+        let inherited = Inherited {
+            handles: process.handles.clone(),
+            files: process.files.clone(),
+            cwd: process.cwd.clone(),
+            signals: process.signals.fork(),
+        };
+        (process.name.clone(), inherited)
+    }).ok_or(Errno::EPERM)?;
+    let mut child_frame = *frame;
+    child_frame.rax = 0;
+    start(&name, space, inherited, move || unsafe { user::resume(&child_frame) }).map_err(Errno::from)
+}
+
+/// 以新的 ELF 镜像替换当前进程,句柄表与文件描述符表保持不变,信号处理函数恢复为默认动作
+///
+/// 新地址空间建好之后才释放旧的,加载失败时进程不受影响;成功时改写 `frame`,系统调用返回后从新镜像入口开始执行。
+pub fn exec(path: &str, argv: &[&str], envp: &[&str], frame: &mut SyscallFrame) -> Result<(), SpawnError> {
+    let data = read_image(path)?;
+    let (space, image) = load_elf(&data, argv, envp)?;
+    drop(data);
+    let page_table = space.page_table();
+    let old = with_current(|process| {
+        process.name = program_name(path).to_string();
+        process.signals.reset_handlers();
+        process.address_space.replace(space)
+    });
+    if old.is_some() {
+        thread::set_page_table(page_table);
+    }
+    drop(old);
+    *frame = SyscallFrame {
+        rip: image.entry.as_u64(),
+        rsp: image.stack_pointer.as_u64(),
+        rflags: user::INITIAL_RFLAGS,
+        cs: frame.cs,
+        ss: frame.ss,
+        ..SyscallFrame::default()
+    };
+    Ok(())
+}
+
+/// 把当前进程的地址空间取出来交给 `f`,复制或映射大量页面时不必持有进程表锁,完成后放回
+///
+/// 进程只有一个用户线程,就是调用者自己,取出期间不会有别人用到它;内核线程返回 EPERM
+pub fn with_address_space<F, R>(f: F) -> Result<R, Errno> where F: FnOnce(&mut AddressSpace) -> R {
+    let mut space = with_current(|process| process.address_space.take())
+        .ok_or(Errno::EPERM)?
+        .ok_or(Errno::ESRCH)?;
+    let result = f(&mut space);
+    with_current(|process| process.address_space = Some(space));
+    Ok(result)
+}
+
+/// 当前进程对 `addr` 的写时复制缺页,已处理返回 true
+pub fn resolve_cow(addr: VirtAddr) -> bool {
+    with_current(|process| process.address_space.as_mut().map_or(false, |space| space.resolve_cow(addr)))
+        .unwrap_or(false)
+}
+
+fn attach_current(pid: Pid) {
+    let id = thread::current();
+    let mut table = TABLE.lock();
+    table.threads.insert(id, pid);
+    if let Some(process) = table.processes.get_mut(&pid) {
+        process.threads.push(id);
+    }
+}
+
+/// 当前线程所属的进程,内核线程返回 `None`
+pub fn current() -> Option<Pid> {
+    if !thread::scheduler::is_initialized() {
+        return None;
+    }
+    let id = thread::current();
+    TABLE.lock().threads.get(&id).copied()
+}
+
+/// 当前进程的 PID,内核线程视为 [`Pid::KERNEL`]
+pub fn current_pid() -> Pid {
+    current().unwrap_or(Pid::KERNEL)
+}
+
+/// 在持有进程表锁(关中断)的情况下访问当前进程
+pub fn with_current<F, R>(f: F) -> Option<R> where F: FnOnce(&mut Process) -> R {
+    let id = thread::current();
+    let mut table = TABLE.lock();
+    let pid = *table.threads.get(&id)?;
+    table.processes.get_mut(&pid).map(f)
+}
+
+pub fn with_process<F, R>(pid: Pid, f: F) -> Option<R> where F: FnOnce(&mut Process) -> R {
+    TABLE.lock().processes.get_mut(&pid).map(f)
+}
+
+pub fn parent_of(pid: Pid) -> Option<Pid> {
+    with_process(pid, |process| process.parent)
+}
+
+/// 结束当前进程:释放地址空间、句柄与文件描述符,子进程交给内核收养,自身成为僵尸等待父进程回收
+pub fn exit(status: ExitStatus) -> ! {
+    let id = thread::current();
+    thread::set_page_table(mem::kernel_page_table());
+    // 不会再被 wait 的进程从表中移除,在锁外销毁
+    let mut reaped = Vec::new();
+    let released = {
+        let mut table = TABLE.lock();
+        match table.threads.remove(&id) {
+            Some(pid) => {
+                let zombies: Vec<Pid> = table.processes.values_mut()
+                    .filter(|child| child.parent == pid)
+                    .filter_map(|child| {
+                        child.parent = Pid::KERNEL;
+                        child.orphaned = true;
+                        matches!(child.state, ProcessState::Zombie(_)).then_some(child.pid)
+                    })
+                    .collect();
+                reaped.extend(zombies.iter().filter_map(|zombie| table.processes.remove(zombie)));
+                let released = table.processes.get_mut(&pid).map(|process| {
+                    process.threads.retain(|thread| *thread != id);
+                    process.state = ProcessState::Zombie(status);
+                    let handles = core::mem::take(&mut process.handles);
+                    let files = core::mem::take(&mut process.files);
+                    (process.pid, process.parent, process.orphaned, process.address_space.take(), handles, files)
+                });
+                if let Some((_, _, true, ..)) = released {
+                    reaped.extend(table.processes.remove(&pid));
+                }
+                released
+            }
+            None => None,
+        }
+    };
+    drop(reaped);
+    if let Some((pid, parent, _, space, handles, files)) = released {
+        println!("Process {} exited, {:?}", pid.0, status);
+        drop(space);
+        drop(handles);
+        drop(files);
+        if parent != Pid::KERNEL {
+            let _ = signal::send(parent, signal::SIGCHLD);
+        }
+        CHILD_EXITED.notify_all();
+    }
+    thread::exit()
+}
+
+/// 等待子进程退出并回收,`target` 为 `None` 时等待任意子进程
+///
+/// `nohang` 时没有已退出的子进程立即返回 `Ok(None)`;没有符合条件的子进程返回 `ECHILD`,
+/// 等待期间收到信号返回 `EINTR`。
+pub fn waitpid(target: Option<Pid>, nohang: bool) -> Result<Option<(Pid, ExitStatus)>, Errno> {
+    let parent = current_pid();
+    CHILD_EXITED.wait_until(|| {
+        if parent != Pid::KERNEL && signal::has_deliverable() {
+            return Some(Err(Errno::EINTR));
+        }
+        let mut table = TABLE.lock();
+        let mut found = false;
+        let mut zombie = None;
+        for child in table.processes.values()
+            .filter(|child| child.parent == parent && target.map_or(true, |pid| pid == child.pid)) {
+            found = true;
+            if let ProcessState::Zombie(status) = child.state {
+                zombie = Some((child.pid, status));
+                break;
+            }
+        }
+        match zombie {
+            Some((pid, status)) => {
+                table.processes.remove(&pid);
+                Some(Ok(Some((pid, status))))
+            }
+            None if !found => Some(Err(Errno::ECHILD)),
+            None if nohang => Some(Ok(None)),
+            None => None,
+        }
+    })
+}
+
+pub fn count() -> usize {
+    TABLE.lock().processes.len()
+}
+
+pub fn print_processes() {
+    let rows: Vec<(Pid, Pid, String, &'static str, usize, usize, usize, u64)> = {
+        let table = TABLE.lock();
+        table.processes.values().map(|process| {
+            let mapped = process.address_space.as_ref()
+                .map(|space| space.regions().map(|region| region.end - region.start).sum())
+                .unwrap_or(0);
+            let state = if process.signals.is_stopped() { "stopped" } else { process.state.as_str() };
+            (process.pid, process.parent, process.name.clone(), state,
+             process.threads.len(), process.handles.len(), process.files.len(), mapped)
+        }).collect()
+    };
+    println!("{:>4} {:>4} {:<12} {:<8} {:>7} {:>7} {:>5} {:>8}", "PID", "PPID", "NAME", "STATE", "THREADS", "HANDLES", "FILES", "MEM(KiB)");
+    for (pid, parent, name, state, threads, handles, files, mapped) in rows {
+        println!("{:>4} {:>4} {:<12} {:<8} {:>7} {:>7} {:>5} {:>8}",
+                 pid.0, parent.0, name, state, threads, handles, files, mapped / 1024);
+    }
+}
@@ -0,0 +1,154 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+use crate::print;
+use crate::syscall::Errno;
+
+/// 文件描述符,为进程内从 0 开始的小整数
+pub type Fd = u32;
+
+pub const STDIN: Fd = 0;
+pub const STDOUT: Fd = 1;
+pub const STDERR: Fd = 2;
+/// 每个进程最多同时打开的文件描述符数
+pub const MAX_FDS: usize = 64;
+
+/// lseek 的起点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    Current(i64),
+    End(i64),
+}
+
+/// 可以通过文件描述符读写的内核对象,不支持的操作返回 EBADF
+pub trait FileObject: Any + Send + Sync {
+    fn type_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn read(&self, _buf: &mut [u8]) -> Result<usize, Errno> {
+        Err(Errno::EBADF)
+    }
+
+    fn write(&self, _buf: &[u8]) -> Result<usize, Errno> {
+        Err(Errno::EBADF)
+    }
+
+    /// 管道、设备等流式对象没有偏移
+    fn seek(&self, _pos: SeekFrom) -> Result<u64, Errno> {
+        Err(Errno::ESPIPE)
+    }
+}
+
+/// 控制台:写入输出到屏幕,键盘输入由异步任务消费,读取总是返回文件结束
+pub struct Console;
+
+impl FileObject for Console {
+    fn type_name(&self) -> &'static str {
+        "console"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn read(&self, _buf: &mut [u8]) -> Result<usize, Errno> {
+        Ok(0)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize, Errno> {
+        match core::str::from_utf8(buf) {
+            Ok(text) => print!("{}", text),
+            Err(_) => buf.iter().for_each(|&byte| print!("{}", byte as char)),
+        }
+        Ok(buf.len())
+    }
+}
+
+/// 每个进程的文件描述符表,新描述符取最小的空闲编号;同一对象可被多个描述符共享
+#[derive(Clone, Default)]
+pub struct FdTable {
+    files: Vec<Option<Arc<dyn FileObject>>>,
+}
+
+impl FdTable {
+    pub fn new() -> Self {
+        FdTable { files: Vec::new() }
+    }
+
+    /// 标准输入、输出与错误都指向控制台
+    pub fn with_console() -> Self {
+        let console: Arc<dyn FileObject> = Arc::new(Console);
+        FdTable { files: alloc::vec![Some(console.clone()), Some(console.clone()), Some(console)] }
+    }
+
+    pub fn install(&mut self, file: Arc<dyn FileObject>) -> Result<Fd, Errno> {
+        let fd = match self.files.iter().position(Option::is_none) {
+            Some(fd) => fd,
+            None if self.files.len() < MAX_FDS => {
+                self.files.push(None);
+                self.files.len() - 1
+            }
+            None => return Err(Errno::EMFILE),
+        };
+        self.files[fd] = Some(file);
+        Ok(fd as Fd)
+    }
+
+    pub fn get(&self, fd: Fd) -> Result<Arc<dyn FileObject>, Errno> {
+        self.files.get(fd as usize).cloned().flatten().ok_or(Errno::EBADF)
+    }
+
+    /// 关闭描述符,返回的对象应在进程表锁之外销毁
+    pub fn remove(&mut self, fd: Fd) -> Result<Arc<dyn FileObject>, Errno> {
+        let file = self.files.get_mut(fd as usize).and_then(Option::take).ok_or(Errno::EBADF)?;
+        while let Some(None) = self.files.last() {
+            self.files.pop();
+        }
+        Ok(file)
+    }
+
+    pub fn dup(&mut self, fd: Fd) -> Result<Fd, Errno> {
+        let file = self.get(fd)?;
+        self.install(file)
+    }
+
+    /// 让 `new` 指向 `old` 的对象,返回 `new` 原先打开的对象
+    pub fn dup2(&mut self, old: Fd, new: Fd) -> Result<Option<Arc<dyn FileObject>>, Errno> {
+        let file = self.get(old)?;
+        let index = new as usize;
+        if index >= MAX_FDS {
+            return Err(Errno::EBADF);
+        }
+        if old == new {
+            return Ok(None);
+        }
+        if index >= self.files.len() {
+            self.files.resize(index + 1, None);
+        }
+        Ok(self.files[index].replace(file))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.iter().filter(|file| file.is_some()).count()
+    }
+
+    /// 还能打开的描述符数
+    pub fn free(&self) -> usize {
+        MAX_FDS - self.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(Fd, &Arc<dyn FileObject>)> {
+        self.files.iter().enumerate().filter_map(|(fd, file)| file.as_ref().map(|file| (fd as Fd, file)))
+    }
+
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+}
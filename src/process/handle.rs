@@ -0,0 +1,150 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::any::Any;
+use core::ops::BitOr;
+
+use crate::syscall::Errno;
+
+/// 进程内句柄的编号,只在所属进程的句柄表中有效
+pub type Handle = u32;
+
+/// 句柄持有的权限,复制或传递句柄时只能缩小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rights(u32);
+
+impl Rights {
+    pub const NONE: Rights = Rights(0);
+    pub const READ: Rights = Rights(1 << 0);
+    pub const WRITE: Rights = Rights(1 << 1);
+    /// 可以复制出新的句柄
+    pub const DUPLICATE: Rights = Rights(1 << 2);
+    /// 可以传递给其他进程
+    pub const TRANSFER: Rights = Rights(1 << 3);
+    pub const ALL: Rights = Rights(0b1111);
+
+    pub const fn from_bits_truncate(bits: u32) -> Rights {
+        Rights(bits & Rights::ALL.0)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(&self, other: Rights) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersection(&self, other: Rights) -> Rights {
+        Rights(self.0 & other.0)
+    }
+}
+
+impl BitOr for Rights {
+    type Output = Rights;
+
+    fn bitor(self, rhs: Rights) -> Rights {
+        Rights(self.0 | rhs.0)
+    }
+}
+
+/// 可以放进句柄表的内核对象
+pub trait KernelObject: Any + Send + Sync {
+    fn type_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+#[derive(Clone)]
+pub struct HandleEntry {
+    pub object: Arc<dyn KernelObject>,
+    pub rights: Rights,
+}
+
+impl HandleEntry {
+    /// 对象类型为 `T` 且持有 `required` 权限时返回对象
+    pub fn downcast<T: KernelObject>(&self, required: Rights) -> Result<Arc<T>, Errno> {
+        if !self.object.as_any().is::<T>() {
+            return Err(Errno::EBADF);
+        }
+        if !self.rights.contains(required) {
+            return Err(Errno::EPERM);
+        }
+        // 已确认具体类型为 T,去掉 vtable 即得到同一分配上的 Arc<T>
+        let raw = Arc::into_raw(self.object.clone()) as *const T;
+        Ok(unsafe { Arc::from_raw(raw) })
+    }
+}
+
+/// 每个进程的句柄表,编号从 1 开始递增且不复用
+#[derive(Clone)]
+pub struct HandleTable {
+    entries: BTreeMap<Handle, HandleEntry>,
+    next: Handle,
+}
+
+impl HandleTable {
+    pub fn new() -> Self {
+        HandleTable { entries: BTreeMap::new(), next: 1 }
+    }
+
+    pub fn insert(&mut self, object: Arc<dyn KernelObject>, rights: Rights) -> Handle {
+        let handle = self.next;
+        self.next += 1;
+        self.entries.insert(handle, HandleEntry { object, rights });
+        handle
+    }
+
+    pub fn get(&self, handle: Handle) -> Result<&HandleEntry, Errno> {
+        self.entries.get(&handle).ok_or(Errno::EBADF)
+    }
+
+    pub fn get_as<T: KernelObject>(&self, handle: Handle, required: Rights) -> Result<Arc<T>, Errno> {
+        self.get(handle)?.downcast(required)
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Result<HandleEntry, Errno> {
+        self.entries.remove(&handle).ok_or(Errno::EBADF)
+    }
+
+    /// 以原编号放回移出的句柄(如发送失败时退回),编号已被占用时分配新编号
+    pub fn restore(&mut self, handle: Handle, entry: HandleEntry) -> Handle {
+        if handle == 0 || handle >= self.next || self.entries.contains_key(&handle) {
+            return self.insert(entry.object, entry.rights);
+        }
+        self.entries.insert(handle, entry);
+        handle
+    }
+
+    /// 复制句柄,新句柄的权限为原权限与 `rights` 的交集
+    pub fn duplicate(&mut self, handle: Handle, rights: Rights) -> Result<Handle, Errno> {
+        let entry = self.get(handle)?;
+        if !entry.rights.contains(Rights::DUPLICATE) {
+            return Err(Errno::EPERM);
+        }
+        let object = entry.object.clone();
+        let rights = entry.rights.intersection(rights);
+        Ok(self.insert(object, rights))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(Handle, &HandleEntry)> {
+        self.entries.iter().map(|(handle, entry)| (*handle, entry))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for HandleTable {
+    fn default() -> Self {
+        HandleTable::new()
+    }
+}
@@ -0,0 +1,421 @@
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{gdt, println, thread, user};
+use crate::mem::address_space::{USER_REGION_BOTTOM, USER_REGION_TOP};
+use crate::process::{self, ExitStatus, Pid, ProcessState};
+use crate::sync::WaitQueue;
+use crate::syscall::{Errno, SyscallFrame};
+use crate::syscall::uaccess;
+
+/// 信号编号与 Linux x86_64 相同
+pub const SIGHUP: u32 = 1;
+pub const SIGINT: u32 = 2;
+pub const SIGQUIT: u32 = 3;
+pub const SIGILL: u32 = 4;
+pub const SIGTRAP: u32 = 5;
+pub const SIGABRT: u32 = 6;
+pub const SIGBUS: u32 = 7;
+pub const SIGFPE: u32 = 8;
+pub const SIGKILL: u32 = 9;
+pub const SIGUSR1: u32 = 10;
+pub const SIGSEGV: u32 = 11;
+pub const SIGUSR2: u32 = 12;
+pub const SIGPIPE: u32 = 13;
+pub const SIGALRM: u32 = 14;
+pub const SIGTERM: u32 = 15;
+pub const SIGCHLD: u32 = 17;
+pub const SIGCONT: u32 = 18;
+pub const SIGSTOP: u32 = 19;
+pub const SIGTSTP: u32 = 20;
+/// 有效编号为 1..NSIG
+pub const NSIG: u32 = 32;
+
+/// sigaction 中表示默认动作与忽略的处理函数地址
+pub const SIG_DFL: u64 = 0;
+pub const SIG_IGN: u64 = 1;
+
+/// sigprocmask 的 how
+pub const SIG_BLOCK: u64 = 0;
+pub const SIG_UNBLOCK: u64 = 1;
+pub const SIG_SETMASK: u64 = 2;
+
+/// 用户态可以通过 sigreturn 恢复的 RFLAGS 位:CF PF AF ZF SF DF OF
+const USER_RFLAGS: u64 = 0x0cd5;
+/// 信号帧放在 System V ABI 的 red zone 之下
+const RED_ZONE: u64 = 128;
+
+pub fn is_valid(sig: u32) -> bool {
+    (1..NSIG).contains(&sig)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SigSet(u64);
+
+impl SigSet {
+    /// SIGKILL 与 SIGSTOP 不能被阻塞、忽略或捕获
+    pub const UNBLOCKABLE: SigSet = SigSet((1 << SIGKILL) | (1 << SIGSTOP));
+
+    pub const fn empty() -> Self {
+        SigSet(0)
+    }
+
+    pub const fn from_bits(bits: u64) -> Self {
+        SigSet(bits & !1 & ((1 << NSIG) - 1))
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn contains(&self, sig: u32) -> bool {
+        self.0 & (1 << sig) != 0
+    }
+
+    pub fn insert(&mut self, sig: u32) {
+        self.0 |= 1 << sig;
+    }
+
+    pub fn remove(&mut self, sig: u32) {
+        self.0 &= !(1 << sig);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(&self, other: SigSet) -> SigSet {
+        SigSet(self.0 | other.0)
+    }
+
+    pub fn difference(&self, other: SigSet) -> SigSet {
+        SigSet(self.0 & !other.0)
+    }
+
+    /// 编号最小的信号
+    pub fn first(&self) -> Option<u32> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(self.0.trailing_zeros())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultAction {
+    Terminate,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+pub fn default_action(sig: u32) -> DefaultAction {
+    match sig {
+        SIGCHLD => DefaultAction::Ignore,
+        SIGCONT => DefaultAction::Continue,
+        SIGSTOP | SIGTSTP => DefaultAction::Stop,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigAction {
+    Default,
+    Ignore,
+    /// 处理函数返回到 `restorer`,由它调用 sigreturn;执行期间额外阻塞 `mask` 与该信号本身
+    Handler { handler: u64, restorer: u64, mask: SigSet },
+}
+
+impl SigAction {
+    fn as_raw(&self) -> u64 {
+        match self {
+            SigAction::Default => SIG_DFL,
+            SigAction::Ignore => SIG_IGN,
+            SigAction::Handler { handler, .. } => *handler,
+        }
+    }
+
+    fn ignores(&self, sig: u32) -> bool {
+        match self {
+            SigAction::Ignore => true,
+            SigAction::Default => default_action(sig) == DefaultAction::Ignore,
+            SigAction::Handler { .. } => false,
+        }
+    }
+}
+
+/// 进程的信号状态:待处理集合、阻塞集合与各信号的处理方式
+#[derive(Debug, Clone)]
+pub struct SignalState {
+    pending: SigSet,
+    blocked: SigSet,
+    actions: [SigAction; NSIG as usize],
+    stopped: bool,
+}
+
+impl SignalState {
+    pub fn new() -> Self {
+        SignalState {
+            pending: SigSet::empty(),
+            blocked: SigSet::empty(),
+            actions: [SigAction::Default; NSIG as usize],
+            stopped: false,
+        }
+    }
+
+    /// fork 出的子进程继承处理方式与阻塞集合,待处理信号清空
+    pub fn fork(&self) -> Self {
+        SignalState { pending: SigSet::empty(), stopped: false, ..self.clone() }
+    }
+
+    /// exec 后用户处理函数不再存在,恢复为默认动作,忽略的信号保持忽略
+    pub fn reset_handlers(&mut self) {
+        for action in self.actions.iter_mut() {
+            if let SigAction::Handler { .. } = action {
+                *action = SigAction::Default;
+            }
+        }
+    }
+
+    pub fn pending(&self) -> SigSet {
+        self.pending
+    }
+
+    pub fn blocked(&self) -> SigSet {
+        self.blocked
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// 待处理且未被阻塞的信号
+    fn deliverable(&self) -> SigSet {
+        self.pending.difference(self.blocked.difference(SigSet::UNBLOCKABLE))
+    }
+}
+
+impl Default for SignalState {
+    fn default() -> Self {
+        SignalState::new()
+    }
+}
+
+/// 被停止的进程在此等待 SIGCONT 或 SIGKILL
+static STOPPED: WaitQueue = WaitQueue::new();
+
+/// Ctrl-C 发送 SIGINT 的前台进程,0 表示没有
+static FOREGROUND: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_foreground(pid: Option<Pid>) {
+    FOREGROUND.store(pid.map_or(0, |pid| pid.as_u64()), Ordering::Relaxed);
+}
+
+pub fn foreground() -> Option<Pid> {
+    match FOREGROUND.load(Ordering::Relaxed) {
+        0 => None,
+        pid => Some(Pid::from_u64(pid)),
+    }
+}
+
+/// 向进程发送信号;被忽略的信号直接丢弃,`sig` 为 0 时只检查进程是否存在
+pub fn send(pid: Pid, sig: u32) -> Result<(), Errno> {
+    if sig != 0 && !is_valid(sig) {
+        return Err(Errno::EINVAL);
+    }
+    let woken = process::with_process(pid, |process| {
+        if sig == 0 || process.state != ProcessState::Running {
+            return None;
+        }
+        let signals = &mut process.signals;
+        match sig {
+            SIGCONT => {
+                signals.stopped = false;
+                signals.pending.remove(SIGSTOP);
+                signals.pending.remove(SIGTSTP);
+            }
+            SIGSTOP | SIGTSTP => signals.pending.remove(SIGCONT),
+            _ => {}
+        }
+        if signals.actions[sig as usize].ignores(sig) && sig != SIGKILL {
+            return None;
+        }
+        signals.pending.insert(sig);
+        Some(process.threads.clone())
+    }).ok_or(Errno::ESRCH)?;
+    if sig == SIGCONT || sig == SIGKILL {
+        STOPPED.notify_all();
+    }
+    // 让睡眠中的线程尽快处理信号
+    for thread in woken.into_iter().flatten() {
+        thread::unblock(thread);
+    }
+    Ok(())
+}
+
+/// Ctrl-C:向前台进程发送 SIGINT,供软中断调用
+pub fn interrupt_foreground(_data: usize) {
+    if let Some(pid) = foreground() {
+        if send(pid, SIGINT).is_err() {
+            set_foreground(None);
+        }
+    }
+}
+
+/// 当前进程有未被阻塞的待处理信号,可中断的阻塞操作据此返回 EINTR
+pub fn has_deliverable() -> bool {
+    process::with_current(|process| !process.signals.deliverable().is_empty()).unwrap_or(false)
+}
+
+/// 同步异常产生的信号:被阻塞或忽略时恢复为默认动作,返回用户态时一定得到处理,不会反复触发同一异常
+pub fn force(sig: u32) {
+    process::with_current(|process| {
+        let signals = &mut process.signals;
+        if signals.blocked.contains(sig) || signals.actions[sig as usize] == SigAction::Ignore {
+            signals.blocked.remove(sig);
+            signals.actions[sig as usize] = SigAction::Default;
+        }
+        signals.pending.insert(sig);
+    });
+}
+
+/// 取出下一个需要处理的信号及其处理方式
+fn next_signal() -> Option<(u32, SigAction)> {
+    process::with_current(|process| {
+        let signals = &mut process.signals;
+        let sig = signals.deliverable().first()?;
+        signals.pending.remove(sig);
+        Some((sig, signals.actions[sig as usize]))
+    }).flatten()
+}
+
+/// 执行默认动作;返回 false 表示信号需要交给用户处理函数
+fn apply_default(sig: u32, action: SigAction) -> bool {
+    let default = match action {
+        SigAction::Ignore => return true,
+        SigAction::Handler { .. } if sig != SIGKILL => return false,
+        _ => default_action(sig),
+    };
+    match default {
+        DefaultAction::Terminate => process::exit(ExitStatus::Signaled(sig)),
+        DefaultAction::Stop => stop_current(),
+        DefaultAction::Ignore | DefaultAction::Continue => {}
+    }
+    true
+}
+
+/// 停止当前进程直到收到 SIGCONT;期间的 SIGKILL 在恢复后立即处理
+fn stop_current() {
+    let pid = process::current_pid();
+    process::with_current(|process| process.signals.stopped = true);
+    println!("Process {} stopped", pid.as_u64());
+    STOPPED.wait_until(|| {
+        process::with_current(|process| {
+            let signals = &process.signals;
+            if !signals.stopped || signals.pending.contains(SIGKILL) {
+                Some(())
+            } else {
+                None
+            }
+        }).unwrap_or(Some(()))
+    });
+}
+
+/// 系统调用或中断返回用户态前处理待处理信号,最多为一个信号建立用户处理函数的栈帧
+pub fn deliver(frame: &mut SyscallFrame) {
+    while let Some((sig, action)) = next_signal() {
+        if apply_default(sig, action) {
+            continue;
+        }
+        if let SigAction::Handler { handler, restorer, mask } = action {
+            if setup_frame(frame, sig, handler, restorer, mask).is_err() {
+                println!("Process {} signal frame setup failed", process::current_pid().as_u64());
+                process::exit(ExitStatus::Signaled(SIGSEGV));
+            }
+            return;
+        }
+    }
+}
+
+/// 压在用户栈上的信号帧,处理函数的返回地址位于最低处
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct SignalFrame {
+    restorer: u64,
+    signal: u64,
+    blocked: u64,
+    saved: SyscallFrame,
+}
+
+fn setup_frame(frame: &mut SyscallFrame, sig: u32, handler: u64, restorer: u64, mask: SigSet) -> Result<(), Errno> {
+    let blocked = process::with_current(|process| process.signals.blocked).ok_or(Errno::ESRCH)?;
+    let size = size_of::<SignalFrame>() as u64;
+    // 进入处理函数时 rsp + 8 按 16 字节对齐,与 call 指令之后一致
+    let sp = ((frame.rsp.wrapping_sub(RED_ZONE + size)) & !0xf).wrapping_sub(8);
+    uaccess::write_user(sp, SignalFrame { restorer, signal: sig as u64, blocked: blocked.bits(), saved: *frame })?;
+    process::with_current(|process| {
+        let signals = &mut process.signals;
+        signals.blocked = signals.blocked.union(mask);
+        signals.blocked.insert(sig);
+        signals.blocked = signals.blocked.difference(SigSet::UNBLOCKABLE);
+    });
+    frame.rip = handler;
+    frame.rsp = sp;
+    frame.rdi = sig as u64;
+    // 按 ABI 进入函数时 DF 为 0
+    frame.rflags &= !0x400;
+    Ok(())
+}
+
+/// 处理函数经 restorer 返回后调用,恢复信号帧中保存的现场与阻塞集合,返回原 rax
+pub fn sigreturn(frame: &mut SyscallFrame) -> Result<u64, Errno> {
+    // restorer 是被 ret 弹出的返回地址,信号帧位于其下 8 字节
+    let signal_frame: SignalFrame = uaccess::read_user(frame.rsp.wrapping_sub(8))?;
+    let saved = signal_frame.saved;
+    // rip 与 rsp 都由用户写入信号帧,sysret 前必须是规范的用户地址
+    if !user::is_user_pointer(saved.rip) || !(USER_REGION_BOTTOM..=USER_REGION_TOP).contains(&saved.rsp) {
+        return Err(Errno::EFAULT);
+    }
+    process::with_current(|process| {
+        process.signals.blocked = SigSet::from_bits(signal_frame.blocked).difference(SigSet::UNBLOCKABLE);
+    });
+    *frame = SyscallFrame {
+        cs: gdt::user_code_selector().0 as u64,
+        ss: gdt::user_data_selector().0 as u64,
+        rflags: (saved.rflags & USER_RFLAGS) | user::INITIAL_RFLAGS,
+        ..saved
+    };
+    Ok(saved.rax)
+}
+
+/// 设置信号处理方式,返回原来的处理函数地址(SIG_DFL、SIG_IGN 或函数地址)
+pub fn set_action(sig: u32, action: SigAction) -> Result<u64, Errno> {
+    if !is_valid(sig) || SigSet::UNBLOCKABLE.contains(sig) {
+        return Err(Errno::EINVAL);
+    }
+    process::with_current(|process| {
+        let signals = &mut process.signals;
+        let old = core::mem::replace(&mut signals.actions[sig as usize], action);
+        if action.ignores(sig) {
+            signals.pending.remove(sig);
+        }
+        old.as_raw()
+    }).ok_or(Errno::EPERM)
+}
+
+/// 修改阻塞集合,返回原来的集合
+pub fn set_blocked(how: u64, set: SigSet) -> Result<SigSet, Errno> {
+    process::with_current(|process| {
+        let signals = &mut process.signals;
+        let old = signals.blocked;
+        let blocked = match how {
+            SIG_BLOCK => old.union(set),
+            SIG_UNBLOCK => old.difference(set),
+            SIG_SETMASK => set,
+            _ => return Err(Errno::EINVAL),
+        };
+        signals.blocked = blocked.difference(SigSet::UNBLOCKABLE);
+        Ok(old)
+    }).ok_or(Errno::EPERM)?
+}
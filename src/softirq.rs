@@ -0,0 +1,104 @@
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use x86_64::instructions::interrupts;
+
+use crate::sync::SpinLock;
+
+/// 延迟执行的工作函数,参数由入队者传入
+pub type WorkFn = fn(usize);
+
+#[derive(Clone, Copy)]
+struct WorkItem {
+    func: WorkFn,
+    data: usize,
+}
+
+const QUEUE_SIZE: usize = 256;
+
+/// 定长环形队列:中断上下文中不能分配堆内存
+struct WorkQueue {
+    items: [Option<WorkItem>; QUEUE_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl WorkQueue {
+    const fn new() -> Self {
+        WorkQueue {
+            items: [None; QUEUE_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, item: WorkItem) -> bool {
+        if self.len == QUEUE_SIZE {
+            return false;
+        }
+        self.items[(self.head + self.len) % QUEUE_SIZE] = Some(item);
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<WorkItem> {
+        if self.len == 0 {
+            return None;
+        }
+        let item = self.items[self.head].take();
+        self.head = (self.head + 1) % QUEUE_SIZE;
+        self.len -= 1;
+        item
+    }
+}
+
+static QUEUE: SpinLock<WorkQueue> = SpinLock::new(WorkQueue::new());
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// 供中断处理函数调用:只入队,不做耗时处理。队列满时丢弃并计数
+pub fn queue(func: WorkFn, data: usize) -> bool {
+    let queued = QUEUE.lock().push(WorkItem { func, data });
+    if !queued {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+    queued
+}
+
+pub fn pending() -> usize {
+    QUEUE.lock().len
+}
+
+/// 延迟工作执行期间不发生线程抢占
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+pub fn dropped() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// 在中断处理函数发送 EOI 之后调用,开中断执行队列中的工作
+/// 嵌套的中断只负责入队,由最外层的调用者统一执行
+pub fn run_pending() {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let was_enabled = interrupts::are_enabled();
+    interrupts::disable();
+    loop {
+        let item = QUEUE.lock().pop();
+        match item {
+            Some(work) => {
+                interrupts::enable();
+                (work.func)(work.data);
+                interrupts::disable();
+            }
+            None => break,
+        }
+    }
+    // 关中断状态下清除标志,避免与新入队的工作产生竞争
+    RUNNING.store(false, Ordering::SeqCst);
+    if was_enabled {
+        interrupts::enable();
+    }
+}
@@ -0,0 +1,33 @@
+//! 内核同步原语
+//!
+//! [`SpinLock`] 持锁期间关中断,可在中断上下文中使用;
+//! 其余原语在拿不到资源时挂起当前线程,不能在中断上下文中等待。
+
+/// 开启 `lock-debug` 时调用 [`lockdep`] 的钩子,否则展开为空
+macro_rules! lockdep {
+    ($($call:tt)*) => {
+        #[cfg(feature = "lock-debug")]
+        {
+            use crate::sync::lockdep::*;
+            $($call)*;
+        }
+    };
+}
+
+pub use condvar::Condvar;
+pub use event::Event;
+pub use mutex::{Mutex, MutexGuard};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::{Semaphore, SemaphorePermit};
+pub use spinlock::{SpinLock, SpinLockGuard};
+pub use wait_queue::WaitQueue;
+
+pub mod spinlock;
+pub mod wait_queue;
+pub mod mutex;
+pub mod rwlock;
+pub mod semaphore;
+pub mod condvar;
+pub mod event;
+#[cfg(feature = "lock-debug")]
+pub mod lockdep;
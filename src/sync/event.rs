@@ -0,0 +1,72 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll};
+
+use crate::sync::WaitQueue;
+
+/// 手动复位事件:`set` 后所有等待者都会返回,直到 `reset`
+pub struct Event {
+    set: AtomicBool,
+    waiters: WaitQueue,
+}
+
+impl Event {
+    pub const fn new() -> Self {
+        Event {
+            set: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// 可在中断上下文中调用
+    pub fn set(&self) {
+        self.set.store(true, Ordering::Release);
+        self.waiters.notify_all();
+    }
+
+    pub fn reset(&self) {
+        self.set.store(false, Ordering::Release);
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.set.load(Ordering::Acquire)
+    }
+
+    #[track_caller]
+    pub fn wait(&self) {
+        self.waiters.wait_until(|| if self.is_set() { Some(()) } else { None })
+    }
+
+    /// 供异步任务等待
+    pub fn wait_async(&self) -> EventWait<'_> {
+        EventWait { event: self }
+    }
+}
+
+impl Default for Event {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct EventWait<'a> {
+    event: &'a Event,
+}
+
+impl<'a> Future for EventWait<'a> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.event.is_set() {
+            return Poll::Ready(());
+        }
+        self.event.waiters.register(cx.waker());
+        // 登记后再检查一次,避免错过登记前的 set
+        if self.event.is_set() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
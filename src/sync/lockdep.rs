@@ -0,0 +1,272 @@
+//! 锁调试(`lock-debug` feature)
+//!
+//! 按锁的地址记录每个执行上下文(线程或中断)当前持有的锁及获取位置,检测:
+//! - 同一上下文重复获取同一把锁(必然死锁,直接 panic)
+//! - 在中断上下文中获取会睡眠的锁,或在中断上下文/持有自旋锁时睡眠
+//! - 锁获取顺序成环(潜在的 ABBA 死锁)
+//!
+//! 非致命的问题先记下,等当前上下文不再持有锁时再输出,避免打印时再去拿 `WRITER`。
+//! 记录需要分配内存,堆初始化之前获取的锁不做检查;全局分配器与 lockdep 自身的锁不经过这里。
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::panic::Location;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::{allocator, idt, println};
+use crate::thread::scheduler;
+
+type Site = &'static Location<'static>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    Spin,
+    Mutex,
+    RwLock,
+}
+
+impl LockKind {
+    /// 只有关中断的自旋锁可以在中断上下文中获取
+    fn irq_safe(self) -> bool {
+        self == LockKind::Spin
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            LockKind::Spin => "SpinLock",
+            LockKind::Mutex => "Mutex",
+            LockKind::RwLock => "RwLock",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ExecContext {
+    /// 线程初始化之前
+    Boot,
+    Thread(u64),
+    Interrupt,
+}
+
+impl ExecContext {
+    fn current() -> Self {
+        if idt::in_interrupt() {
+            return ExecContext::Interrupt;
+        }
+        match scheduler::current_id() {
+            Some(id) => ExecContext::Thread(id.as_u64()),
+            None => ExecContext::Boot,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HeldLock {
+    addr: usize,
+    kind: LockKind,
+    site: Site,
+}
+
+struct LockDep {
+    held: BTreeMap<ExecContext, Vec<HeldLock>>,
+    /// 锁顺序图:`order[a][b]` 为持有 a 时首次获取 b 的位置
+    order: BTreeMap<usize, BTreeMap<usize, Site>>,
+    /// 已报告过的问题,每个只报告一次
+    reported: BTreeSet<(usize, usize)>,
+    pending: Vec<String>,
+}
+
+static STATE: Mutex<LockDep> = Mutex::new(LockDep {
+    held: BTreeMap::new(),
+    order: BTreeMap::new(),
+    reported: BTreeSet::new(),
+    pending: Vec::new(),
+});
+
+/// 输出报告期间不再记录,避免打印本身触发钩子;
+/// 报告时始终关中断,其它上下文不会在此期间获取或释放锁而漏记
+static REPORTING: AtomicBool = AtomicBool::new(false);
+
+fn with_state<F, R>(f: F) -> Option<R> where F: FnOnce(&mut LockDep) -> R {
+    if REPORTING.load(Ordering::Relaxed) || !allocator::is_initialized() {
+        return None;
+    }
+    interrupts::without_interrupts(|| Some(f(&mut STATE.lock())))
+}
+
+impl LockDep {
+    fn warn_once(&mut self, key: (usize, usize), message: String) {
+        if self.reported.insert(key) {
+            self.pending.push(message);
+        }
+    }
+
+    /// 沿顺序图查找 from 到 to 的路径
+    fn find_path(&self, from: usize, to: usize) -> Option<Vec<(usize, usize, Site)>> {
+        let mut visited = BTreeSet::new();
+        let mut stack = Vec::new();
+        stack.push((from, Vec::new()));
+        while let Some((node, path)) = stack.pop() {
+            if node == to {
+                return Some(path);
+            }
+            if !visited.insert(node) {
+                continue;
+            }
+            if let Some(edges) = self.order.get(&node) {
+                for (&next, &site) in edges {
+                    let mut next_path = path.clone();
+                    next_path.push((node, next, site));
+                    stack.push((next, next_path));
+                }
+            }
+        }
+        None
+    }
+
+    fn check_order(&mut self, prev: HeldLock, addr: usize, site: Site) {
+        if self.order.get(&prev.addr).map_or(false, |edges| edges.contains_key(&addr)) {
+            return;
+        }
+        if let Some(path) = self.find_path(addr, prev.addr) {
+            let mut message = format!(
+                "lockdep: possible circular locking: acquiring {:#x} at {} while holding {:#x} (taken at {})\n  existing order:",
+                addr, site, prev.addr, prev.site);
+            for (from, to, edge_site) in path {
+                message += &format!("\n    {:#x} -> {:#x} at {}", from, to, edge_site);
+            }
+            self.warn_once((prev.addr, addr), message);
+        }
+        self.order.entry(prev.addr).or_default().insert(addr, site);
+    }
+}
+
+/// 获取锁之前调用;`trylock` 不会等待,不参与顺序检查
+pub(crate) fn acquire(addr: usize, kind: LockKind, site: Site, trylock: bool) {
+    let recursive = with_state(|state| {
+        let context = ExecContext::current();
+        if context == ExecContext::Interrupt && !kind.irq_safe() {
+            state.warn_once((addr, site as *const _ as usize), format!(
+                "lockdep: {} {:#x} acquired in interrupt context at {}", kind.name(), addr, site));
+        }
+        let held = state.held.get(&context).cloned().unwrap_or_default();
+        if let Some(previous) = held.iter().find(|lock| lock.addr == addr) {
+            if !trylock {
+                return Some(*previous);
+            }
+        }
+        if !trylock {
+            if let Some(&prev) = held.last() {
+                state.check_order(prev, addr, site);
+            }
+        }
+        None
+    }).flatten();
+    if let Some(previous) = recursive {
+        REPORTING.store(true, Ordering::Relaxed);
+        panic!("lockdep: recursive acquisition of {} {:#x} at {}, already held since {}",
+               kind.name(), addr, site, previous.site);
+    }
+}
+
+/// 成功获取锁之后调用
+pub(crate) fn acquired(addr: usize, kind: LockKind, site: Site) {
+    with_state(|state| {
+        state.held.entry(ExecContext::current()).or_default().push(HeldLock { addr, kind, site });
+    });
+}
+
+/// 释放锁之后调用;锁可能在其它上下文中获取(如启动阶段获取、线程中释放)
+pub(crate) fn release(addr: usize) {
+    let flush = with_state(|state| {
+        let context = ExecContext::current();
+        let owner = match state.held.get(&context) {
+            Some(held) if held.iter().any(|lock| lock.addr == addr) => Some(context),
+            _ => state.held.iter()
+                .find(|(_, held)| held.iter().any(|lock| lock.addr == addr))
+                .map(|(&owner, _)| owner),
+        };
+        if let Some(owner) = owner {
+            let held = state.held.get_mut(&owner).unwrap();
+            if let Some(index) = held.iter().rposition(|lock| lock.addr == addr) {
+                held.remove(index);
+            }
+            if held.is_empty() {
+                state.held.remove(&owner);
+            }
+        }
+        !state.pending.is_empty() && !state.held.contains_key(&context)
+    }).unwrap_or(false);
+    if flush {
+        report();
+    }
+}
+
+/// 即将睡眠(阻塞在等待队列上)时调用
+pub(crate) fn might_sleep(site: Site) {
+    with_state(|state| {
+        let context = ExecContext::current();
+        if context == ExecContext::Interrupt {
+            state.warn_once((0, site as *const _ as usize), format!("lockdep: sleeping in interrupt context at {}", site));
+            return;
+        }
+        let spin = state.held.get(&context)
+            .and_then(|held| held.iter().rev().find(|lock| lock.kind == LockKind::Spin).copied());
+        if let Some(lock) = spin {
+            state.warn_once((lock.addr, site as *const _ as usize), format!(
+                "lockdep: sleeping at {} while holding SpinLock {:#x} (taken at {})", site, lock.addr, lock.site));
+        }
+    });
+}
+
+/// 锁被销毁,地址可能被复用,从顺序图中移除
+pub(crate) fn forget(addr: usize) {
+    with_state(|state| {
+        state.order.remove(&addr);
+        for edges in state.order.values_mut() {
+            edges.remove(&addr);
+        }
+    });
+}
+
+/// 输出积压的报告
+pub fn report() {
+    let pending = with_state(|state| core::mem::take(&mut state.pending)).unwrap_or_default();
+    if pending.is_empty() {
+        return;
+    }
+    interrupts::without_interrupts(|| {
+        REPORTING.store(true, Ordering::Relaxed);
+        for message in pending {
+            println!("{}", message);
+        }
+        REPORTING.store(false, Ordering::Relaxed);
+    });
+}
+
+/// 打印各上下文持有的锁与顺序图规模
+pub fn dump() {
+    let snapshot = with_state(|state| {
+        let edges: usize = state.order.values().map(|edges| edges.len()).sum();
+        (state.held.clone(), state.order.len(), edges)
+    });
+    let (held, nodes, edges) = match snapshot {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+    interrupts::without_interrupts(|| {
+        REPORTING.store(true, Ordering::Relaxed);
+        println!("lockdep: {} locks, {} ordering edges", nodes, edges);
+        for (context, locks) in held {
+            for lock in locks {
+                println!("  {:?} holds {} {:#x} since {}", context, lock.kind.name(), lock.addr, lock.site);
+            }
+        }
+        REPORTING.store(false, Ordering::Relaxed);
+    });
+}
@@ -0,0 +1,108 @@
+use core::cell::UnsafeCell;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::sync::WaitQueue;
+
+/// 拿不到锁时挂起当前线程的互斥锁,不能在中断上下文中使用
+pub struct Mutex<T> {
+    locked: AtomicBool,
+    waiters: WaitQueue,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Mutex {
+            locked: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        lockdep!(acquire(self as *const _ as usize, LockKind::Mutex, core::panic::Location::caller(), false));
+        let guard = self.waiters.wait_until(|| self.try_acquire());
+        lockdep!(acquired(self as *const _ as usize, LockKind::Mutex, core::panic::Location::caller()));
+        guard
+    }
+
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        lockdep!(acquire(self as *const _ as usize, LockKind::Mutex, core::panic::Location::caller(), true));
+        let guard = self.try_acquire();
+        if guard.is_some() {
+            lockdep!(acquired(self as *const _ as usize, LockKind::Mutex, core::panic::Location::caller()));
+        }
+        guard
+    }
+
+    fn try_acquire(&self) -> Option<MutexGuard<'_, T>> {
+        self.locked.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard { mutex: self })
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    pub fn into_inner(self) -> T {
+        let this = ManuallyDrop::new(self);
+        lockdep!(forget(&*this as *const Self as usize));
+        unsafe {
+            drop(ptr::read(&this.waiters));
+            ptr::read(&this.data).into_inner()
+        }
+    }
+
+    fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+        lockdep!(release(self as *const _ as usize));
+        self.waiters.notify_one();
+    }
+}
+
+#[cfg(feature = "lock-debug")]
+impl<T> Drop for Mutex<T> {
+    fn drop(&mut self) {
+        lockdep!(forget(self as *const Self as usize));
+    }
+}
+
+impl<'a, T> MutexGuard<'a, T> {
+    /// 供 [`Condvar`](crate::sync::Condvar) 在等待前释放锁
+    pub(crate) fn mutex(&self) -> &'a Mutex<T> {
+        self.mutex
+    }
+}
+
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
@@ -0,0 +1,171 @@
+use core::cell::UnsafeCell;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::sync::WaitQueue;
+
+const WRITER: usize = 1 << (usize::BITS - 1);
+
+/// 读写锁:多个读者或一个写者。有写者等待时新读者也会等待,避免写者饿死
+pub struct RwLock<T> {
+    /// 最高位表示写者持有,其余位为读者数
+    state: AtomicUsize,
+    writers_waiting: AtomicUsize,
+    readers: WaitQueue,
+    writers: WaitQueue,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T> RwLock<T> {
+    pub const fn new(value: T) -> Self {
+        RwLock {
+            state: AtomicUsize::new(0),
+            writers_waiting: AtomicUsize::new(0),
+            readers: WaitQueue::new(),
+            writers: WaitQueue::new(),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    #[track_caller]
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        lockdep!(acquire(self as *const _ as usize, LockKind::RwLock, core::panic::Location::caller(), false));
+        let guard = self.readers.wait_until(|| {
+            if self.writers_waiting.load(Ordering::Relaxed) > 0 {
+                return None;
+            }
+            self.try_acquire_read()
+        });
+        lockdep!(acquired(self as *const _ as usize, LockKind::RwLock, core::panic::Location::caller()));
+        guard
+    }
+
+    #[track_caller]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        lockdep!(acquire(self as *const _ as usize, LockKind::RwLock, core::panic::Location::caller(), true));
+        let guard = self.try_acquire_read();
+        if guard.is_some() {
+            lockdep!(acquired(self as *const _ as usize, LockKind::RwLock, core::panic::Location::caller()));
+        }
+        guard
+    }
+
+    fn try_acquire_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let state = self.state.load(Ordering::Relaxed);
+        if state & WRITER != 0 {
+            return None;
+        }
+        self.state.compare_exchange(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| RwLockReadGuard { lock: self })
+    }
+
+    #[track_caller]
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        lockdep!(acquire(self as *const _ as usize, LockKind::RwLock, core::panic::Location::caller(), false));
+        let guard = match self.try_acquire_write() {
+            Some(guard) => guard,
+            None => {
+                self.writers_waiting.fetch_add(1, Ordering::Relaxed);
+                let guard = self.writers.wait_until(|| self.try_acquire_write());
+                self.writers_waiting.fetch_sub(1, Ordering::Relaxed);
+                guard
+            }
+        };
+        lockdep!(acquired(self as *const _ as usize, LockKind::RwLock, core::panic::Location::caller()));
+        guard
+    }
+
+    #[track_caller]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        lockdep!(acquire(self as *const _ as usize, LockKind::RwLock, core::panic::Location::caller(), true));
+        let guard = self.try_acquire_write();
+        if guard.is_some() {
+            lockdep!(acquired(self as *const _ as usize, LockKind::RwLock, core::panic::Location::caller()));
+        }
+        guard
+    }
+
+    fn try_acquire_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.state.compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| RwLockWriteGuard { lock: self })
+    }
+
+    pub fn into_inner(self) -> T {
+        let this = ManuallyDrop::new(self);
+        lockdep!(forget(&*this as *const Self as usize));
+        unsafe {
+            drop(ptr::read(&this.readers));
+            drop(ptr::read(&this.writers));
+            ptr::read(&this.data).into_inner()
+        }
+    }
+
+    /// 优先唤醒写者,没有写者等待时唤醒全部读者
+    fn wake_waiters(&self) {
+        if !self.writers.notify_one() {
+            self.readers.notify_all();
+        }
+    }
+}
+
+#[cfg(feature = "lock-debug")]
+impl<T> Drop for RwLock<T> {
+    fn drop(&mut self) {
+        lockdep!(forget(self as *const Self as usize));
+    }
+}
+
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        let readers = self.lock.state.fetch_sub(1, Ordering::Release);
+        lockdep!(release(self.lock as *const _ as usize));
+        if readers == 1 {
+            self.lock.wake_waiters();
+        }
+    }
+}
+
+impl<'a, T> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.state.store(0, Ordering::Release);
+        lockdep!(release(self.lock as *const _ as usize));
+        self.lock.wake_waiters();
+    }
+}
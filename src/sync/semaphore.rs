@@ -0,0 +1,62 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::sync::WaitQueue;
+
+/// 计数信号量
+pub struct Semaphore {
+    permits: AtomicUsize,
+    waiters: WaitQueue,
+}
+
+/// 离开作用域时归还许可
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    pub const fn new(permits: usize) -> Self {
+        Semaphore {
+            permits: AtomicUsize::new(permits),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    #[track_caller]
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        self.waiters.wait_until(|| self.try_acquire())
+    }
+
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let mut permits = self.permits.load(Ordering::Relaxed);
+        while permits > 0 {
+            match self.permits.compare_exchange_weak(permits, permits - 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return Some(SemaphorePermit { semaphore: self }),
+                Err(actual) => permits = actual,
+            }
+        }
+        None
+    }
+
+    /// 增加一个许可,可在中断上下文中调用
+    pub fn release(&self) {
+        self.permits.fetch_add(1, Ordering::Release);
+        self.waiters.notify_one();
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.permits.load(Ordering::Relaxed)
+    }
+}
+
+impl<'a> SemaphorePermit<'a> {
+    /// 不归还许可,用于生产者/消费者式的用法
+    pub fn forget(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl<'a> Drop for SemaphorePermit<'a> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
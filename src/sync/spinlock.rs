@@ -0,0 +1,106 @@
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+use x86_64::instructions::interrupts;
+
+/// 持锁期间关闭中断的自旋锁,避免持锁时被同一 CPU 上的中断处理函数重入造成死锁
+pub struct SpinLock<T> {
+    inner: spin::Mutex<T>,
+}
+
+pub struct SpinLockGuard<'a, T> {
+    #[cfg(feature = "lock-debug")]
+    addr: usize,
+    guard: Option<spin::MutexGuard<'a, T>>,
+    /// 加锁前的中断状态,解锁后恢复
+    interrupts_enabled: bool,
+}
+
+impl<T> SpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        SpinLock { inner: spin::Mutex::new(value) }
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        let interrupts_enabled = interrupts::are_enabled();
+        interrupts::disable();
+        lockdep!(acquire(self as *const _ as usize, LockKind::Spin, core::panic::Location::caller(), false));
+        let guard = self.inner.lock();
+        lockdep!(acquired(self as *const _ as usize, LockKind::Spin, core::panic::Location::caller()));
+        SpinLockGuard {
+            #[cfg(feature = "lock-debug")]
+            addr: self as *const _ as usize,
+            guard: Some(guard),
+            interrupts_enabled,
+        }
+    }
+
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
+        let interrupts_enabled = interrupts::are_enabled();
+        interrupts::disable();
+        lockdep!(acquire(self as *const _ as usize, LockKind::Spin, core::panic::Location::caller(), true));
+        match self.inner.try_lock() {
+            Some(guard) => {
+                lockdep!(acquired(self as *const _ as usize, LockKind::Spin, core::panic::Location::caller()));
+                Some(SpinLockGuard {
+                    #[cfg(feature = "lock-debug")]
+                    addr: self as *const _ as usize,
+                    guard: Some(guard),
+                    interrupts_enabled,
+                })
+            }
+            None => {
+                if interrupts_enabled {
+                    interrupts::enable();
+                }
+                None
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        let this = ManuallyDrop::new(self);
+        lockdep!(forget(&*this as *const Self as usize));
+        unsafe { ptr::read(&this.inner) }.into_inner()
+    }
+
+    /// 强制解锁,仅用于 panic 等不会再返回的路径
+    pub unsafe fn force_unlock(&self) {
+        self.inner.force_unlock()
+    }
+}
+
+#[cfg(feature = "lock-debug")]
+impl<T> Drop for SpinLock<T> {
+    fn drop(&mut self) {
+        lockdep!(forget(self as *const Self as usize));
+    }
+}
+
+impl<'a, T> Deref for SpinLockGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl<'a, T> DerefMut for SpinLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.guard.as_mut().unwrap()
+    }
+}
+
+impl<'a, T> Drop for SpinLockGuard<'a, T> {
+    /// 先释放锁再恢复中断
+    fn drop(&mut self) {
+        self.guard.take();
+        lockdep!(release(self.addr));
+        if self.interrupts_enabled {
+            interrupts::enable();
+        }
+    }
+}
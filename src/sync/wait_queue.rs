@@ -0,0 +1,144 @@
+use alloc::collections::VecDeque;
+use core::task::Waker;
+
+use x86_64::instructions::interrupts;
+
+use crate::sync::SpinLock;
+use crate::thread::{self, scheduler, ThreadId};
+
+enum Waiter {
+    Thread(ThreadId),
+    Task(Waker),
+}
+
+impl Waiter {
+    fn wake(self) {
+        match self {
+            Waiter::Thread(id) => thread::unblock(id),
+            Waiter::Task(waker) => waker.wake(),
+        }
+    }
+}
+
+/// 等待某个条件成立的线程或异步任务队列,按 FIFO 顺序唤醒
+pub struct WaitQueue {
+    waiters: SpinLock<VecDeque<Waiter>>,
+}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        WaitQueue { waiters: SpinLock::new(VecDeque::new()) }
+    }
+
+    /// 挂起当前线程直到 `condition` 返回 `Some`
+    ///
+    /// 入队后会再检查一次条件,入队与阻塞之间的唤醒由调度器记下,不会丢失。
+    /// 线程尚未初始化时退化为自旋等待。
+    #[track_caller]
+    pub fn wait_until<F, R>(&self, mut condition: F) -> R where F: FnMut() -> Option<R> {
+        lockdep!(might_sleep(core::panic::Location::caller()));
+        loop {
+            if let Some(result) = condition() {
+                return result;
+            }
+            if !scheduler::is_initialized() {
+                core::hint::spin_loop();
+                continue;
+            }
+            let woken = interrupts::without_interrupts(|| {
+                let current = thread::current();
+                self.waiters.lock().push_back(Waiter::Thread(current));
+                if let Some(result) = condition() {
+                    self.remove_thread(current);
+                    return Some(result);
+                }
+                thread::block_current();
+                self.remove_thread(current);
+                None
+            });
+            if let Some(result) = woken {
+                return result;
+            }
+        }
+    }
+
+    /// 入队后执行 `release`(如释放互斥锁)再挂起当前线程,直到被唤醒
+    ///
+    /// 入队与释放在关中断下完成,其间的唤醒不会丢失。
+    #[track_caller]
+    pub fn wait_after<F>(&self, release: F) where F: FnOnce() {
+        lockdep!(might_sleep(core::panic::Location::caller()));
+        if !scheduler::is_initialized() {
+            release();
+            core::hint::spin_loop();
+            return;
+        }
+        interrupts::without_interrupts(|| {
+            let current = thread::current();
+            self.waiters.lock().push_back(Waiter::Thread(current));
+            release();
+            thread::block_current();
+            self.remove_thread(current);
+        });
+    }
+
+    /// 无条件挂起当前线程,直到被唤醒
+    #[track_caller]
+    pub fn wait(&self) {
+        self.wait_after(|| {});
+    }
+
+    /// 登记异步任务的 waker,条件由调用者在 `poll` 中检查
+    pub fn register(&self, waker: &Waker) {
+        let mut waiters = self.waiters.lock();
+        let registered = waiters.iter().any(|waiter| match waiter {
+            Waiter::Task(old) => old.will_wake(waker),
+            Waiter::Thread(_) => false,
+        });
+        if !registered {
+            waiters.push_back(Waiter::Task(waker.clone()));
+        }
+    }
+
+    /// 被唤醒的线程可能还在队列中(如被定时器唤醒),离开前将其移除
+    fn remove_thread(&self, id: ThreadId) {
+        self.waiters.lock().retain(|waiter| match waiter {
+            Waiter::Thread(waiting) => *waiting != id,
+            Waiter::Task(_) => true,
+        });
+    }
+
+    pub fn notify_one(&self) -> bool {
+        let waiter = self.waiters.lock().pop_front();
+        match waiter {
+            Some(waiter) => {
+                waiter.wake();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn notify_all(&self) -> usize {
+        let waiters = core::mem::take(&mut *self.waiters.lock());
+        let count = waiters.len();
+        for waiter in waiters {
+            waiter.wake();
+        }
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiters.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,201 @@
+use x86_64::instructions::interrupts;
+use x86_64::registers::model_specific::{Efer, EferFlags, LStar, SFMask, Star};
+use x86_64::registers::rflags::RFlags;
+
+use crate::{gdt, println, process, user};
+use crate::process::ExitStatus;
+
+pub use errno::Errno;
+
+pub mod errno;
+pub mod entry;
+pub mod uaccess;
+pub mod ipc;
+mod fs;
+mod handle;
+mod io;
+mod memory;
+mod signal;
+mod task;
+
+/// `int 0x80` 兼容入口的向量号
+pub const SYSCALL_VECTOR: u8 = 0x80;
+
+/// 系统调用号,调用约定与 Linux 相同:rax 为调用号,参数依次为 rdi, rsi, rdx, r10, r8, r9
+pub mod nr {
+    pub const WRITE: usize = 0;
+    pub const EXIT: usize = 1;
+    pub const YIELD: usize = 2;
+    pub const SLEEP: usize = 3;
+    pub const MMAP: usize = 4;
+    pub const MUNMAP: usize = 5;
+    pub const GETPID: usize = 6;
+    pub const GETPPID: usize = 7;
+    pub const WAITPID: usize = 8;
+    pub const FORK: usize = 9;
+    pub const EXEC: usize = 10;
+    pub const KILL: usize = 11;
+    pub const SIGACTION: usize = 12;
+    pub const SIGPROCMASK: usize = 13;
+    pub const SIGRETURN: usize = 14;
+    pub const CHANNEL_CREATE: usize = 15;
+    pub const CHANNEL_SEND: usize = 16;
+    pub const CHANNEL_RECV: usize = 17;
+    pub const HANDLE_CLOSE: usize = 18;
+    pub const HANDLE_DUP: usize = 19;
+    pub const SHM_CREATE: usize = 20;
+    pub const SHM_MAP: usize = 21;
+    pub const READ: usize = 22;
+    pub const CLOSE: usize = 23;
+    pub const DUP: usize = 24;
+    pub const DUP2: usize = 25;
+    pub const PIPE: usize = 26;
+    pub const FD_FROM_HANDLE: usize = 27;
+    pub const OPEN: usize = 28;
+    pub const LSEEK: usize = 29;
+    pub const GETDENTS: usize = 30;
+    pub const STAT: usize = 31;
+    pub const FSTAT: usize = 32;
+    pub const MKDIR: usize = 33;
+    pub const UNLINK: usize = 34;
+    pub const RMDIR: usize = 35;
+    pub const RENAME: usize = 36;
+    pub const SYMLINK: usize = 37;
+    pub const READLINK: usize = 38;
+    pub const CHDIR: usize = 39;
+    pub const GETCWD: usize = 40;
+    pub const TRUNCATE: usize = 41;
+    pub const FTRUNCATE: usize = 42;
+}
+
+const SYSCALL_COUNT: usize = 64;
+
+/// 两个入口在内核栈上保存的用户态现场,返回值写回 `rax`
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct SyscallFrame {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub r10: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+}
+
+pub type SyscallResult = Result<u64, Errno>;
+pub type SyscallHandler = fn(&mut SyscallFrame, [u64; 6]) -> SyscallResult;
+
+/// 下标即系统调用号
+static SYSCALL_TABLE: [Option<SyscallHandler>; SYSCALL_COUNT] = build_table();
+
+const fn build_table() -> [Option<SyscallHandler>; SYSCALL_COUNT] {
+    let mut table: [Option<SyscallHandler>; SYSCALL_COUNT] = [None; SYSCALL_COUNT];
+    table[nr::WRITE] = Some(io::sys_write);
+    table[nr::EXIT] = Some(task::sys_exit);
+    table[nr::YIELD] = Some(task::sys_yield);
+    table[nr::SLEEP] = Some(task::sys_sleep);
+    table[nr::MMAP] = Some(memory::sys_mmap);
+    table[nr::MUNMAP] = Some(memory::sys_munmap);
+    table[nr::GETPID] = Some(task::sys_getpid);
+    table[nr::GETPPID] = Some(task::sys_getppid);
+    table[nr::WAITPID] = Some(task::sys_waitpid);
+    table[nr::FORK] = Some(task::sys_fork);
+    table[nr::EXEC] = Some(task::sys_exec);
+    table[nr::KILL] = Some(signal::sys_kill);
+    table[nr::SIGACTION] = Some(signal::sys_sigaction);
+    table[nr::SIGPROCMASK] = Some(signal::sys_sigprocmask);
+    table[nr::SIGRETURN] = Some(signal::sys_sigreturn);
+    table[nr::CHANNEL_CREATE] = Some(ipc::sys_channel_create);
+    table[nr::CHANNEL_SEND] = Some(ipc::sys_channel_send);
+    table[nr::CHANNEL_RECV] = Some(ipc::sys_channel_recv);
+    table[nr::HANDLE_CLOSE] = Some(handle::sys_handle_close);
+    table[nr::HANDLE_DUP] = Some(handle::sys_handle_dup);
+    table[nr::SHM_CREATE] = Some(ipc::sys_shm_create);
+    table[nr::SHM_MAP] = Some(ipc::sys_shm_map);
+    table[nr::READ] = Some(io::sys_read);
+    table[nr::CLOSE] = Some(io::sys_close);
+    table[nr::DUP] = Some(io::sys_dup);
+    table[nr::DUP2] = Some(io::sys_dup2);
+    table[nr::PIPE] = Some(io::sys_pipe);
+    table[nr::FD_FROM_HANDLE] = Some(io::sys_fd_from_handle);
+    table[nr::OPEN] = Some(fs::sys_open);
+    table[nr::LSEEK] = Some(fs::sys_lseek);
+    table[nr::GETDENTS] = Some(fs::sys_getdents);
+    table[nr::STAT] = Some(fs::sys_stat);
+    table[nr::FSTAT] = Some(fs::sys_fstat);
+    table[nr::MKDIR] = Some(fs::sys_mkdir);
+    table[nr::UNLINK] = Some(fs::sys_unlink);
+    table[nr::RMDIR] = Some(fs::sys_rmdir);
+    table[nr::RENAME] = Some(fs::sys_rename);
+    table[nr::SYMLINK] = Some(fs::sys_symlink);
+    table[nr::READLINK] = Some(fs::sys_readlink);
+    table[nr::CHDIR] = Some(fs::sys_chdir);
+    table[nr::GETCWD] = Some(fs::sys_getcwd);
+    table[nr::TRUNCATE] = Some(fs::sys_truncate);
+    table[nr::FTRUNCATE] = Some(fs::sys_ftruncate);
+    table
+}
+
+/// 打开 SYSCALL/SYSRET,需在 GDT 初始化之后调用
+pub fn init() {
+    println!("Init syscall ...");
+    unsafe {
+        Efer::write(Efer::read() | EferFlags::SYSTEM_CALL_EXTENSIONS);
+        Star::write(gdt::user_code_selector(), gdt::user_data_selector(),
+                    gdt::kernel_code_selector(), gdt::kernel_data_selector())
+            .expect("GDT layout incompatible with SYSRET");
+    }
+    LStar::write(entry::syscall_entry_addr());
+    // 进入内核时关中断并清除方向、单步与对齐检查标志
+    SFMask::write(RFlags::INTERRUPT_FLAG | RFlags::DIRECTION_FLAG | RFlags::TRAP_FLAG | RFlags::ALIGNMENT_CHECK);
+}
+
+fn dispatch(frame: &mut SyscallFrame) {
+    interrupts::enable();
+    let number = frame.rax as usize;
+    let args = [frame.rdi, frame.rsi, frame.rdx, frame.r10, frame.r8, frame.r9];
+    let result = match SYSCALL_TABLE.get(number).copied().flatten() {
+        Some(handler) => handler(frame, args),
+        None => Err(Errno::ENOSYS),
+    };
+    frame.rax = match result {
+        Ok(value) => value,
+        Err(errno) => (-errno.as_i64()) as u64,
+    };
+    process::signal::deliver(frame);
+    interrupts::disable();
+}
+
+#[no_mangle]
+extern "C" fn mongo_os_syscall_dispatch(frame: &mut SyscallFrame) {
+    frame.cs = gdt::user_code_selector().0 as u64;
+    frame.ss = gdt::user_data_selector().0 as u64;
+    dispatch(frame);
+    check_return_address(frame);
+}
+
+#[no_mangle]
+extern "C" fn mongo_os_int80_dispatch(frame: &mut SyscallFrame) {
+    dispatch(frame);
+    check_return_address(frame);
+}
+
+/// sysret/iretq 到非规范地址会在 ring 0 触发 #GP,返回用户态前检查未截断的 rip
+pub fn check_return_address(frame: &SyscallFrame) {
+    if !user::is_user_pointer(frame.rip) {
+        println!("syscall: bad return address {:#x}, killed", frame.rip);
+        process::exit(ExitStatus::Signaled(process::signal::SIGSEGV));
+    }
+}
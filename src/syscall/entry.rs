@@ -0,0 +1,100 @@
+use core::arch::global_asm;
+
+use x86_64::VirtAddr;
+
+/// 进入 syscall 时暂存用户栈指针,随即压入内核栈
+#[export_name = "mongo_os_syscall_user_rsp"]
+static mut SYSCALL_USER_RSP: u64 = 0;
+
+// 两个入口在内核栈上构造相同布局的 SyscallFrame:
+// 末尾 5 项与中断返回帧一致(rip, cs, rflags, rsp, ss),其上依次是通用寄存器。
+// SFMASK 与中断门都会清除 IF,入口处不会被打断。
+global_asm!(r#"
+.global mongo_os_syscall_entry
+mongo_os_syscall_entry:
+    mov [rip + mongo_os_syscall_user_rsp], rsp
+    mov rsp, [rip + mongo_os_tss + 4]
+    push 0
+    push qword ptr [rip + mongo_os_syscall_user_rsp]
+    push r11
+    push 0
+    push rcx
+    push rax
+    push rdi
+    push rsi
+    push rdx
+    push r10
+    push r8
+    push r9
+    push rbx
+    push rbp
+    push r12
+    push r13
+    push r14
+    push r15
+    mov rdi, rsp
+    call mongo_os_syscall_dispatch
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbp
+    pop rbx
+    pop r9
+    pop r8
+    pop r10
+    pop rdx
+    pop rsi
+    pop rdi
+    pop rax
+    pop rcx
+    add rsp, 8
+    pop r11
+    pop rsp
+    sysretq
+
+.global mongo_os_int80_entry
+mongo_os_int80_entry:
+    push rax
+    push rdi
+    push rsi
+    push rdx
+    push r10
+    push r8
+    push r9
+    push rbx
+    push rbp
+    push r12
+    push r13
+    push r14
+    push r15
+    mov rdi, rsp
+    call mongo_os_int80_dispatch
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbp
+    pop rbx
+    pop r9
+    pop r8
+    pop r10
+    pop rdx
+    pop rsi
+    pop rdi
+    pop rax
+    iretq
+"#);
+
+extern "C" {
+    fn mongo_os_syscall_entry();
+    fn mongo_os_int80_entry();
+}
+
+pub fn syscall_entry_addr() -> VirtAddr {
+    VirtAddr::new(mongo_os_syscall_entry as unsafe extern "C" fn() as usize as u64)
+}
+
+pub fn int80_entry_addr() -> VirtAddr {
+    VirtAddr::new(mongo_os_int80_entry as unsafe extern "C" fn() as usize as u64)
+}
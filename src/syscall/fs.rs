@@ -0,0 +1,222 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use crate::{fs, process};
+use crate::fs::{InodeKind, Metadata, OpenFile, OpenFlags};
+use crate::fs::path::{self, PATH_MAX};
+use crate::process::fd::SeekFrom;
+use crate::syscall::{Errno, SyscallFrame, SyscallResult, uaccess};
+use crate::syscall::io::{file, to_fd};
+
+/// lseek 的 whence
+pub const SEEK_SET: u64 = 0;
+pub const SEEK_CUR: u64 = 1;
+pub const SEEK_END: u64 = 2;
+/// stat 的 flags:不跟随最后一个分量的符号链接
+pub const STAT_NOFOLLOW: u64 = 0x1;
+
+/// stat/fstat 写回的文件信息,时间为 Unix 时间戳(秒)
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct Stat {
+    pub ino: u64,
+    /// `InodeKind` 的取值
+    pub kind: u32,
+    pub nlink: u32,
+    pub size: u64,
+    pub atime: u64,
+    pub mtime: u64,
+    pub ctime: u64,
+}
+
+impl From<Metadata> for Stat {
+    fn from(metadata: Metadata) -> Self {
+        Stat {
+            ino: metadata.ino,
+            kind: metadata.kind as u32,
+            nlink: metadata.nlink,
+            size: metadata.size,
+            atime: metadata.atime,
+            mtime: metadata.mtime,
+            ctime: metadata.ctime,
+        }
+    }
+}
+
+/// getdents 写回的目录项头部,布局同 Linux 的 `linux_dirent64`,其后紧跟以 0 结尾的名字,整项按 8 字节对齐
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct DirentHeader {
+    ino: u64,
+    /// 下一项的序号
+    offset: u64,
+    reclen: u16,
+    /// `InodeKind` 的取值
+    kind: u8,
+}
+
+/// 读取用户传入的路径,相对路径接在当前进程的工作目录之后
+pub(super) fn user_path(addr: u64) -> Result<String, Errno> {
+    let path = uaccess::read_user_str(addr, PATH_MAX)?;
+    if path.is_empty() {
+        return Err(Errno::ENOENT);
+    }
+    let cwd = process::with_current(|process| process.cwd().to_string()).ok_or(Errno::EPERM)?;
+    Ok(path::join(&cwd, &path))
+}
+
+/// 描述符指向的对象不是打开的文件时返回 `err`
+fn with_open_file<F, R>(fd: u64, err: Errno, f: F) -> Result<R, Errno> where F: FnOnce(&OpenFile) -> Result<R, Errno> {
+    let file = file(to_fd(fd)?)?;
+    let open_file = file.as_any().downcast_ref::<OpenFile>().ok_or(err)?;
+    f(open_file)
+}
+
+/// open(path, flags):返回新的文件描述符
+pub fn sys_open(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let path = user_path(args[0])?;
+    let flags = u32::try_from(args[1]).ok().and_then(OpenFlags::from_bits).ok_or(Errno::EINVAL)?;
+    let file = fs::open(&path, flags)?;
+    process::with_current(|process| process.files().install(file))
+        .ok_or(Errno::EPERM)?
+        .map(u64::from)
+}
+
+/// lseek(fd, offset, whence):返回新的偏移
+pub fn sys_lseek(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let offset = args[1] as i64;
+    let pos = match args[2] {
+        SEEK_SET if offset >= 0 => SeekFrom::Start(offset as u64),
+        SEEK_CUR => SeekFrom::Current(offset),
+        SEEK_END => SeekFrom::End(offset),
+        _ => return Err(Errno::EINVAL),
+    };
+    file(to_fd(args[0])?)?.seek(pos)
+}
+
+/// getdents(fd, buf, len):尽量多地填入目录项,返回写入的字节数,0 表示已读完
+pub fn sys_getdents(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (buf, len) = (args[1], args[2] as usize);
+    uaccess::check_range(buf, len, true)?;
+    with_open_file(args[0], Errno::ENOTDIR, |file| {
+        if file.metadata().kind != InodeKind::Directory {
+            return Err(Errno::ENOTDIR);
+        }
+        let mut out = Vec::new();
+        while let Some(entry) = file.read_dir()? {
+            let reclen = (size_of::<DirentHeader>() + entry.name.len() + 1 + 7) & !7;
+            if out.len() + reclen > len {
+                file.unread_dir();
+                if out.is_empty() {
+                    return Err(Errno::EINVAL);
+                }
+                break;
+            }
+            let header = DirentHeader { ino: entry.ino, offset: file.offset(), reclen: reclen as u16, kind: entry.kind as u8 };
+            let start = out.len();
+            out.extend_from_slice(unsafe {
+                core::slice::from_raw_parts(&header as *const DirentHeader as *const u8, size_of::<DirentHeader>())
+            });
+            out.extend_from_slice(entry.name.as_bytes());
+            out.resize(start + reclen, 0);
+        }
+        uaccess::copy_to_user(buf, &out)?;
+        Ok(out.len() as u64)
+    })
+}
+
+/// stat(path, stat, flags)
+pub fn sys_stat(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (out, flags) = (args[1], args[2]);
+    if flags & !STAT_NOFOLLOW != 0 {
+        return Err(Errno::EINVAL);
+    }
+    uaccess::check_range(out, size_of::<Stat>(), true)?;
+    let metadata = fs::stat(&user_path(args[0])?, flags & STAT_NOFOLLOW == 0)?;
+    uaccess::write_user(out, Stat::from(metadata))?;
+    Ok(0)
+}
+
+/// fstat(fd, stat)
+pub fn sys_fstat(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let out = args[1];
+    uaccess::check_range(out, size_of::<Stat>(), true)?;
+    let metadata = with_open_file(args[0], Errno::EINVAL, |file| Ok(file.metadata()))?;
+    uaccess::write_user(out, Stat::from(metadata))?;
+    Ok(0)
+}
+
+/// mkdir(path)
+pub fn sys_mkdir(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    fs::mkdir(&user_path(args[0])?)?;
+    Ok(0)
+}
+
+/// unlink(path)
+pub fn sys_unlink(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    fs::unlink(&user_path(args[0])?)?;
+    Ok(0)
+}
+
+/// rmdir(path)
+pub fn sys_rmdir(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    fs::rmdir(&user_path(args[0])?)?;
+    Ok(0)
+}
+
+/// rename(old, new)
+pub fn sys_rename(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    fs::rename(&user_path(args[0])?, &user_path(args[1])?)?;
+    Ok(0)
+}
+
+/// symlink(target, path):`target` 原样保存,解析时才相对于链接所在目录
+pub fn sys_symlink(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let target = uaccess::read_user_str(args[0], PATH_MAX)?;
+    fs::symlink(&target, &user_path(args[1])?)?;
+    Ok(0)
+}
+
+/// readlink(path, buf, len):返回写入的字节数,不补 0,超出 `len` 的部分被截断
+pub fn sys_readlink(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (buf, len) = (args[1], args[2] as usize);
+    let target = fs::read_link(&user_path(args[0])?)?;
+    let count = target.len().min(len);
+    uaccess::copy_to_user(buf, &target.as_bytes()[..count])?;
+    Ok(count as u64)
+}
+
+/// chdir(path)
+pub fn sys_chdir(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let resolved = path::resolve(&user_path(args[0])?, true)?;
+    if resolved.inode.metadata().kind != InodeKind::Directory {
+        return Err(Errno::ENOTDIR);
+    }
+    process::with_current(|process| process.set_cwd(resolved.path)).ok_or(Errno::EPERM)?;
+    Ok(0)
+}
+
+/// getcwd(buf, len):写入以 0 结尾的工作目录,返回包括 0 在内的长度
+pub fn sys_getcwd(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (buf, len) = (args[0], args[1] as usize);
+    let mut cwd = process::with_current(|process| process.cwd().to_string()).ok_or(Errno::EPERM)?.into_bytes();
+    cwd.push(0);
+    if cwd.len() > len {
+        return Err(Errno::ERANGE);
+    }
+    uaccess::copy_to_user(buf, &cwd)?;
+    Ok(cwd.len() as u64)
+}
+
+/// truncate(path, len)
+pub fn sys_truncate(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    fs::truncate(&user_path(args[0])?, args[1])?;
+    Ok(0)
+}
+
+/// ftruncate(fd, len):需要以可写方式打开
+pub fn sys_ftruncate(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    with_open_file(args[0], Errno::EINVAL, |file| file.truncate(args[1]))?;
+    Ok(0)
+}
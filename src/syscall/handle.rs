@@ -0,0 +1,24 @@
+use crate::process;
+use crate::process::handle::{Handle, Rights};
+use crate::syscall::{Errno, SyscallFrame, SyscallResult};
+
+pub(super) fn to_handle(raw: u64) -> Result<Handle, Errno> {
+    Handle::try_from(raw).map_err(|_| Errno::EBADF)
+}
+
+/// handle_close(handle)
+pub fn sys_handle_close(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let handle = to_handle(args[0])?;
+    // 对象在进程表锁之外销毁
+    let entry = process::with_current(|process| process.handles().remove(handle)).ok_or(Errno::EPERM)??;
+    drop(entry);
+    Ok(0)
+}
+
+/// handle_dup(handle, rights):新句柄的权限为原权限与 `rights` 的交集
+pub fn sys_handle_dup(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (handle, rights) = (to_handle(args[0])?, Rights::from_bits_truncate(args[1] as u32));
+    process::with_current(|process| process.handles().duplicate(handle, rights))
+        .ok_or(Errno::EPERM)?
+        .map(u64::from)
+}
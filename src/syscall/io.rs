@@ -0,0 +1,97 @@
+use alloc::sync::Arc;
+use alloc::vec;
+use core::mem::size_of;
+
+use crate::{ipc, process};
+use crate::ipc::Endpoint;
+use crate::process::fd::{Fd, FileObject};
+use crate::process::handle::Rights;
+use crate::process::signal;
+use crate::syscall::{Errno, SyscallFrame, SyscallResult, uaccess};
+use crate::syscall::handle::to_handle;
+
+/// 单次 read/write 最多传输的字节数,超出部分由调用者再次发起
+const IO_MAX: usize = 64 * 1024;
+
+pub(super) fn to_fd(raw: u64) -> Result<Fd, Errno> {
+    Fd::try_from(raw).map_err(|_| Errno::EBADF)
+}
+
+pub(super) fn file(fd: Fd) -> Result<Arc<dyn FileObject>, Errno> {
+    process::with_current(|process| process.files().get(fd)).ok_or(Errno::EPERM)?
+}
+
+/// read(fd, buf, len):返回读到的字节数,0 表示文件结束
+pub fn sys_read(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (fd, buf, len) = (to_fd(args[0])?, args[1], (args[2] as usize).min(IO_MAX));
+    uaccess::check_range(buf, len, true)?;
+    let file = file(fd)?;
+    let mut data = vec![0; len];
+    let count = file.read(&mut data)?;
+    uaccess::copy_to_user(buf, &data[..count])?;
+    Ok(count as u64)
+}
+
+/// write(fd, buf, len):返回写入的字节数;写入读端已关闭的管道时还会收到 SIGPIPE
+pub fn sys_write(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (fd, buf, len) = (to_fd(args[0])?, args[1], (args[2] as usize).min(IO_MAX));
+    let file = file(fd)?;
+    let data = uaccess::copy_from_user(buf, len)?;
+    match file.write(&data) {
+        Err(Errno::EPIPE) => {
+            let _ = signal::send(process::current_pid(), signal::SIGPIPE);
+            Err(Errno::EPIPE)
+        }
+        result => result.map(|count| count as u64),
+    }
+}
+
+/// close(fd)
+pub fn sys_close(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let fd = to_fd(args[0])?;
+    // 对象在进程表锁之外销毁
+    let file = process::with_current(|process| process.files().remove(fd)).ok_or(Errno::EPERM)??;
+    drop(file);
+    Ok(0)
+}
+
+/// dup(fd):返回指向同一对象的最小空闲描述符
+pub fn sys_dup(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let fd = to_fd(args[0])?;
+    process::with_current(|process| process.files().dup(fd))
+        .ok_or(Errno::EPERM)?
+        .map(u64::from)
+}
+
+/// dup2(old, new):`new` 已打开时先关闭
+pub fn sys_dup2(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (old, new) = (to_fd(args[0])?, to_fd(args[1])?);
+    let replaced = process::with_current(|process| process.files().dup2(old, new)).ok_or(Errno::EPERM)??;
+    drop(replaced);
+    Ok(new as u64)
+}
+
+/// pipe(fds):读端与写端的描述符写入 `fds[0..2]`
+pub fn sys_pipe(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let out = args[0];
+    uaccess::check_range(out, size_of::<[Fd; 2]>(), true)?;
+    let (reader, writer) = ipc::pipe();
+    let fds = process::with_current(|process| {
+        let files = process.files();
+        if files.free() < 2 {
+            return Err(Errno::EMFILE);
+        }
+        Ok([files.install(Arc::new(reader))?, files.install(Arc::new(writer))?])
+    }).ok_or(Errno::EPERM)??;
+    uaccess::write_user(out, fds)?;
+    Ok(0)
+}
+
+/// fd_from_handle(handle):为通道句柄分配描述符,之后可用 read/write 收发纯数据消息;句柄仍然有效
+pub fn sys_fd_from_handle(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let handle = to_handle(args[0])?;
+    process::with_current(|process| {
+        let endpoint = process.handles().get_as::<Endpoint>(handle, Rights::READ | Rights::WRITE)?;
+        process.files().install(endpoint)
+    }).ok_or(Errno::EPERM)?.map(u64::from)
+}
@@ -0,0 +1,268 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::mem::size_of;
+
+use x86_64::VirtAddr;
+
+use crate::{process, user};
+use crate::ipc::{self, ChannelError, Endpoint, Message, SendError, SharedMemory};
+use crate::ipc::channel::{DEFAULT_CAPACITY, MAX_DATA, MAX_HANDLES, MAX_PAGES};
+use crate::ipc::shm::NAME_MAX;
+use crate::mem::frame_refs;
+use crate::process::Process;
+use crate::process::handle::{Handle, Rights};
+use crate::process::signal;
+use crate::syscall::{Errno, SyscallFrame, SyscallResult};
+use crate::syscall::handle::to_handle;
+use crate::syscall::memory::{PROT_READ, PROT_WRITE};
+use crate::syscall::uaccess;
+
+/// send/recv 的 flags:不阻塞,队列满或为空时返回 EAGAIN
+pub const CHANNEL_NONBLOCK: u64 = 0x1;
+
+const PAGE_SIZE: u64 = 4096;
+
+/// 用户态的消息描述
+///
+/// 发送时各字段描述要发送的内容,`pages` 为 mmap 区中页对齐的起始地址,这些页从发送方转移给接收方。
+/// 接收时长度字段为缓冲区容量,返回后改写为实际长度,`pages` 改写为映射到的地址;
+/// 缓冲区不足时返回 EMSGSIZE 并写回所需长度,消息留在队列中。
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct UserMessage {
+    pub data: u64,
+    pub data_len: u64,
+    pub handles: u64,
+    pub handles_len: u64,
+    pub pages: u64,
+    pub pages_len: u64,
+}
+
+fn endpoint(handle: Handle, rights: Rights) -> Result<Arc<Endpoint>, Errno> {
+    process::with_current(|process| process.handles().get_as::<Endpoint>(handle, rights)).ok_or(Errno::EPERM)?
+}
+
+/// channel_create(handles):创建通道,两端的句柄写入 `handles[0..2]`
+pub fn sys_channel_create(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let out = args[0];
+    uaccess::check_range(out, size_of::<[Handle; 2]>(), true)?;
+    let (first, second) = ipc::channel(DEFAULT_CAPACITY);
+    let handles = process::with_current(|process| {
+        let table = process.handles();
+        [table.insert(Arc::new(first), Rights::ALL), table.insert(Arc::new(second), Rights::ALL)]
+    }).ok_or(Errno::EPERM)?;
+    uaccess::write_user(out, handles)?;
+    Ok(0)
+}
+
+/// channel_send(handle, message, flags)
+pub fn sys_channel_send(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (handle, desc_addr, flags) = (to_handle(args[0])?, args[1], args[2]);
+    if flags & !CHANNEL_NONBLOCK != 0 {
+        return Err(Errno::EINVAL);
+    }
+    let desc: UserMessage = uaccess::read_user(desc_addr)?;
+    if desc.data_len > MAX_DATA as u64 || desc.handles_len > MAX_HANDLES as u64 || desc.pages_len > MAX_PAGES as u64 {
+        return Err(Errno::EMSGSIZE);
+    }
+    let endpoint = endpoint(handle, Rights::WRITE)?;
+    let data = uaccess::copy_from_user(desc.data, desc.data_len as usize)?;
+    let handles = (0..desc.handles_len)
+        .map(|i| uaccess::read_user::<Handle>(desc.handles + i * size_of::<Handle>() as u64))
+        .collect::<Result<Vec<Handle>, Errno>>()?;
+    let pages = VirtAddr::try_new(desc.pages).map_err(|_| Errno::EINVAL)?;
+    if desc.pages_len > 0 {
+        if !pages.is_aligned(PAGE_SIZE) {
+            return Err(Errno::EINVAL);
+        }
+        user::check_mmap_range(pages, desc.pages_len * PAGE_SIZE)?;
+    }
+    let message = take_message(&endpoint, data, &handles, pages, desc.pages_len as usize)?;
+    let result = if flags & CHANNEL_NONBLOCK != 0 {
+        endpoint.try_send(message)
+    } else {
+        endpoint.send_cancellable(message, signal::has_deliverable)
+    };
+    match result {
+        Ok(()) => Ok(0),
+        Err(SendError { kind, message }) => {
+            restore_message(message, &handles, pages);
+            Err(kind.into())
+        }
+    }
+}
+
+/// 先移出句柄,再在进程表锁外取出页;取页失败时放回句柄,任何一步失败都不改变进程状态
+fn take_message(via: &Endpoint, data: Vec<u8>, handles: &[Handle], pages: VirtAddr, page_count: usize) -> Result<Message, Errno> {
+    let mut message = process::with_current(|process| take_handles(process, via, handles)).ok_or(Errno::EPERM)??;
+    message.data = data;
+    if page_count > 0 {
+        match process::with_address_space(|space| space.take_pages(pages, page_count)) {
+            Ok(Some(frames)) => message.pages = frames,
+            result => {
+                restore_message(message, handles, pages);
+                return Err(result.err().unwrap_or(Errno::EFAULT));
+            }
+        }
+    }
+    Ok(message)
+}
+
+/// 检查全部句柄后一起移出,检查失败时不改变句柄表
+fn take_handles(process: &mut Process, via: &Endpoint, handles: &[Handle]) -> Result<Message, Errno> {
+    let mut endpoints = Vec::new();
+    for (i, handle) in handles.iter().enumerate() {
+        if handles[..i].contains(handle) {
+            return Err(Errno::EINVAL);
+        }
+        let entry = process.handles().get(*handle)?;
+        if !entry.rights.contains(Rights::TRANSFER) {
+            return Err(Errno::EPERM);
+        }
+        if let Ok(endpoint) = entry.downcast::<Endpoint>(Rights::NONE) {
+            endpoints.push(endpoint);
+        }
+    }
+    // 登记随消息存在,直到消息被接收或销毁,阻塞等待发送期间也计入
+    let transit = via.reserve_transfer(endpoints.iter().map(|endpoint| &**endpoint)).ok_or(Errno::EINVAL)?;
+    let table = process.handles();
+    let entries = handles.iter().filter_map(|handle| table.remove(*handle).ok()).collect();
+    Ok(Message { data: Vec::new(), handles: entries, pages: Vec::new(), transit: Some(transit) })
+}
+
+/// 发送失败时把句柄与页放回原处
+fn restore_message(mut message: Message, handles: &[Handle], pages: VirtAddr) {
+    let entries = core::mem::take(&mut message.handles);
+    let frames = core::mem::take(&mut message.pages);
+    let restored = process::with_current(|process| {
+        for (handle, entry) in handles.iter().zip(entries) {
+            process.handles().restore(*handle, entry);
+        }
+    });
+    debug_assert!(restored.is_some());
+    if !frames.is_empty() {
+        let _ = process::with_address_space(|space| space.map_frames(Some(pages), frames));
+    }
+}
+
+/// channel_recv(handle, message, flags)
+pub fn sys_channel_recv(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (handle, desc_addr, flags) = (to_handle(args[0])?, args[1], args[2]);
+    if flags & !CHANNEL_NONBLOCK != 0 {
+        return Err(Errno::EINVAL);
+    }
+    uaccess::check_range(desc_addr, size_of::<UserMessage>(), true)?;
+    let desc: UserMessage = uaccess::read_user(desc_addr)?;
+    let data_capacity = desc.data_len.min(MAX_DATA as u64) as usize;
+    let handles_capacity = desc.handles_len.min(MAX_HANDLES as u64) as usize;
+    let pages_capacity = desc.pages_len.min(MAX_PAGES as u64) as usize;
+    uaccess::check_range(desc.data, data_capacity, true)?;
+    uaccess::check_range(desc.handles, handles_capacity * size_of::<Handle>(), true)?;
+    let endpoint = endpoint(handle, Rights::READ)?;
+
+    let required = Cell::new((0, 0, 0));
+    let accept = |message: &Message| {
+        required.set((message.data.len(), message.handles.len(), message.pages.len()));
+        message.data.len() <= data_capacity && message.handles.len() <= handles_capacity && message.pages.len() <= pages_capacity
+    };
+    let result = if flags & CHANNEL_NONBLOCK != 0 {
+        endpoint.try_recv_with(accept)
+    } else {
+        endpoint.recv_cancellable(accept, signal::has_deliverable)
+    };
+    let mut message = match result {
+        Ok(message) => message,
+        Err(ChannelError::TooLarge) => {
+            let (data_len, handles_len, pages_len) = required.get();
+            let needed = UserMessage { data_len: data_len as u64, handles_len: handles_len as u64, pages_len: pages_len as u64, ..desc };
+            uaccess::write_user(desc_addr, needed)?;
+            return Err(Errno::EMSGSIZE);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    // 交付失败时撤销已做的修改并把消息放回队首,消息不会丢失
+    if let Err(err) = deliver(&mut message, &desc, desc_addr) {
+        endpoint.unrecv(message);
+        return Err(err);
+    }
+    Ok(0)
+}
+
+/// 把消息交付给当前进程:复制数据、映射页、安装句柄并写回描述;失败时撤销,消息原样保留
+fn deliver(message: &mut Message, desc: &UserMessage, desc_addr: u64) -> Result<(), Errno> {
+    uaccess::copy_to_user(desc.data, &message.data)?;
+    let page_count = message.pages.len();
+    let pages = if page_count == 0 {
+        0
+    } else {
+        let frames = message.pages.clone();
+        process::with_address_space(|space| {
+            // 映射持有额外的引用,失败时 map_frames 释放的是这一份,消息中的页仍然有效
+            frames.iter().for_each(|(frame, _)| frame_refs::share(*frame));
+            space.map_frames(None, frames)
+        })?.map_err(|_| Errno::ENOMEM)?.as_u64()
+    };
+    let installed = process::with_current(|process| {
+        let table = process.handles();
+        message.handles.iter().map(|entry| table.insert(entry.object.clone(), entry.rights)).collect::<Vec<Handle>>()
+    });
+    let handles = installed.clone().unwrap_or_default();
+    let written = (|| {
+        installed.ok_or(Errno::EPERM)?;
+        for (i, handle) in handles.iter().enumerate() {
+            uaccess::write_user(desc.handles + (i * size_of::<Handle>()) as u64, *handle)?;
+        }
+        let received = UserMessage {
+            data_len: message.data.len() as u64,
+            handles_len: handles.len() as u64,
+            pages,
+            pages_len: page_count as u64,
+            ..*desc
+        };
+        uaccess::write_user(desc_addr, received)
+    })();
+    if let Err(err) = written {
+        let removed = process::with_current(|process| {
+            handles.iter().filter_map(|handle| process.handles().remove(*handle).ok()).collect::<Vec<_>>()
+        });
+        drop(removed);
+        if page_count > 0 {
+            let _ = process::with_address_space(|space| space.unmap(VirtAddr::new(pages), page_count as u64 * PAGE_SIZE));
+        }
+        return Err(err);
+    }
+    // 交付成功:句柄已在表中,页的引用转给映射
+    message.handles.clear();
+    for (frame, _) in message.pages.drain(..) {
+        frame_refs::free(frame);
+    }
+    Ok(())
+}
+
+/// shm_create(name, size):`name` 为 0 时创建匿名对象,否则按名字打开或创建;返回句柄
+pub fn sys_shm_create(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (name_addr, size) = (args[0], args[1]);
+    let shm = if name_addr == 0 {
+        Arc::new(SharedMemory::new(size)?)
+    } else {
+        SharedMemory::open(&uaccess::read_user_str(name_addr, NAME_MAX + 1)?, size)?
+    };
+    process::with_current(|process| process.handles().insert(shm, Rights::ALL))
+        .ok_or(Errno::EPERM)
+        .map(u64::from)
+}
+
+/// shm_map(handle, prot):映射整个对象并返回地址,可写映射需要 WRITE 权限
+pub fn sys_shm_map(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (handle, prot) = (to_handle(args[0])?, args[1]);
+    if prot & !(PROT_READ | PROT_WRITE) != 0 {
+        return Err(Errno::EINVAL);
+    }
+    let writable = prot & PROT_WRITE != 0;
+    let rights = if writable { Rights::READ | Rights::WRITE } else { Rights::READ };
+    let shm = process::with_current(|process| process.handles().get_as::<SharedMemory>(handle, rights)).ok_or(Errno::EPERM)??;
+    // 映射整个对象可能涉及上千页,不持有进程表锁
+    process::with_address_space(|space| shm.map_into(space, writable))?.map(|addr| addr.as_u64())
+}
@@ -0,0 +1,28 @@
+use x86_64::VirtAddr;
+
+use crate::user;
+use crate::syscall::{Errno, SyscallFrame, SyscallResult};
+
+pub const PROT_READ: u64 = 0x1;
+pub const PROT_WRITE: u64 = 0x2;
+pub const PROT_EXEC: u64 = 0x4;
+
+/// mmap(addr, len, prot):匿名映射,地址由内核选择,`addr` 仅作提示
+pub fn sys_mmap(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (len, prot) = (args[1], args[2]);
+    if len == 0 || prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+        return Err(Errno::EINVAL);
+    }
+    user::map_anonymous(len, prot & PROT_WRITE != 0, prot & PROT_EXEC != 0)
+        .map(|addr| addr.as_u64())
+}
+
+/// munmap(addr, len)
+pub fn sys_munmap(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (addr, len) = (args[0], args[1]);
+    if addr % 4096 != 0 || len == 0 {
+        return Err(Errno::EINVAL);
+    }
+    user::unmap(VirtAddr::try_new(addr).map_err(|_| Errno::EINVAL)?, len)?;
+    Ok(0)
+}
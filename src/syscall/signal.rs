@@ -0,0 +1,47 @@
+use crate::process;
+use crate::process::{ExitStatus, Pid};
+use crate::process::signal::{self, SIG_DFL, SIG_IGN, SigAction, SigSet};
+use crate::syscall::{Errno, SyscallFrame, SyscallResult};
+use crate::user;
+
+/// kill(pid, sig):`sig` 为 0 时只检查进程是否存在
+pub fn sys_kill(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (pid, sig) = (args[0] as i64, args[1]);
+    if pid <= 0 || sig > u32::MAX as u64 {
+        return Err(Errno::EINVAL);
+    }
+    signal::send(Pid::from_u64(pid as u64), sig as u32)?;
+    Ok(0)
+}
+
+/// sigaction(sig, handler, mask, restorer):返回原处理函数;`handler` 为 SIG_DFL、SIG_IGN 或函数地址
+pub fn sys_sigaction(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (sig, handler, mask, restorer) = (args[0], args[1], args[2], args[3]);
+    if sig > u32::MAX as u64 {
+        return Err(Errno::EINVAL);
+    }
+    let action = match handler {
+        SIG_DFL => SigAction::Default,
+        SIG_IGN => SigAction::Ignore,
+        _ => {
+            if !user::is_user_pointer(handler) || !user::is_user_pointer(restorer) {
+                return Err(Errno::EFAULT);
+            }
+            SigAction::Handler { handler, restorer, mask: SigSet::from_bits(mask) }
+        }
+    };
+    signal::set_action(sig as u32, action)
+}
+
+/// sigprocmask(how, set):返回原阻塞集合
+pub fn sys_sigprocmask(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    signal::set_blocked(args[0], SigSet::from_bits(args[1])).map(|old| old.bits())
+}
+
+/// 只能由信号处理函数的 restorer 调用,信号帧损坏时以 SIGSEGV 结束进程
+pub fn sys_sigreturn(frame: &mut SyscallFrame, _args: [u64; 6]) -> SyscallResult {
+    match signal::sigreturn(frame) {
+        Ok(rax) => Ok(rax),
+        Err(_) => process::exit(ExitStatus::Signaled(signal::SIGSEGV)),
+    }
+}
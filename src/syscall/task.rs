@@ -0,0 +1,86 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::{process, thread};
+use crate::process::{ExitStatus, Pid};
+use crate::syscall::{Errno, SyscallFrame, SyscallResult};
+use crate::syscall::fs::user_path;
+use crate::syscall::uaccess;
+
+/// 单个参数的最大长度
+const ARG_MAX: usize = 4096;
+/// argv 与 envp 各自的最大项数
+const MAX_ARGS: usize = 64;
+
+/// waitpid 的 options:没有已退出的子进程时立即返回 0
+pub const WNOHANG: u64 = 0x1;
+
+pub fn sys_exit(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    process::exit(ExitStatus::Exited(args[0] as i32))
+}
+
+pub fn sys_yield(_frame: &mut SyscallFrame, _args: [u64; 6]) -> SyscallResult {
+    thread::yield_now();
+    Ok(0)
+}
+
+/// sleep(ms)
+pub fn sys_sleep(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    thread::sleep(Duration::from_millis(args[0]));
+    Ok(0)
+}
+
+pub fn sys_getpid(_frame: &mut SyscallFrame, _args: [u64; 6]) -> SyscallResult {
+    Ok(process::current_pid().as_u64())
+}
+
+pub fn sys_getppid(_frame: &mut SyscallFrame, _args: [u64; 6]) -> SyscallResult {
+    let parent = process::parent_of(process::current_pid()).unwrap_or(Pid::KERNEL);
+    Ok(parent.as_u64())
+}
+
+/// waitpid(pid, status, options):`pid` 为 -1 时等待任意子进程,`status` 为 0 时不写回状态
+pub fn sys_waitpid(_frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let (pid, status_addr, options) = (args[0] as i64, args[1], args[2]);
+    if options & !WNOHANG != 0 || pid == 0 || pid < -1 {
+        return Err(Errno::EINVAL);
+    }
+    if status_addr != 0 {
+        uaccess::check_range(status_addr, core::mem::size_of::<u32>(), true)?;
+    }
+    let target = if pid == -1 { None } else { Some(Pid::from_u64(pid as u64)) };
+    match process::waitpid(target, options & WNOHANG != 0)? {
+        Some((pid, status)) => {
+            if status_addr != 0 {
+                uaccess::write_user(status_addr, status.wait_status())?;
+            }
+            Ok(pid.as_u64())
+        }
+        None => Ok(0),
+    }
+}
+
+pub fn sys_fork(frame: &mut SyscallFrame, _args: [u64; 6]) -> SyscallResult {
+    process::fork(frame).map(|pid| pid.as_u64())
+}
+
+/// exec(path, argv, envp):相对路径接在工作目录之后,`argv`、`envp` 为以 0 结尾的字符串指针数组,可以为 0;成功时不返回
+pub fn sys_exec(frame: &mut SyscallFrame, args: [u64; 6]) -> SyscallResult {
+    let path = user_path(args[0])?;
+    let argv = read_strings(args[1])?;
+    let envp = read_strings(args[2])?;
+    let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+    let envp: Vec<&str> = envp.iter().map(String::as_str).collect();
+    process::exec(&path, &argv, &envp, frame)?;
+    Ok(0)
+}
+
+fn read_strings(addr: u64) -> Result<Vec<String>, Errno> {
+    if addr == 0 {
+        return Ok(Vec::new());
+    }
+    uaccess::read_user_ptrs(addr, MAX_ARGS)?.into_iter()
+        .map(|ptr| uaccess::read_user_str(ptr, ARG_MAX))
+        .collect()
+}
@@ -0,0 +1,111 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use x86_64::structures::paging::PageTableFlags;
+use x86_64::VirtAddr;
+
+use crate::mem::address_space;
+use crate::{process, user};
+use crate::syscall::Errno;
+
+const PAGE_SIZE: u64 = 4096;
+
+/// 检查 [addr, addr+len) 整段位于用户区且已映射为用户可访问(写入时还需可写)
+pub fn check_range(addr: u64, len: usize, write: bool) -> Result<(), Errno> {
+    if len == 0 {
+        return Ok(());
+    }
+    let end = addr.checked_add(len as u64).ok_or(Errno::EFAULT)?;
+    // 先拒绝非规范地址,下面的 VirtAddr::new 不会 panic
+    if !user::is_user_pointer(addr) || !user::is_user_pointer(end - 1) {
+        return Err(Errno::EFAULT);
+    }
+    let mut required = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+    if write {
+        required |= PageTableFlags::WRITABLE;
+    }
+    let mut page = addr & !(PAGE_SIZE - 1);
+    while page < end {
+        match address_space::translate_current(VirtAddr::new(page)) {
+            Some((_, flags)) if flags.contains(required) => {}
+            // 写时复制页先复制,内核写入不依赖 CR0.WP 触发缺页
+            Some((_, flags)) if write && flags.contains(address_space::COW) => {
+                if !process::resolve_cow(VirtAddr::new(page)) {
+                    return Err(Errno::EFAULT);
+                }
+            }
+            _ => return Err(Errno::EFAULT),
+        }
+        page += PAGE_SIZE;
+    }
+    Ok(())
+}
+
+/// 借用用户缓冲区,只在当前系统调用期间有效
+pub fn user_slice<'a>(addr: u64, len: usize) -> Result<&'a [u8], Errno> {
+    check_range(addr, len, false)?;
+    if len == 0 {
+        return Ok(&[]);
+    }
+    Ok(unsafe { core::slice::from_raw_parts(addr as *const u8, len) })
+}
+
+pub fn user_slice_mut<'a>(addr: u64, len: usize) -> Result<&'a mut [u8], Errno> {
+    check_range(addr, len, true)?;
+    if len == 0 {
+        return Ok(&mut []);
+    }
+    Ok(unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) })
+}
+
+pub fn copy_from_user(addr: u64, len: usize) -> Result<Vec<u8>, Errno> {
+    Ok(user_slice(addr, len)?.to_vec())
+}
+
+pub fn copy_to_user(addr: u64, data: &[u8]) -> Result<(), Errno> {
+    user_slice_mut(addr, data.len())?.copy_from_slice(data);
+    Ok(())
+}
+
+pub fn read_user<T: Copy>(addr: u64) -> Result<T, Errno> {
+    check_range(addr, size_of::<T>(), false)?;
+    Ok(unsafe { core::ptr::read_unaligned(addr as *const T) })
+}
+
+pub fn write_user<T: Copy>(addr: u64, value: T) -> Result<(), Errno> {
+    check_range(addr, size_of::<T>(), true)?;
+    unsafe { core::ptr::write_unaligned(addr as *mut T, value) };
+    Ok(())
+}
+
+/// 读取以 0 结尾的指针数组(如 argv),超过 `max` 项返回 E2BIG
+pub fn read_user_ptrs(addr: u64, max: usize) -> Result<Vec<u64>, Errno> {
+    let mut ptrs = Vec::new();
+    loop {
+        let ptr: u64 = read_user(addr + (ptrs.len() * size_of::<u64>()) as u64)?;
+        if ptr == 0 {
+            return Ok(ptrs);
+        }
+        if ptrs.len() >= max {
+            return Err(Errno::E2BIG);
+        }
+        ptrs.push(ptr);
+    }
+}
+
+/// 读取以 0 结尾的字符串,超过 `max` 字节返回 ENAMETOOLONG
+pub fn read_user_str(addr: u64, max: usize) -> Result<String, Errno> {
+    let mut bytes = Vec::new();
+    loop {
+        if bytes.len() >= max {
+            return Err(Errno::ENAMETOOLONG);
+        }
+        let byte: u8 = read_user(addr + bytes.len() as u64)?;
+        if byte == 0 {
+            break;
+        }
+        bytes.push(byte);
+    }
+    String::from_utf8(bytes).map_err(|_| Errno::EINVAL)
+}
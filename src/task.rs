@@ -0,0 +1,184 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+
+use crate::sync::SpinLock;
+use crate::time::{self, Instant, TimerId};
+
+pub mod executor;
+pub mod keyboard;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+pub struct Task {
+    id: TaskId,
+    future: Pin<Box<dyn Future<Output=()> + Send>>,
+}
+
+impl Task {
+    pub fn new(future: impl Future<Output=()> + Send + 'static) -> Task {
+        Task {
+            id: TaskId::new(),
+            future: Box::pin(future),
+        }
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    fn poll(&mut self, context: &mut Context) -> Poll<()> {
+        self.future.as_mut().poll(context)
+    }
+}
+
+/// 尚未被执行器接收的任务
+static SPAWNED: SpinLock<Vec<Task>> = SpinLock::new(Vec::new());
+
+/// 在任意任务中创建新任务,由正在运行的执行器接收
+pub fn spawn(future: impl Future<Output=()> + Send + 'static) -> TaskId {
+    let task = Task::new(future);
+    let id = task.id;
+    SPAWNED.lock().push(task);
+    id
+}
+
+fn take_spawned() -> Vec<Task> {
+    core::mem::take(&mut *SPAWNED.lock())
+}
+
+fn has_spawned() -> bool {
+    !SPAWNED.lock().is_empty()
+}
+
+/// `sleep` 返回的 future,到期由定时器回调唤醒
+pub struct Sleep {
+    deadline: Instant,
+    timer: Option<(TimerId, Waker)>,
+}
+
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(time::now() + duration)
+}
+
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep { deadline, timer: None }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if time::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        match &self.timer {
+            Some((_, waker)) if waker.will_wake(cx.waker()) => {}
+            _ => {
+                if let Some((id, _)) = self.timer.take() {
+                    time::cancel_timer(id);
+                }
+                let waker = cx.waker().clone();
+                let id = time::add_timer(self.deadline, move || waker.wake_by_ref());
+                self.timer = Some((id, cx.waker().clone()));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some((id, _)) = self.timer.take() {
+            time::cancel_timer(id);
+        }
+    }
+}
+
+/// 让出一次执行权
+pub async fn yield_now() {
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    YieldNow(false).await
+}
+
+/// 异步产生一系列值,与 `futures::Stream` 语义一致
+pub trait Stream {
+    type Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>>;
+}
+
+pub trait StreamExt: Stream {
+    fn next(&mut self) -> Next<'_, Self> where Self: Unpin {
+        Next { stream: self }
+    }
+}
+
+impl<S: Stream + ?Sized> StreamExt for S {}
+
+pub struct Next<'a, S: ?Sized> {
+    stream: &'a mut S,
+}
+
+impl<S: Stream + Unpin + ?Sized> Future for Next<'_, S> {
+    type Output = Option<S::Item>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<S::Item>> {
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
+
+/// 中断上下文与任务之间传递 waker
+pub struct AtomicWaker {
+    waker: SpinLock<Option<Waker>>,
+}
+
+impl AtomicWaker {
+    pub const fn new() -> Self {
+        AtomicWaker { waker: SpinLock::new(None) }
+    }
+
+    pub fn register(&self, waker: &Waker) {
+        let mut slot = self.waker.lock();
+        match &*slot {
+            Some(old) if old.will_wake(waker) => {}
+            _ => *slot = Some(waker.clone()),
+        }
+    }
+
+    pub fn take(&self) -> Option<Waker> {
+        self.waker.lock().take()
+    }
+
+    pub fn wake(&self) {
+        if let Some(waker) = self.take() {
+            waker.wake();
+        }
+    }
+}
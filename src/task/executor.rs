@@ -0,0 +1,153 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use x86_64::instructions::interrupts;
+
+use crate::sync::SpinLock;
+use crate::task::{has_spawned, take_spawned, Task, TaskId};
+
+const TASK_QUEUE_CAPACITY: usize = 128;
+
+/// 预分配容量的就绪队列,唤醒可能发生在中断上下文,入队时不再分配内存
+///
+/// 每个任务至多在队列中出现一次,容量在 `spawn` 时按任务数扩充,所以入队不会超出容量
+struct TaskQueue {
+    inner: SpinLock<VecDeque<TaskId>>,
+}
+
+impl TaskQueue {
+    fn new() -> Self {
+        TaskQueue {
+            inner: SpinLock::new(VecDeque::with_capacity(TASK_QUEUE_CAPACITY)),
+        }
+    }
+
+    fn push(&self, id: TaskId) {
+        self.inner.lock().push_back(id);
+    }
+
+    /// 保证能容纳 `tasks` 个任务,只在非中断上下文调用
+    fn reserve(&self, tasks: usize) {
+        let mut queue = self.inner.lock();
+        if queue.capacity() < tasks {
+            let additional = tasks - queue.len();
+            queue.reserve(additional);
+        }
+    }
+
+    fn pop(&self) -> Option<TaskId> {
+        self.inner.lock().pop_front()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.lock().is_empty()
+    }
+}
+
+struct TaskWaker {
+    task_id: TaskId,
+    task_queue: Arc<TaskQueue>,
+    /// 已在就绪队列中,重复唤醒不再入队;轮询前清除
+    queued: AtomicBool,
+}
+
+impl TaskWaker {
+    /// 新任务直接入队,所以初始为已入队
+    fn new(task_id: TaskId, task_queue: Arc<TaskQueue>) -> Arc<Self> {
+        Arc::new(TaskWaker { task_id, task_queue, queued: AtomicBool::new(true) })
+    }
+
+    fn wake_task(&self) {
+        if !self.queued.swap(true, Ordering::AcqRel) {
+            self.task_queue.push(self.task_id);
+        }
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_task();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wake_task();
+    }
+}
+
+/// 协作式执行器:没有就绪任务时 `hlt` 等待中断
+pub struct Executor {
+    tasks: BTreeMap<TaskId, Task>,
+    task_queue: Arc<TaskQueue>,
+    waker_cache: BTreeMap<TaskId, Arc<TaskWaker>>,
+}
+
+impl Executor {
+    pub fn new() -> Self {
+        Executor {
+            tasks: BTreeMap::new(),
+            task_queue: Arc::new(TaskQueue::new()),
+            waker_cache: BTreeMap::new(),
+        }
+    }
+
+    pub fn spawn(&mut self, task: Task) {
+        let task_id = task.id;
+        if self.tasks.insert(task_id, task).is_some() {
+            panic!("task with same ID already in tasks");
+        }
+        self.task_queue.reserve(self.tasks.len());
+        self.waker_cache.insert(task_id, TaskWaker::new(task_id, self.task_queue.clone()));
+        self.task_queue.push(task_id);
+    }
+
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.accept_spawned();
+            self.run_ready_tasks();
+            self.sleep_if_idle();
+        }
+    }
+
+    fn accept_spawned(&mut self) {
+        for task in take_spawned() {
+            self.spawn(task);
+        }
+    }
+
+    fn run_ready_tasks(&mut self) {
+        while let Some(task_id) = self.task_queue.pop() {
+            let task = match self.tasks.get_mut(&task_id) {
+                Some(task) => task,
+                // 已结束的任务
+                None => continue,
+            };
+            let task_waker = &self.waker_cache[&task_id];
+            task_waker.queued.store(false, Ordering::Release);
+            let waker = Waker::from(task_waker.clone());
+            let mut context = Context::from_waker(&waker);
+            if let Poll::Ready(()) = task.poll(&mut context) {
+                self.tasks.remove(&task_id);
+                self.waker_cache.remove(&task_id);
+            }
+        }
+    }
+
+    /// 关中断后再检查队列,`sti; hlt` 之间不会漏掉唤醒
+    fn sleep_if_idle(&self) {
+        interrupts::disable();
+        if self.task_queue.is_empty() && !has_spawned() {
+            interrupts::enable_and_hlt();
+        } else {
+            interrupts::enable();
+        }
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,163 @@
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use core::task::{Context, Poll};
+
+use pc_keyboard::{DecodedKey, HandleControl, Keyboard};
+use pc_keyboard::layouts::Us104Key;
+use pc_keyboard::ScancodeSet1;
+
+use crate::print;
+use crate::task::{AtomicWaker, Stream, StreamExt};
+
+const SCANCODE_QUEUE_SIZE: usize = 128;
+
+/// 单生产者(键盘中断)单消费者(`ScancodeStream`)的无锁环形队列
+struct ScancodeQueue {
+    buffer: [AtomicU8; SCANCODE_QUEUE_SIZE],
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+impl ScancodeQueue {
+    const fn new() -> Self {
+        const EMPTY: AtomicU8 = AtomicU8::new(0);
+        ScancodeQueue {
+            buffer: [EMPTY; SCANCODE_QUEUE_SIZE],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, scancode: u8) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.head.load(Ordering::Acquire)) == SCANCODE_QUEUE_SIZE {
+            return false;
+        }
+        self.buffer[tail % SCANCODE_QUEUE_SIZE].store(scancode, Ordering::Relaxed);
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    fn pop(&self) -> Option<u8> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let scancode = self.buffer[head % SCANCODE_QUEUE_SIZE].load(Ordering::Relaxed);
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(scancode)
+    }
+}
+
+static SCANCODE_QUEUE: ScancodeQueue = ScancodeQueue::new();
+static WAKER: AtomicWaker = AtomicWaker::new();
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+static STREAM_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// 由键盘中断调用,不能阻塞或分配内存
+pub(crate) fn add_scancode(scancode: u8) {
+    if SCANCODE_QUEUE.push(scancode) {
+        WAKER.wake();
+    } else {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 队列满时被丢弃的扫描码数量
+pub fn dropped_scancodes() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+pub struct ScancodeStream {
+    _private: (),
+}
+
+impl ScancodeStream {
+    /// 队列只有一个消费者,重复创建会 panic;因此不实现 `Default`
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        if STREAM_TAKEN.swap(true, Ordering::SeqCst) {
+            panic!("ScancodeStream::new should only be called once at a time");
+        }
+        ScancodeStream { _private: () }
+    }
+}
+
+impl Drop for ScancodeStream {
+    fn drop(&mut self) {
+        STREAM_TAKEN.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Stream for ScancodeStream {
+    type Item = u8;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<u8>> {
+        if let Some(scancode) = SCANCODE_QUEUE.pop() {
+            return Poll::Ready(Some(scancode));
+        }
+        WAKER.register(cx.waker());
+        // 注册后再检查一次,避免错过注册前到达的扫描码
+        match SCANCODE_QUEUE.pop() {
+            Some(scancode) => {
+                WAKER.take();
+                Poll::Ready(Some(scancode))
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// 在任务上下文中将扫描码解码为按键
+pub struct KeyStream {
+    scancodes: ScancodeStream,
+    keyboard: Keyboard<Us104Key, ScancodeSet1>,
+}
+
+impl KeyStream {
+    /// 内部创建 `ScancodeStream`,同样只能有一个
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        KeyStream {
+            scancodes: ScancodeStream::new(),
+            keyboard: Keyboard::new(ScancodeSet1::new(), Us104Key, HandleControl::Ignore),
+        }
+    }
+
+    fn decode(&mut self, scancode: u8) -> Option<DecodedKey> {
+        match self.keyboard.add_byte(scancode) {
+            Ok(Some(key_event)) => self.keyboard.process_keyevent(key_event),
+            _ => None,
+        }
+    }
+}
+
+impl Stream for KeyStream {
+    type Item = DecodedKey;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<DecodedKey>> {
+        loop {
+            match Pin::new(&mut self.scancodes).poll_next(cx) {
+                Poll::Ready(Some(scancode)) => {
+                    if let Some(key) = self.decode(scancode) {
+                        return Poll::Ready(Some(key));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+pub async fn print_keypresses() {
+    let mut keys = KeyStream::new();
+    while let Some(key) = keys.next().await {
+        match key {
+            DecodedKey::Unicode(character) => print!("{}", character),
+            DecodedKey::RawKey(_key) => {
+                //print!("{:?}", key)
+            }
+        }
+    }
+}
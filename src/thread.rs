@@ -0,0 +1,280 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+use x86_64::instructions::interrupts;
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::structures::paging::{PhysFrame, Size4KiB};
+
+use crate::println;
+use crate::sync::SpinLock;
+use crate::thread::scheduler::{PolicyKind, SchedEntity, SchedStats, Scheduler};
+use crate::thread::stack::Stack;
+use crate::{mem, time};
+
+pub mod context;
+pub mod stack;
+pub mod scheduler;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadId(u64);
+
+impl ThreadId {
+    fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        ThreadId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    Ready,
+    Running,
+    Blocked,
+    Exited,
+}
+
+impl ThreadState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThreadState::Ready => "ready",
+            ThreadState::Running => "running",
+            ThreadState::Blocked => "blocked",
+            ThreadState::Exited => "exited",
+        }
+    }
+}
+
+pub struct Thread {
+    id: ThreadId,
+    name: String,
+    state: ThreadState,
+    /// 切换出去时保存的栈指针
+    rsp: u64,
+    /// 启动线程沿用 bootloader 的栈
+    stack: Option<Stack>,
+    entry: Option<Box<dyn FnOnce() + Send + 'static>>,
+    joiners: Vec<ThreadId>,
+    wakeup_pending: bool,
+    sched: SchedEntity,
+    /// 运行时使用的 P4 页表,内核线程使用内核页表
+    page_table: PhysFrame,
+}
+
+impl Thread {
+    fn new(name: &str, entry: Box<dyn FnOnce() + Send + 'static>) -> Result<Box<Thread>, MapToError<Size4KiB>> {
+        let stack = Stack::allocate()?;
+        let rsp = unsafe { context::init_stack(stack.top()) };
+        Ok(Box::new(Thread {
+            id: ThreadId::new(),
+            name: name.to_string(),
+            state: ThreadState::Ready,
+            rsp,
+            stack: Some(stack),
+            entry: Some(entry),
+            joiners: Vec::new(),
+            wakeup_pending: false,
+            sched: SchedEntity::default(),
+            page_table: mem::kernel_page_table(),
+        }))
+    }
+
+    fn bootstrap(name: &str) -> Box<Thread> {
+        Box::new(Thread {
+            id: ThreadId::new(),
+            name: name.to_string(),
+            state: ThreadState::Running,
+            rsp: 0,
+            stack: None,
+            entry: None,
+            joiners: Vec::new(),
+            wakeup_pending: false,
+            sched: SchedEntity::default(),
+            page_table: mem::kernel_page_table(),
+        })
+    }
+
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> ThreadState {
+        self.state
+    }
+
+    pub fn stack(&self) -> Option<&Stack> {
+        self.stack.as_ref()
+    }
+
+    pub fn nice(&self) -> i8 {
+        self.sched.nice
+    }
+
+    pub fn stats(&self) -> SchedStats {
+        self.sched.stats
+    }
+}
+
+/// 将当前执行流登记为 main 线程并创建 idle 线程,之后开始抢占调度
+/// 需在堆初始化之后调用
+pub fn init() {
+    println!("Init threads ...");
+    let main = Thread::bootstrap("main");
+    let idle = Thread::new("idle", Box::new(idle_loop)).expect("create idle thread failed");
+    scheduler::install(Scheduler::new(main, idle, PolicyKind::Fair));
+}
+
+fn idle_loop() {
+    crate::hlt_loop()
+}
+
+/// 新线程经 trampoline 进入此处
+#[no_mangle]
+extern "C" fn mongo_os_thread_start() -> ! {
+    scheduler::finish_switch();
+    let entry = scheduler::lock().as_mut().and_then(|scheduler| scheduler.current_thread().entry.take());
+    interrupts::enable();
+    if let Some(entry) = entry {
+        entry();
+    }
+    exit()
+}
+
+pub struct JoinHandle<T> {
+    id: ThreadId,
+    result: Arc<SpinLock<Option<T>>>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> JoinHandle<T> {
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    /// 阻塞直到线程结束,返回其结果
+    pub fn join(self) -> T {
+        loop {
+            let finished = interrupts::without_interrupts(|| {
+                if let Some(result) = self.result.lock().take() {
+                    return Some(result);
+                }
+                let current = current();
+                scheduler::with_scheduler(|scheduler| {
+                    if let Some(thread) = scheduler.threads.get_mut(&self.id) {
+                        thread.joiners.push(current);
+                    }
+                });
+                block_current();
+                None
+            });
+            if let Some(result) = finished {
+                return result;
+            }
+        }
+    }
+}
+
+pub fn spawn<F, T>(name: &str, f: F) -> Result<JoinHandle<T>, MapToError<Size4KiB>>
+    where F: FnOnce() -> T + Send + 'static, T: Send + 'static {
+    let result = Arc::new(SpinLock::new(None));
+    let thread_result = result.clone();
+    let thread = Thread::new(name, Box::new(move || {
+        let value = f();
+        interrupts::without_interrupts(|| *thread_result.lock() = Some(value));
+    }))?;
+    let id = thread.id;
+    scheduler::with_scheduler(|scheduler| scheduler.add(thread)).expect("threads not initialized");
+    Ok(JoinHandle { id, result, _marker: PhantomData })
+}
+
+pub fn current() -> ThreadId {
+    scheduler::with_scheduler(|scheduler| scheduler.current).expect("threads not initialized")
+}
+
+pub fn yield_now() {
+    scheduler::schedule(ThreadState::Ready);
+}
+
+/// 阻塞当前线程直到被 [`unblock`] 唤醒;之前已有未消费的唤醒则立即返回
+pub fn block_current() {
+    scheduler::schedule(ThreadState::Blocked);
+}
+
+pub fn unblock(id: ThreadId) {
+    scheduler::with_scheduler(|scheduler| scheduler.unblock(id));
+}
+
+/// 结束当前线程并唤醒所有 join 它的线程
+pub fn exit() -> ! {
+    interrupts::disable();
+    scheduler::with_scheduler(|scheduler| {
+        let joiners = core::mem::take(&mut scheduler.current_thread().joiners);
+        for joiner in joiners {
+            scheduler.unblock(joiner);
+        }
+    });
+    scheduler::schedule(ThreadState::Exited);
+    unreachable!("exited thread was rescheduled");
+}
+
+/// 借助定时器阻塞当前线程
+pub fn sleep(duration: Duration) {
+    let id = current();
+    interrupts::without_interrupts(|| {
+        time::add_timer(time::now() + duration, move || unblock(id));
+        block_current();
+    });
+}
+
+/// 切换当前线程的地址空间,之后每次调度到该线程都会加载此页表
+pub fn set_page_table(page_table: PhysFrame) {
+    interrupts::without_interrupts(|| {
+        scheduler::with_scheduler(|scheduler| scheduler.current_thread().page_table = page_table);
+        mem::address_space::switch_to(page_table);
+    });
+}
+
+/// nice 取值 -20..=19,越小获得的 CPU 时间越多
+pub fn set_nice(id: ThreadId, nice: i8) -> bool {
+    scheduler::with_scheduler(|scheduler| scheduler.set_nice(id, nice)).unwrap_or(false)
+}
+
+pub fn set_policy(kind: PolicyKind) {
+    scheduler::with_scheduler(|scheduler| scheduler.set_policy(kind));
+}
+
+pub fn stats(id: ThreadId) -> Option<SchedStats> {
+    scheduler::with_scheduler(|scheduler| scheduler.threads.get(&id).map(|thread| thread.sched.stats)).flatten()
+}
+
+pub fn print_threads() {
+    #[allow(clippy::type_complexity)]
+    let rows: Option<(&'static str, Vec<(ThreadId, String, ThreadState, i8, SchedStats)>)> = scheduler::with_scheduler(|scheduler| {
+        let rows = scheduler.threads.values()
+            .map(|thread| (thread.id, thread.name.clone(), thread.state, thread.sched.nice, thread.sched.stats))
+            .collect();
+        (scheduler.policy_name(), rows)
+    });
+    let (policy, rows) = match rows {
+        Some(rows) => rows,
+        None => return,
+    };
+    println!("policy: {}", policy);
+    println!("{:>4} {:<12} {:<8} {:>4} {:>12} {:>12} {:>8}", "TID", "NAME", "STATE", "NICE", "RUN(us)", "WAIT(us)", "SWITCH");
+    for (id, name, state, nice, stats) in rows {
+        println!("{:>4} {:<12} {:<8} {:>4} {:>12} {:>12} {:>8}",
+                 id.0, name, state.as_str(), nice, stats.runtime / 1000, stats.wait_time / 1000, stats.context_switches);
+    }
+}
@@ -0,0 +1,58 @@
+use core::arch::global_asm;
+
+use x86_64::VirtAddr;
+
+// switch_context(old_rsp: *mut u64, new_rsp: u64)
+// 保存 callee-saved 寄存器与 RFLAGS 到当前栈,切换栈指针后从新栈恢复
+global_asm!(r#"
+.global mongo_os_switch_context
+mongo_os_switch_context:
+    push rbp
+    push rbx
+    push r12
+    push r13
+    push r14
+    push r15
+    pushfq
+    mov [rdi], rsp
+    mov rsp, rsi
+    popfq
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbx
+    pop rbp
+    ret
+
+.global mongo_os_thread_trampoline
+mongo_os_thread_trampoline:
+    call mongo_os_thread_start
+    ud2
+"#);
+
+extern "C" {
+    fn mongo_os_switch_context(old_rsp: *mut u64, new_rsp: u64);
+    fn mongo_os_thread_trampoline();
+}
+
+/// RFLAGS 第 1 位保留为 1;新线程以关中断状态开始,由 `thread_start` 打开
+const INITIAL_RFLAGS: u64 = 0x2;
+const SAVED_REGISTERS: usize = 7;
+
+/// 在新栈上伪造一帧 `switch_context` 保存的现场,返回地址指向 trampoline
+pub unsafe fn init_stack(stack_top: VirtAddr) -> u64 {
+    let top = stack_top.align_down(16u64).as_u64() as *mut u64;
+    let frame = top.sub(1 + SAVED_REGISTERS);
+    *top.sub(1) = mongo_os_thread_trampoline as unsafe extern "C" fn() as usize as u64;
+    for i in 0..SAVED_REGISTERS {
+        *frame.add(i) = 0;
+    }
+    *frame = INITIAL_RFLAGS;
+    frame as u64
+}
+
+/// 需在关中断状态下调用
+pub unsafe fn switch_context(old_rsp: *mut u64, new_rsp: u64) {
+    mongo_os_switch_context(old_rsp, new_rsp);
+}
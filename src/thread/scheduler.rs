@@ -0,0 +1,304 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use x86_64::instructions::interrupts;
+use x86_64::registers::control::Cr3;
+
+use crate::{gdt, mem, softirq, time};
+use crate::sync::{SpinLock, SpinLockGuard};
+use crate::thread::{context, Thread, ThreadId, ThreadState};
+
+pub mod round_robin;
+pub mod priority;
+pub mod fair;
+
+/// 时间片长度,以 timer tick 计
+pub const TIME_SLICE_TICKS: u32 = 10;
+pub const NICE_MIN: i8 = -20;
+pub const NICE_MAX: i8 = 19;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchedStats {
+    /// 累计运行时间(纳秒)
+    pub runtime: u64,
+    /// 就绪后等待被调度的累计时间(纳秒)
+    pub wait_time: u64,
+    pub context_switches: u64,
+}
+
+/// 线程上与调度相关的数据,由策略读写
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SchedEntity {
+    pub nice: i8,
+    pub vruntime: u64,
+    pub stats: SchedStats,
+    ready_since: u64,
+}
+
+/// 可插拔的调度策略,只管理就绪队列,idle 线程不会入队
+pub trait Policy: Send {
+    fn name(&self) -> &'static str;
+
+    fn enqueue(&mut self, id: ThreadId, entity: &mut SchedEntity);
+
+    fn remove(&mut self, id: ThreadId, entity: &SchedEntity);
+
+    /// 取出下一个要运行的线程
+    fn pick_next(&mut self) -> Option<ThreadId>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 当前线程运行了 `delta` 纳秒
+    fn account(&mut self, _entity: &mut SchedEntity, _delta: u64) {}
+
+    /// 每个 tick 对当前线程调用,返回是否需要抢占
+    fn should_preempt(&mut self, id: ThreadId, entity: &SchedEntity) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyKind {
+    RoundRobin,
+    Priority,
+    Fair,
+}
+
+impl PolicyKind {
+    fn create(self) -> Box<dyn Policy> {
+        match self {
+            PolicyKind::RoundRobin => Box::new(round_robin::RoundRobin::new()),
+            PolicyKind::Priority => Box::new(priority::Priority::new()),
+            PolicyKind::Fair => Box::new(fair::Fair::new()),
+        }
+    }
+}
+
+pub(crate) struct Scheduler {
+    pub(crate) threads: BTreeMap<ThreadId, Box<Thread>>,
+    policy: Box<dyn Policy>,
+    pub(crate) current: ThreadId,
+    idle: ThreadId,
+    /// 当前线程开始计时的时间点
+    exec_start: u64,
+}
+
+static SCHEDULER: SpinLock<Option<Scheduler>> = SpinLock::new(None);
+static PREEMPTION_ENABLED: AtomicBool = AtomicBool::new(false);
+/// 当前线程 id 的无锁副本,供不能获取调度器锁的代码使用
+static CURRENT: AtomicU64 = AtomicU64::new(u64::MAX);
+
+impl Scheduler {
+    pub(crate) fn new(current: Box<Thread>, idle: Box<Thread>, policy: PolicyKind) -> Self {
+        let mut scheduler = Scheduler {
+            threads: BTreeMap::new(),
+            policy: policy.create(),
+            current: current.id,
+            idle: idle.id,
+            exec_start: time::now().as_nanos(),
+        };
+        scheduler.threads.insert(current.id, current);
+        scheduler.threads.insert(idle.id, idle);
+        scheduler
+    }
+
+    fn enqueue(&mut self, id: ThreadId) {
+        if id == self.idle {
+            return;
+        }
+        if let Some(thread) = self.threads.get_mut(&id) {
+            thread.sched.ready_since = time::now().as_nanos();
+            self.policy.enqueue(id, &mut thread.sched);
+        }
+    }
+
+    pub(crate) fn add(&mut self, thread: Box<Thread>) {
+        let id = thread.id;
+        self.threads.insert(id, thread);
+        self.enqueue(id);
+    }
+
+    pub(crate) fn current_thread(&mut self) -> &mut Thread {
+        let current = self.current;
+        self.threads.get_mut(&current).expect("current thread missing")
+    }
+
+    /// 阻塞中的线程转为就绪;仍在运行的线程记下唤醒,下次阻塞时直接返回
+    pub(crate) fn unblock(&mut self, id: ThreadId) {
+        let state = match self.threads.get_mut(&id) {
+            Some(thread) => thread.state,
+            None => return,
+        };
+        match state {
+            ThreadState::Blocked => {
+                self.threads.get_mut(&id).unwrap().state = ThreadState::Ready;
+                self.enqueue(id);
+            }
+            ThreadState::Running | ThreadState::Ready => self.threads.get_mut(&id).unwrap().wakeup_pending = true,
+            ThreadState::Exited => {}
+        }
+    }
+
+    /// 将当前线程自上次计时以来的运行时间计入统计与策略
+    fn account_current(&mut self) {
+        let now = time::now().as_nanos();
+        let delta = now.saturating_sub(self.exec_start);
+        self.exec_start = now;
+        let current = self.current;
+        let thread = self.threads.get_mut(&current).expect("current thread missing");
+        thread.sched.stats.runtime += delta;
+        self.policy.account(&mut thread.sched, delta);
+    }
+
+    /// 选出下一个线程,需要切换时返回 (旧线程 rsp 保存位置, 新线程 rsp)
+    fn pick_next(&mut self, current_state: ThreadState) -> Option<(*mut u64, u64)> {
+        let current = self.current;
+        {
+            let thread = self.current_thread();
+            if current_state == ThreadState::Blocked && thread.wakeup_pending {
+                thread.wakeup_pending = false;
+                return None;
+            }
+            thread.state = current_state;
+        }
+        self.account_current();
+        if current_state == ThreadState::Ready {
+            self.enqueue(current);
+        }
+        let next = self.policy.pick_next().unwrap_or(self.idle);
+        if next == current {
+            self.current_thread().state = ThreadState::Running;
+            return None;
+        }
+        self.current = next;
+        CURRENT.store(next.0, Ordering::Relaxed);
+        let now = time::now().as_nanos();
+        let next_thread = self.threads.get_mut(&next).expect("next thread missing");
+        next_thread.state = ThreadState::Running;
+        next_thread.sched.stats.context_switches += 1;
+        next_thread.sched.stats.wait_time += now.saturating_sub(next_thread.sched.ready_since);
+        if let Some(stack) = next_thread.stack() {
+            gdt::set_kernel_stack(stack.top());
+        }
+        if Cr3::read().0 != next_thread.page_table {
+            mem::address_space::switch_to(next_thread.page_table);
+        }
+        let new_rsp = next_thread.rsp;
+        let old_rsp = &mut self.threads.get_mut(&current).expect("current thread missing").rsp as *mut u64;
+        Some((old_rsp, new_rsp))
+    }
+
+    /// 回收已退出的线程,当前线程的栈仍在使用,留待下次
+    fn reap(&mut self) -> Vec<Box<Thread>> {
+        let current = self.current;
+        let exited: Vec<ThreadId> = self.threads.iter()
+            .filter(|(&id, thread)| id != current && thread.state == ThreadState::Exited)
+            .map(|(&id, _)| id)
+            .collect();
+        exited.iter().filter_map(|id| self.threads.remove(id)).collect()
+    }
+
+    fn tick(&mut self) -> bool {
+        self.account_current();
+        if self.current == self.idle {
+            return !self.policy.is_empty();
+        }
+        let current = self.current;
+        let thread = self.threads.get(&current).expect("current thread missing");
+        self.policy.should_preempt(current, &thread.sched)
+    }
+
+    pub(crate) fn set_nice(&mut self, id: ThreadId, nice: i8) -> bool {
+        let nice = nice.clamp(NICE_MIN, NICE_MAX);
+        let thread = match self.threads.get_mut(&id) {
+            Some(thread) => thread,
+            None => return false,
+        };
+        if thread.state == ThreadState::Ready && id != self.idle {
+            self.policy.remove(id, &thread.sched);
+            thread.sched.nice = nice;
+            self.policy.enqueue(id, &mut thread.sched);
+        } else {
+            thread.sched.nice = nice;
+        }
+        true
+    }
+
+    /// 将就绪线程迁移到新策略的队列中
+    pub(crate) fn set_policy(&mut self, kind: PolicyKind) {
+        let mut policy = kind.create();
+        while let Some(id) = self.policy.pick_next() {
+            if let Some(thread) = self.threads.get_mut(&id) {
+                policy.enqueue(id, &mut thread.sched);
+            }
+        }
+        self.policy = policy;
+    }
+
+    pub(crate) fn policy_name(&self) -> &'static str {
+        self.policy.name()
+    }
+}
+
+pub(crate) fn install(scheduler: Scheduler) {
+    CURRENT.store(scheduler.current.0, Ordering::Relaxed);
+    interrupts::without_interrupts(|| *SCHEDULER.lock() = Some(scheduler));
+    PREEMPTION_ENABLED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_initialized() -> bool {
+    PREEMPTION_ENABLED.load(Ordering::SeqCst)
+}
+
+/// 不获取调度器锁,线程未初始化时返回 `None`
+pub fn current_id() -> Option<ThreadId> {
+    match CURRENT.load(Ordering::Relaxed) {
+        u64::MAX => None,
+        id => Some(ThreadId(id)),
+    }
+}
+
+/// 调用者需已关中断
+pub(crate) fn lock() -> SpinLockGuard<'static, Option<Scheduler>> {
+    SCHEDULER.lock()
+}
+
+pub(crate) fn with_scheduler<F, R>(f: F) -> Option<R> where F: FnOnce(&mut Scheduler) -> R {
+    interrupts::without_interrupts(|| SCHEDULER.lock().as_mut().map(f))
+}
+
+/// 将当前线程置为 `current_state` 并切换到下一个就绪线程
+pub(crate) fn schedule(current_state: ThreadState) {
+    let was_enabled = interrupts::are_enabled();
+    interrupts::disable();
+    let switch = SCHEDULER.lock().as_mut().and_then(|scheduler| scheduler.pick_next(current_state));
+    if let Some((old_rsp, new_rsp)) = switch {
+        // 锁已释放,但中断仍关闭,切换完成前不会有其它代码修改调度器
+        unsafe { context::switch_context(old_rsp, new_rsp); }
+        finish_switch();
+    }
+    if was_enabled {
+        interrupts::enable();
+    }
+}
+
+/// 切换到新线程后执行,释放已退出线程的资源
+pub(crate) fn finish_switch() {
+    let reaped = SCHEDULER.lock().as_mut().map(|scheduler| scheduler.reap());
+    drop(reaped);
+}
+
+/// 由 timer 中断在发送 EOI 之后调用,由策略决定是否抢占当前线程
+pub fn on_tick() {
+    if !is_initialized() || softirq::is_running() {
+        return;
+    }
+    let preempt = SCHEDULER.lock().as_mut().map(|scheduler| scheduler.tick()).unwrap_or(false);
+    if preempt {
+        schedule(ThreadState::Ready);
+    }
+}
@@ -0,0 +1,88 @@
+use alloc::collections::BTreeSet;
+
+use crate::thread::scheduler::{NICE_MIN, Policy, SchedEntity};
+use crate::thread::ThreadId;
+
+/// nice 为 0 时的权重
+const NICE_0_WEIGHT: u64 = 1024;
+
+/// 与 Linux 相同的 nice -> weight 表,相邻 nice 之间约差 10% CPU 时间
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291,
+    29154, 23254, 18705, 14949, 11916,
+    9548, 7620, 6100, 4904, 3906,
+    3121, 2501, 1991, 1586, 1277,
+    1024, 820, 655, 526, 423,
+    335, 272, 215, 172, 137,
+    110, 87, 70, 56, 45,
+    36, 29, 23, 18, 15,
+];
+
+/// 调度周期内每个线程至少运行的时间
+const MIN_GRANULARITY: u64 = 1_000_000;
+/// 唤醒的线程最多补偿半个调度延迟,避免长期睡眠后独占 CPU
+const SCHED_LATENCY: u64 = 6_000_000;
+
+fn weight(nice: i8) -> u64 {
+    NICE_TO_WEIGHT[(nice - NICE_MIN) as usize]
+}
+
+/// CFS 风格:按虚拟运行时间排序,总是运行 vruntime 最小的线程
+pub struct Fair {
+    timeline: BTreeSet<(u64, ThreadId)>,
+    min_vruntime: u64,
+}
+
+impl Fair {
+    pub fn new() -> Self {
+        Fair {
+            timeline: BTreeSet::new(),
+            min_vruntime: 0,
+        }
+    }
+}
+
+impl Default for Fair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Policy for Fair {
+    fn name(&self) -> &'static str {
+        "fair"
+    }
+
+    fn enqueue(&mut self, id: ThreadId, entity: &mut SchedEntity) {
+        entity.vruntime = entity.vruntime.max(self.min_vruntime.saturating_sub(SCHED_LATENCY / 2));
+        self.timeline.insert((entity.vruntime, id));
+    }
+
+    fn remove(&mut self, id: ThreadId, entity: &SchedEntity) {
+        self.timeline.remove(&(entity.vruntime, id));
+    }
+
+    fn pick_next(&mut self) -> Option<ThreadId> {
+        let first = *self.timeline.iter().next()?;
+        self.timeline.remove(&first);
+        let (vruntime, id) = first;
+        self.min_vruntime = self.min_vruntime.max(vruntime);
+        Some(id)
+    }
+
+    fn len(&self) -> usize {
+        self.timeline.len()
+    }
+
+    /// 实际运行时间按权重折算为虚拟运行时间
+    fn account(&mut self, entity: &mut SchedEntity, delta: u64) {
+        entity.vruntime += delta * NICE_0_WEIGHT / weight(entity.nice);
+    }
+
+    fn should_preempt(&mut self, _id: ThreadId, entity: &SchedEntity) -> bool {
+        match self.timeline.iter().next() {
+            Some(&(leftmost, _)) => entity.vruntime > leftmost + MIN_GRANULARITY,
+            None => false,
+        }
+    }
+}
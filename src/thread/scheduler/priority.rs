@@ -0,0 +1,72 @@
+use alloc::collections::{BTreeMap, VecDeque};
+
+use crate::thread::scheduler::{Policy, SchedEntity, TIME_SLICE_TICKS};
+use crate::thread::ThreadId;
+
+/// 严格优先级:nice 越小优先级越高,同优先级内轮转
+pub struct Priority {
+    queues: BTreeMap<i8, VecDeque<ThreadId>>,
+    len: usize,
+    ticks_left: u32,
+}
+
+impl Priority {
+    pub fn new() -> Self {
+        Priority {
+            queues: BTreeMap::new(),
+            len: 0,
+            ticks_left: TIME_SLICE_TICKS,
+        }
+    }
+
+    fn highest_ready(&self) -> Option<i8> {
+        self.queues.iter().find(|(_, queue)| !queue.is_empty()).map(|(&nice, _)| nice)
+    }
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Policy for Priority {
+    fn name(&self) -> &'static str {
+        "priority"
+    }
+
+    fn enqueue(&mut self, id: ThreadId, entity: &mut SchedEntity) {
+        self.queues.entry(entity.nice).or_default().push_back(id);
+        self.len += 1;
+    }
+
+    fn remove(&mut self, id: ThreadId, entity: &SchedEntity) {
+        if let Some(queue) = self.queues.get_mut(&entity.nice) {
+            let before = queue.len();
+            queue.retain(|&ready| ready != id);
+            self.len -= before - queue.len();
+        }
+    }
+
+    fn pick_next(&mut self) -> Option<ThreadId> {
+        self.ticks_left = TIME_SLICE_TICKS;
+        let nice = self.highest_ready()?;
+        let id = self.queues.get_mut(&nice)?.pop_front()?;
+        self.len -= 1;
+        Some(id)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// 有更高优先级线程就绪时立即抢占,同优先级按时间片轮转
+    fn should_preempt(&mut self, _id: ThreadId, entity: &SchedEntity) -> bool {
+        self.ticks_left = self.ticks_left.saturating_sub(1);
+        match self.highest_ready() {
+            Some(nice) if nice < entity.nice => true,
+            Some(nice) if nice == entity.nice => self.ticks_left == 0,
+            _ => false,
+        }
+    }
+}
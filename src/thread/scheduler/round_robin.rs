@@ -0,0 +1,53 @@
+use alloc::collections::VecDeque;
+
+use crate::thread::scheduler::{Policy, SchedEntity, TIME_SLICE_TICKS};
+use crate::thread::ThreadId;
+
+/// 先进先出,时间片用完即让出
+pub struct RoundRobin {
+    ready: VecDeque<ThreadId>,
+    ticks_left: u32,
+}
+
+impl RoundRobin {
+    pub fn new() -> Self {
+        RoundRobin {
+            ready: VecDeque::new(),
+            ticks_left: TIME_SLICE_TICKS,
+        }
+    }
+}
+
+impl Default for RoundRobin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Policy for RoundRobin {
+    fn name(&self) -> &'static str {
+        "round-robin"
+    }
+
+    fn enqueue(&mut self, id: ThreadId, _entity: &mut SchedEntity) {
+        self.ready.push_back(id);
+    }
+
+    fn remove(&mut self, id: ThreadId, _entity: &SchedEntity) {
+        self.ready.retain(|&ready| ready != id);
+    }
+
+    fn pick_next(&mut self) -> Option<ThreadId> {
+        self.ticks_left = TIME_SLICE_TICKS;
+        self.ready.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.ready.len()
+    }
+
+    fn should_preempt(&mut self, _id: ThreadId, _entity: &SchedEntity) -> bool {
+        self.ticks_left = self.ticks_left.saturating_sub(1);
+        self.ticks_left == 0 && !self.ready.is_empty()
+    }
+}
@@ -0,0 +1,80 @@
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use x86_64::structures::paging::{FrameAllocator, Mapper, Page, PageTableFlags, Size4KiB};
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::VirtAddr;
+
+use crate::mem;
+use crate::sync::SpinLock;
+
+/// 内核线程栈区,每个槽位最低一页不映射作为 guard page
+pub const STACK_REGION_BOTTOM: u64 = 0x6666_0000_0000;
+pub const STACK_REGION_SIZE: u64 = 0x10_0000_0000;
+pub const STACK_PAGES: u64 = 8;
+const PAGE_SIZE: u64 = 4096;
+const SLOT_SIZE: u64 = (STACK_PAGES + 1) * PAGE_SIZE;
+
+static NEXT_SLOT: AtomicU64 = AtomicU64::new(0);
+/// 已映射的栈在线程退出后回收复用,物理帧不归还
+static FREE_SLOTS: SpinLock<Vec<u64>> = SpinLock::new(Vec::new());
+
+#[derive(Debug)]
+pub struct Stack {
+    slot: u64,
+}
+
+impl Stack {
+    pub fn allocate() -> Result<Stack, MapToError<Size4KiB>> {
+        let reused = x86_64::instructions::interrupts::without_interrupts(|| FREE_SLOTS.lock().pop());
+        if let Some(slot) = reused {
+            return Ok(Stack { slot });
+        }
+        let slot = NEXT_SLOT.fetch_add(1, Ordering::SeqCst);
+        assert!((slot + 1) * SLOT_SIZE <= STACK_REGION_SIZE, "kernel stack region exhausted");
+        let stack = Stack { slot };
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+        mem::with_mapper(|mapper, frame_allocator| {
+            let bottom = Page::<Size4KiB>::containing_address(stack.bottom());
+            let top = Page::<Size4KiB>::containing_address(stack.top() - 1u64);
+            for page in Page::range_inclusive(bottom, top) {
+                let frame = frame_allocator.allocate_frame().ok_or(MapToError::FrameAllocationFailed)?;
+                unsafe { mapper.map_to(page, frame, flags, frame_allocator)?.flush(); }
+            }
+            Ok::<(), MapToError<Size4KiB>>(())
+        })?;
+        Ok(stack)
+    }
+
+    fn slot_start(&self) -> u64 {
+        STACK_REGION_BOTTOM + self.slot * SLOT_SIZE
+    }
+
+    pub fn guard_page(&self) -> VirtAddr {
+        VirtAddr::new(self.slot_start())
+    }
+
+    pub fn bottom(&self) -> VirtAddr {
+        VirtAddr::new(self.slot_start() + PAGE_SIZE)
+    }
+
+    pub fn top(&self) -> VirtAddr {
+        VirtAddr::new(self.slot_start() + SLOT_SIZE)
+    }
+}
+
+impl Drop for Stack {
+    fn drop(&mut self) {
+        let slot = self.slot;
+        x86_64::instructions::interrupts::without_interrupts(|| FREE_SLOTS.lock().push(slot));
+    }
+}
+
+/// 用于缺页/双重错误时判断是否为内核栈溢出
+pub fn is_guard_page(addr: VirtAddr) -> bool {
+    let addr = addr.as_u64();
+    if addr < STACK_REGION_BOTTOM || addr >= STACK_REGION_BOTTOM + STACK_REGION_SIZE {
+        return false;
+    }
+    (addr - STACK_REGION_BOTTOM) % SLOT_SIZE < PAGE_SIZE
+}
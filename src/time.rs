@@ -0,0 +1,237 @@
+use core::ops::{Add, Sub};
+use core::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering};
+use core::time::Duration;
+
+use x86_64::instructions::interrupts;
+
+use crate::println;
+
+pub mod pit;
+pub mod tsc;
+pub mod timer;
+pub mod hpet;
+pub mod rtc;
+
+pub use rtc::DateTime;
+pub use timer::{add_periodic_timer, add_timer, cancel_timer, TimedOut, Timeout, TimerId};
+
+pub const DEFAULT_TICK_HZ: u32 = 1000;
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// 驱动 `InterruptIndex::Timer` 的时钟源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ClockSource {
+    Pit = 0,
+    LapicTimer = 1,
+    Hpet = 2,
+}
+
+static TICK_HZ: AtomicU32 = AtomicU32::new(DEFAULT_TICK_HZ);
+static NANOS_PER_TICK: AtomicU64 = AtomicU64::new(NANOS_PER_SEC / DEFAULT_TICK_HZ as u64);
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static CLOCK_NANOS: AtomicU64 = AtomicU64::new(0);
+static LAST_TICK_TSC: AtomicU64 = AtomicU64::new(0);
+static CLOCK_SOURCE: AtomicU8 = AtomicU8::new(ClockSource::Pit as u8);
+/// 切换到 HPET 时的时间与计数器读数
+static HPET_BASE_NANOS: AtomicU64 = AtomicU64::new(0);
+static HPET_BASE_COUNTER: AtomicU64 = AtomicU64::new(0);
+/// 开机时刻对应的 UNIX 时间(纳秒)
+static BOOT_UNIX_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// 开机以来的单调时间点,精度为纳秒
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant {
+    nanos: u64,
+}
+
+impl Instant {
+    pub fn as_nanos(&self) -> u64 {
+        self.nanos
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        now() - *self
+    }
+
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_nanos(self.nanos.saturating_sub(earlier.nanos))
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    /// 超出范围时饱和到最远的时刻,超长的等待即为永远等待
+    fn add(self, rhs: Duration) -> Instant {
+        let nanos = u64::try_from(rhs.as_nanos()).unwrap_or(u64::MAX);
+        Instant { nanos: self.nanos.saturating_add(nanos) }
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Duration {
+        self.duration_since(rhs)
+    }
+}
+
+/// 以 `hz` 编程 PIT 并校准 TSC,需在 IDT 初始化之后调用
+pub fn init(hz: u32) {
+    println!("Init time ...");
+    let divisor = pit::set_frequency(hz);
+    TICK_HZ.store(hz, Ordering::SeqCst);
+    NANOS_PER_TICK.store(divisor as u64 * NANOS_PER_SEC / pit::PIT_FREQUENCY as u64, Ordering::SeqCst);
+    let tsc_hz = tsc::calibrate();
+    println!("Timer {} Hz, TSC {} MHz", hz, tsc_hz / 1_000_000);
+    sync_wall_clock();
+    println!("Wall clock {} UTC", wall_clock().date);
+}
+
+/// 以 RTC 重新校准墙上时间
+pub fn sync_wall_clock() {
+    let rtc_nanos = rtc::read().unix_timestamp() * NANOS_PER_SEC;
+    BOOT_UNIX_NANOS.store(rtc_nanos.saturating_sub(now().as_nanos()), Ordering::SeqCst);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WallClock {
+    pub timestamp: u64,
+    pub nanos: u32,
+    pub date: DateTime,
+}
+
+/// RTC 只精确到秒,秒以下由单调时钟推进
+pub fn wall_clock() -> WallClock {
+    let unix_nanos = BOOT_UNIX_NANOS.load(Ordering::SeqCst) + now().as_nanos();
+    let timestamp = unix_nanos / NANOS_PER_SEC;
+    WallClock {
+        timestamp,
+        nanos: (unix_nanos % NANOS_PER_SEC) as u32,
+        date: DateTime::from_unix_timestamp(timestamp),
+    }
+}
+
+/// 切换时钟源后由其驱动方调用,之后的 tick 按新时钟的周期累加
+pub fn set_clock_source(source: ClockSource, hz: u32) {
+    if source == ClockSource::Hpet {
+        HPET_BASE_NANOS.store(now().as_nanos(), Ordering::SeqCst);
+        HPET_BASE_COUNTER.store(hpet::counter(), Ordering::SeqCst);
+    }
+    CLOCK_SOURCE.store(source as u8, Ordering::SeqCst);
+    TICK_HZ.store(hz, Ordering::SeqCst);
+    NANOS_PER_TICK.store(NANOS_PER_SEC / hz as u64, Ordering::SeqCst);
+}
+
+pub fn clock_source() -> ClockSource {
+    match CLOCK_SOURCE.load(Ordering::Relaxed) {
+        1 => ClockSource::LapicTimer,
+        2 => ClockSource::Hpet,
+        _ => ClockSource::Pit,
+    }
+}
+
+pub fn tick_hz() -> u32 {
+    TICK_HZ.load(Ordering::Relaxed)
+}
+
+/// 由 timer 中断调用
+pub fn tick() {
+    CLOCK_NANOS.fetch_add(NANOS_PER_TICK.load(Ordering::Relaxed), Ordering::SeqCst);
+    LAST_TICK_TSC.store(tsc::read(), Ordering::SeqCst);
+    TICKS.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::SeqCst)
+}
+
+/// 以 HPET 替代 PIT/LAPIC timer 产生 tick,并以其主计数器作为高精度时钟;
+/// 启动时仅在开启 `hpet` feature 时调用
+pub fn use_hpet() -> Result<(), hpet::HpetError> {
+    let hz = tick_hz();
+    hpet::init()?;
+    // 32 位计数器在 14.3MHz 下约 5 分钟回绕,不适合作为单调时钟
+    if !hpet::counter_is_64bit() {
+        return Err(hpet::HpetError::CounterTooNarrow);
+    }
+    interrupts::without_interrupts(|| {
+        hpet::start_legacy_tick(hz)?;
+        set_clock_source(ClockSource::Hpet, hz);
+        Ok(())
+    })
+}
+
+/// HPET 时直接读取主计数器;
+/// 否则为累计的 tick 时间加上 TSC 插值,插值不超过一个 tick,保证单调
+pub fn now() -> Instant {
+    if clock_source() == ClockSource::Hpet {
+        let elapsed = hpet::counter().wrapping_sub(HPET_BASE_COUNTER.load(Ordering::SeqCst));
+        return Instant { nanos: HPET_BASE_NANOS.load(Ordering::SeqCst) + hpet::counter_to_nanos(elapsed) };
+    }
+    interrupts::without_interrupts(|| {
+        let nanos = CLOCK_NANOS.load(Ordering::SeqCst);
+        let offset = match TICKS.load(Ordering::SeqCst) {
+            0 => 0,
+            _ => {
+                let cycles = tsc::read().saturating_sub(LAST_TICK_TSC.load(Ordering::SeqCst));
+                tsc::cycles_to_nanos(cycles).min(NANOS_PER_TICK.load(Ordering::Relaxed) - 1)
+            }
+        };
+        Instant { nanos: nanos + offset }
+    })
+}
+
+pub fn uptime() -> Duration {
+    Duration::from_nanos(now().as_nanos())
+}
+
+/// 忙等待,可在关中断时调用
+///
+/// 关中断或时钟中断还没开始时 tick 不会前进,`now()` 的 TSC 插值又不超过一个 tick,
+/// 这时改为直接数 TSC 周期;HPET 主计数器不依赖中断,照常使用 `now()`。
+pub fn sleep(duration: Duration) {
+    if clock_source() != ClockSource::Hpet && (!interrupts::are_enabled() || ticks() == 0) {
+        return spin_without_ticks(duration);
+    }
+    let deadline = now() + duration;
+    while now() < deadline {
+        core::hint::spin_loop();
+    }
+}
+
+/// PIT channel 2 单次计时的上限约 55ms,分段等待
+const PIT_ONESHOT_MAX_MS: u32 = 50;
+
+fn spin_without_ticks(duration: Duration) {
+    let hz = tsc::frequency();
+    if hz != 0 {
+        let cycles = (duration.as_nanos() * hz as u128 / NANOS_PER_SEC as u128).min(u64::MAX as u128) as u64;
+        let start = tsc::read();
+        while tsc::read().wrapping_sub(start) < cycles {
+            core::hint::spin_loop();
+        }
+        return;
+    }
+    // TSC 未校准:以 PIT channel 2 计时,按毫秒向上取整
+    let mut ms = duration.as_micros().div_ceil(1000);
+    while ms > 0 {
+        let chunk = ms.min(PIT_ONESHOT_MAX_MS as u128) as u32;
+        interrupts::without_interrupts(|| unsafe {
+            pit::start_oneshot(chunk);
+            while !pit::oneshot_expired() {
+                core::hint::spin_loop();
+            }
+        });
+        ms -= chunk as u128;
+    }
+}
+
+pub fn sleep_ms(ms: u64) {
+    sleep(Duration::from_millis(ms))
+}
+
+pub fn sleep_us(us: u64) {
+    sleep(Duration::from_micros(us))
+}
@@ -0,0 +1,210 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+use x86_64::PhysAddr;
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::structures::paging::Size4KiB;
+
+use crate::{acpi, mem};
+use crate::apic::{self, ioapic, lapic};
+
+const REG_CAPABILITIES: u64 = 0x000;
+const REG_CONFIG: u64 = 0x010;
+const REG_INTERRUPT_STATUS: u64 = 0x020;
+const REG_MAIN_COUNTER: u64 = 0x0F0;
+
+const fn reg_timer_config(index: u8) -> u64 {
+    0x100 + 0x20 * index as u64
+}
+
+const fn reg_timer_comparator(index: u8) -> u64 {
+    0x108 + 0x20 * index as u64
+}
+
+const CAP_COUNTER_64BIT: u64 = 1 << 13;
+const CAP_LEGACY_REPLACEMENT: u64 = 1 << 15;
+const CONFIG_ENABLE: u64 = 1 << 0;
+const CONFIG_LEGACY_REPLACEMENT: u64 = 1 << 1;
+
+const TIMER_LEVEL_TRIGGERED: u64 = 1 << 1;
+const TIMER_INTERRUPT_ENABLE: u64 = 1 << 2;
+const TIMER_PERIODIC: u64 = 1 << 3;
+const TIMER_PERIODIC_CAPABLE: u64 = 1 << 4;
+const TIMER_VALUE_SET: u64 = 1 << 6;
+const TIMER_ROUTE_SHIFT: u64 = 9;
+const TIMER_ROUTE_MASK: u64 = 0x1F << TIMER_ROUTE_SHIFT;
+
+const FEMTOS_PER_NANO: u64 = 1_000_000;
+/// 规范要求计数周期不超过 100ns
+const MAX_PERIOD_FEMTOS: u64 = 100_000_000;
+
+#[derive(Debug)]
+pub enum HpetError {
+    TableNotFound,
+    NotMemoryMapped,
+    InvalidPeriod(u64),
+    NoSuchTimer(u8),
+    PeriodicUnsupported(u8),
+    LegacyReplacementUnsupported,
+    CounterTooNarrow,
+    NoRoute(u8),
+    MapFailed(MapToError<Size4KiB>),
+}
+
+static BASE: AtomicU64 = AtomicU64::new(0);
+static PERIOD_FEMTOS: AtomicU64 = AtomicU64::new(0);
+
+unsafe fn read(reg: u64) -> u64 {
+    core::ptr::read_volatile((BASE.load(Ordering::SeqCst) + reg) as *const u64)
+}
+
+unsafe fn write(reg: u64, value: u64) {
+    core::ptr::write_volatile((BASE.load(Ordering::SeqCst) + reg) as *mut u64, value);
+}
+
+pub fn is_initialized() -> bool {
+    BASE.load(Ordering::SeqCst) != 0
+}
+
+/// 通过 ACPI HPET 表找到寄存器并映射,启动主计数器
+pub fn init() -> Result<(), HpetError> {
+    if is_initialized() {
+        return Ok(());
+    }
+    let table = acpi::hpet().ok_or(HpetError::TableNotFound)?;
+    if table.base_address.address_space_id != acpi::ADDRESS_SPACE_SYSTEM_MEMORY {
+        return Err(HpetError::NotMemoryMapped);
+    }
+    let base = mem::map_mmio(PhysAddr::new(table.base_address.address), 1024).map_err(HpetError::MapFailed)?;
+    BASE.store(base.as_u64(), Ordering::SeqCst);
+    unsafe {
+        let period = read(REG_CAPABILITIES) >> 32;
+        if period == 0 || period > MAX_PERIOD_FEMTOS {
+            BASE.store(0, Ordering::SeqCst);
+            return Err(HpetError::InvalidPeriod(period));
+        }
+        PERIOD_FEMTOS.store(period, Ordering::SeqCst);
+        for index in 0..timer_count() {
+            let config = read(reg_timer_config(index));
+            write(reg_timer_config(index), config & !(TIMER_INTERRUPT_ENABLE | TIMER_PERIODIC));
+        }
+        write(REG_CONFIG, read(REG_CONFIG) | CONFIG_ENABLE);
+    }
+    Ok(())
+}
+
+pub fn timer_count() -> u8 {
+    unsafe { (((read(REG_CAPABILITIES) >> 8) & 0x1F) + 1) as u8 }
+}
+
+pub fn counter_is_64bit() -> bool {
+    unsafe { read(REG_CAPABILITIES) & CAP_COUNTER_64BIT != 0 }
+}
+
+pub fn frequency() -> u64 {
+    match PERIOD_FEMTOS.load(Ordering::Relaxed) {
+        0 => 0,
+        period => 1_000_000_000_000_000 / period,
+    }
+}
+
+pub fn counter() -> u64 {
+    unsafe { read(REG_MAIN_COUNTER) }
+}
+
+pub fn counter_to_nanos(count: u64) -> u64 {
+    (count as u128 * PERIOD_FEMTOS.load(Ordering::Relaxed) as u128 / FEMTOS_PER_NANO as u128) as u64
+}
+
+fn nanos_to_counter(nanos: u64) -> u64 {
+    (nanos as u128 * FEMTOS_PER_NANO as u128 / PERIOD_FEMTOS.load(Ordering::Relaxed) as u128).max(1) as u64
+}
+
+/// 主计数器换算的纳秒数
+pub fn nanos() -> u64 {
+    counter_to_nanos(counter())
+}
+
+fn check_timer(index: u8) -> Result<u64, HpetError> {
+    if !is_initialized() || index >= timer_count() {
+        return Err(HpetError::NoSuchTimer(index));
+    }
+    Ok(unsafe { read(reg_timer_config(index)) })
+}
+
+/// 将比较器的中断路由到 IOAPIC 上允许的第一个非 ISA 输入,并绑定到 `vector`
+pub fn route_to_vector(index: u8, vector: u8) -> Result<u32, HpetError> {
+    let config = check_timer(index)?;
+    let allowed = (config >> 32) as u32;
+    let gsi = (16..32).find(|gsi| allowed & (1 << gsi) != 0).ok_or(HpetError::NoRoute(index))?;
+    if !ioapic::route_gsi(gsi, vector, lapic::id(), false, false, false) {
+        return Err(HpetError::NoRoute(index));
+    }
+    let config = (config & !(TIMER_ROUTE_MASK | TIMER_LEVEL_TRIGGERED)) | (gsi as u64) << TIMER_ROUTE_SHIFT;
+    unsafe { write(reg_timer_config(index), config); }
+    Ok(gsi)
+}
+
+/// 比较器 `index` 在 `delay` 之后触发一次中断
+pub fn arm_oneshot(index: u8, delay: Duration) -> Result<(), HpetError> {
+    let config = check_timer(index)?;
+    unsafe {
+        write(reg_timer_config(index), (config & !TIMER_PERIODIC) | TIMER_INTERRUPT_ENABLE);
+        write(reg_timer_comparator(index), counter() + nanos_to_counter(delay.as_nanos() as u64));
+    }
+    Ok(())
+}
+
+pub fn start_periodic(index: u8, hz: u32) -> Result<(), HpetError> {
+    let config = check_timer(index)?;
+    if config & TIMER_PERIODIC_CAPABLE == 0 {
+        return Err(HpetError::PeriodicUnsupported(index));
+    }
+    let period = nanos_to_counter(1_000_000_000 / hz as u64);
+    unsafe {
+        // 设置周期期间暂停主计数器
+        let general = read(REG_CONFIG);
+        write(REG_CONFIG, general & !CONFIG_ENABLE);
+        write(reg_timer_config(index), config | TIMER_INTERRUPT_ENABLE | TIMER_PERIODIC | TIMER_VALUE_SET);
+        write(reg_timer_comparator(index), counter() + period);
+        write(reg_timer_comparator(index), period);
+        write(REG_CONFIG, general | CONFIG_ENABLE);
+    }
+    Ok(())
+}
+
+pub fn stop_timer(index: u8) -> Result<(), HpetError> {
+    let config = check_timer(index)?;
+    unsafe { write(reg_timer_config(index), config & !(TIMER_INTERRUPT_ENABLE | TIMER_PERIODIC)); }
+    Ok(())
+}
+
+/// 电平触发时需在中断处理中清除状态位
+pub fn acknowledge(index: u8) {
+    unsafe { write(REG_INTERRUPT_STATUS, 1 << index); }
+}
+
+/// 是否处于 legacy replacement 模式,此时 IRQ0 与 IRQ8 都由 HPET 占用
+pub fn legacy_replacement_enabled() -> bool {
+    is_initialized() && unsafe { read(REG_CONFIG) & CONFIG_LEGACY_REPLACEMENT != 0 }
+}
+
+/// legacy replacement 模式下 timer 0 取代 PIT 接在 IRQ0 上,timer 1 占用 RTC 的 IRQ8,
+/// 复用现有的 `InterruptIndex::Timer` 处理路径
+pub fn start_legacy_tick(hz: u32) -> Result<(), HpetError> {
+    init()?;
+    unsafe {
+        if read(REG_CAPABILITIES) & CAP_LEGACY_REPLACEMENT == 0 {
+            return Err(HpetError::LegacyReplacementUnsupported);
+        }
+    }
+    start_periodic(0, hz)?;
+    unsafe { write(REG_CONFIG, read(REG_CONFIG) | CONFIG_LEGACY_REPLACEMENT); }
+    if apic::is_enabled() {
+        unsafe { lapic::stop_timer(); }
+        if let Some(gsi) = ioapic::isa_irq_gsi(apic::ISA_TIMER_IRQ) {
+            ioapic::set_masked(gsi, false);
+        }
+    }
+    Ok(())
+}
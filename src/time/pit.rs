@@ -0,0 +1,53 @@
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+
+/// PIT 输入时钟频率
+pub const PIT_FREQUENCY: u32 = 1_193_182;
+
+const CHANNEL_0_PORT: u16 = 0x40;
+const CHANNEL_2_PORT: u16 = 0x42;
+const COMMAND_PORT: u16 = 0x43;
+const CHANNEL_2_GATE_PORT: u16 = 0x61;
+
+const GATE_ENABLE: u8 = 0b01;
+const SPEAKER_ENABLE: u8 = 0b10;
+const CHANNEL_2_OUTPUT: u8 = 0x20;
+
+/// 将 channel 0 编程为 mode 2 (rate generator),返回实际使用的分频值
+pub fn set_frequency(hz: u32) -> u16 {
+    let divisor = (PIT_FREQUENCY / hz).clamp(1, u16::MAX as u32) as u16;
+    let mut command: Port<u8> = Port::new(COMMAND_PORT);
+    let mut channel0: Port<u8> = Port::new(CHANNEL_0_PORT);
+    interrupts::without_interrupts(|| unsafe {
+        // channel 0, lobyte/hibyte, mode 2, binary
+        command.write(0b0011_0100);
+        channel0.write(divisor as u8);
+        channel0.write((divisor >> 8) as u8);
+    });
+    divisor
+}
+
+/// channel 2 单次计时,配合 [`oneshot_expired`] 轮询,用于校准其它时钟
+pub unsafe fn start_oneshot(ms: u32) {
+    let mut gate: Port<u8> = Port::new(CHANNEL_2_GATE_PORT);
+    let mut command: Port<u8> = Port::new(COMMAND_PORT);
+    let mut channel2: Port<u8> = Port::new(CHANNEL_2_PORT);
+
+    // 打开 channel 2 gate,关闭扬声器
+    let value = gate.read();
+    gate.write((value & !SPEAKER_ENABLE) | GATE_ENABLE);
+    // channel 2, lobyte/hibyte, mode 0
+    command.write(0b1011_0000);
+    let count = (PIT_FREQUENCY as u64 * ms as u64 / 1000).min(u16::MAX as u64) as u16;
+    channel2.write(count as u8);
+    channel2.write((count >> 8) as u8);
+    // 拉低再拉高 gate 重新开始计数
+    let value = gate.read();
+    gate.write(value & !GATE_ENABLE);
+    gate.write(value | GATE_ENABLE);
+}
+
+pub fn oneshot_expired() -> bool {
+    let mut gate: Port<u8> = Port::new(CHANNEL_2_GATE_PORT);
+    unsafe { gate.read() & CHANNEL_2_OUTPUT != 0 }
+}
@@ -0,0 +1,262 @@
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+use x86_64::structures::idt::InterruptStackFrame;
+
+use crate::apic::{self, ioapic};
+use crate::idt::{enter_interrupt, InterruptIndex, notify_end_of_interrupt, PICS};
+use crate::sync::SpinLock;
+use crate::time::hpet;
+use crate::user;
+
+const CMOS_ADDRESS_PORT: u16 = 0x70;
+const CMOS_DATA_PORT: u16 = 0x71;
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+const REG_STATUS_C: u8 = 0x0C;
+const REG_CENTURY: u8 = 0x32;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+const STATUS_B_BINARY: u8 = 1 << 2;
+const STATUS_B_PERIODIC_INTERRUPT: u8 = 1 << 6;
+const HOUR_PM: u8 = 1 << 7;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+static CMOS: SpinLock<()> = SpinLock::new(());
+static PERIODIC_TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// 需持有 `CMOS` 且关中断
+unsafe fn read_register(reg: u8) -> u8 {
+    let mut address: Port<u8> = Port::new(CMOS_ADDRESS_PORT);
+    let mut data: Port<u8> = Port::new(CMOS_DATA_PORT);
+    address.write(reg);
+    data.read()
+}
+
+unsafe fn write_register(reg: u8, value: u8) {
+    let mut address: Port<u8> = Port::new(CMOS_ADDRESS_PORT);
+    let mut data: Port<u8> = Port::new(CMOS_DATA_PORT);
+    address.write(reg);
+    data.write(value);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    pub fn unix_timestamp(&self) -> u64 {
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        days as u64 * SECS_PER_DAY + self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64
+    }
+
+    pub fn from_unix_timestamp(timestamp: u64) -> Self {
+        let (year, month, day) = civil_from_days((timestamp / SECS_PER_DAY) as i64);
+        let secs = timestamp % SECS_PER_DAY;
+        DateTime {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+
+    /// 0 为星期日
+    pub fn weekday(&self) -> u8 {
+        ((self.unix_timestamp() / SECS_PER_DAY + 4) % 7) as u8
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}", self.year, self.month, self.day, self.hour, self.minute, self.second)
+    }
+}
+
+/// 1970-01-01 起的天数 (Howard Hinnant, days_from_civil)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+    (value & 0x0F) + (value >> 4) * 10
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct RawTime([u8; 7]);
+
+unsafe fn read_raw() -> RawTime {
+    while read_register(REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0 {
+        core::hint::spin_loop();
+    }
+    RawTime([
+        read_register(REG_SECONDS),
+        read_register(REG_MINUTES),
+        read_register(REG_HOURS),
+        read_register(REG_DAY),
+        read_register(REG_MONTH),
+        read_register(REG_YEAR),
+        read_register(REG_CENTURY),
+    ])
+}
+
+/// 连续两次读到相同的值才认为没有跨越更新周期
+pub fn read() -> DateTime {
+    let (raw, status_b) = interrupts::without_interrupts(|| {
+        let _cmos = CMOS.lock();
+        unsafe {
+            let mut raw = read_raw();
+            loop {
+                let again = read_raw();
+                if again == raw {
+                    break;
+                }
+                raw = again;
+            }
+            (raw, read_register(REG_STATUS_B))
+        }
+    });
+    let [mut second, mut minute, hour, mut day, mut month, mut year, mut century] = raw.0;
+    let pm = hour & HOUR_PM != 0;
+    let mut hour = hour & !HOUR_PM;
+    if status_b & STATUS_B_BINARY == 0 {
+        second = bcd_to_binary(second);
+        minute = bcd_to_binary(minute);
+        hour = bcd_to_binary(hour);
+        day = bcd_to_binary(day);
+        month = bcd_to_binary(month);
+        year = bcd_to_binary(year);
+        century = bcd_to_binary(century);
+    }
+    if status_b & STATUS_B_24_HOUR == 0 {
+        // 12 小时制: 12AM 为 0 点, 12PM 为 12 点
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+    // 不是所有平台都提供 century 寄存器
+    let century = match century {
+        19..=99 => century as u16,
+        _ => 20,
+    };
+    DateTime {
+        year: century * 100 + year as u16,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcError {
+    /// HPET legacy replacement 模式占用了 IRQ8,RTC 中断不会再送达
+    IrqTakenByHpet,
+}
+
+/// 开启 IRQ8 周期中断,频率为 32768 >> (rate - 1),rate 取 3..=15
+pub fn enable_periodic_interrupt(rate: u8) -> Result<(), RtcError> {
+    if hpet::legacy_replacement_enabled() {
+        return Err(RtcError::IrqTakenByHpet);
+    }
+    let rate = rate.clamp(3, 15);
+    interrupts::without_interrupts(|| {
+        {
+            let _cmos = CMOS.lock();
+            unsafe {
+                let status_a = read_register(REG_STATUS_A);
+                write_register(REG_STATUS_A, (status_a & 0xF0) | rate);
+                let status_b = read_register(REG_STATUS_B);
+                write_register(REG_STATUS_B, status_b | STATUS_B_PERIODIC_INTERRUPT);
+                read_register(REG_STATUS_C);
+            }
+        }
+        set_irq_masked(false);
+    });
+    Ok(())
+}
+
+pub fn disable_periodic_interrupt() {
+    interrupts::without_interrupts(|| {
+        set_irq_masked(true);
+        let _cmos = CMOS.lock();
+        unsafe {
+            let status_b = read_register(REG_STATUS_B);
+            write_register(REG_STATUS_B, status_b & !STATUS_B_PERIODIC_INTERRUPT);
+        }
+    });
+}
+
+fn set_irq_masked(masked: bool) {
+    if apic::is_enabled() {
+        if let Some(gsi) = ioapic::isa_irq_gsi(apic::ISA_RTC_IRQ) {
+            ioapic::set_masked(gsi, masked);
+        }
+    } else {
+        let mut pics = PICS.lock();
+        unsafe {
+            let [primary, secondary] = pics.read_masks();
+            // 从片经主片 IRQ2 级联
+            match masked {
+                true => pics.write_masks(primary, secondary | 0b1),
+                false => pics.write_masks(primary & !0b100, secondary & !0b1),
+            }
+        }
+    }
+}
+
+pub fn periodic_ticks() -> u64 {
+    PERIODIC_TICKS.load(Ordering::Relaxed)
+}
+
+/// 必须读取 status C,否则 RTC 不会再产生中断
+pub extern "x86-interrupt" fn rtc_interrupt_handler(mut stack_frame: InterruptStackFrame) {
+    let irq = enter_interrupt(InterruptIndex::Rtc.as_u8());
+    {
+        let _cmos = CMOS.lock();
+        unsafe { read_register(REG_STATUS_C); }
+    }
+    PERIODIC_TICKS.fetch_add(1, Ordering::Relaxed);
+    notify_end_of_interrupt(InterruptIndex::Rtc);
+    drop(irq);
+    user::return_from_interrupt(&mut stack_frame);
+}
@@ -0,0 +1,184 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BinaryHeap};
+use core::cmp::Reverse;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+use lazy_static::lazy_static;
+use x86_64::instructions::interrupts;
+
+use crate::softirq;
+use crate::sync::SpinLock;
+use crate::time::{self, Instant};
+
+pub type TimerCallback = Box<dyn FnMut() + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimerId(u64);
+
+struct Timer {
+    deadline: u64,
+    period: Option<u64>,
+    /// 回调执行期间为 None
+    callback: Option<TimerCallback>,
+}
+
+/// 最小堆按到期时间排序;取消时只从 `timers` 中删除,堆中的条目在出堆时惰性丢弃
+struct TimerQueue {
+    heap: BinaryHeap<Reverse<(u64, TimerId)>>,
+    timers: BTreeMap<TimerId, Timer>,
+    next_id: u64,
+}
+
+impl TimerQueue {
+    fn insert(&mut self, deadline: u64, period: Option<u64>, callback: TimerCallback) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.insert(id, Timer { deadline, period, callback: Some(callback) });
+        self.heap.push(Reverse((deadline, id)));
+        id
+    }
+
+    fn pop_expired(&mut self, now: u64) -> Option<(TimerId, TimerCallback)> {
+        while let Some(&Reverse((deadline, id))) = self.heap.peek() {
+            if deadline > now {
+                return None;
+            }
+            self.heap.pop();
+            let timer = match self.timers.get_mut(&id) {
+                Some(timer) if timer.deadline == deadline => timer,
+                _ => continue,
+            };
+            let callback = match timer.period {
+                Some(_) => timer.callback.take(),
+                None => self.timers.remove(&id).and_then(|timer| timer.callback),
+            };
+            if let Some(callback) = callback {
+                return Some((id, callback));
+            }
+        }
+        None
+    }
+
+    /// 周期定时器回调结束后重新入堆;回调期间被取消的不再恢复
+    fn rearm(&mut self, id: TimerId, callback: TimerCallback) {
+        if let Some(timer) = self.timers.get_mut(&id) {
+            if let Some(period) = timer.period {
+                timer.deadline = timer.deadline.saturating_add(period);
+                timer.callback = Some(callback);
+                self.heap.push(Reverse((timer.deadline, id)));
+            }
+        }
+    }
+
+    fn next_deadline(&self) -> u64 {
+        self.heap.peek().map(|&Reverse((deadline, _))| deadline).unwrap_or(u64::MAX)
+    }
+}
+
+lazy_static! {
+    static ref TIMERS: SpinLock<TimerQueue> = SpinLock::new(TimerQueue {
+        heap: BinaryHeap::new(),
+        timers: BTreeMap::new(),
+        next_id: 0,
+    });
+}
+
+/// 中断中只比较该值,避免在硬中断里操作堆
+static NEXT_DEADLINE: AtomicU64 = AtomicU64::new(u64::MAX);
+
+fn with_timers<F, R>(f: F) -> R where F: FnOnce(&mut TimerQueue) -> R {
+    interrupts::without_interrupts(|| {
+        let mut timers = TIMERS.lock();
+        let result = f(&mut timers);
+        NEXT_DEADLINE.store(timers.next_deadline(), Ordering::SeqCst);
+        result
+    })
+}
+
+/// 在 `deadline` 到期后于 softirq 上下文中执行一次 `callback`
+pub fn add_timer<F>(deadline: Instant, callback: F) -> TimerId where F: FnMut() + Send + 'static {
+    with_timers(|timers| timers.insert(deadline.as_nanos(), None, Box::new(callback)))
+}
+
+/// 从现在起每隔 `period` 执行一次 `callback`
+pub fn add_periodic_timer<F>(period: Duration, callback: F) -> TimerId where F: FnMut() + Send + 'static {
+    let period = u64::try_from(period.as_nanos()).unwrap_or(u64::MAX).max(1);
+    let deadline = (time::now() + Duration::from_nanos(period)).as_nanos();
+    with_timers(|timers| timers.insert(deadline, Some(period), Box::new(callback)))
+}
+
+/// 已到期的一次性定时器返回 false
+pub fn cancel_timer(id: TimerId) -> bool {
+    with_timers(|timers| timers.timers.remove(&id).is_some())
+}
+
+pub fn pending_timers() -> usize {
+    with_timers(|timers| timers.timers.len())
+}
+
+/// 由 timer 中断在 `time::tick` 之后调用
+pub fn on_tick() {
+    if time::now().as_nanos() >= NEXT_DEADLINE.load(Ordering::SeqCst) {
+        softirq::queue(run_expired, 0);
+    }
+}
+
+fn run_expired(_: usize) {
+    let now = time::now().as_nanos();
+    while let Some((id, mut callback)) = with_timers(|timers| timers.pop_expired(now)) {
+        callback();
+        with_timers(|timers| timers.rearm(id, callback));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
+/// 阻塞操作的超时期限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout {
+    deadline: Option<Instant>,
+}
+
+impl Timeout {
+    pub fn after(duration: Duration) -> Self {
+        Timeout { deadline: Some(time::now() + duration) }
+    }
+
+    pub fn at(deadline: Instant) -> Self {
+        Timeout { deadline: Some(deadline) }
+    }
+
+    pub fn never() -> Self {
+        Timeout { deadline: None }
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.deadline {
+            Some(deadline) => time::now() >= deadline,
+            None => false,
+        }
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.duration_since(time::now()))
+    }
+}
+
+/// 在 `condition` 满足或超时前以 `hlt` 等待中断,需在开中断状态下调用
+pub fn wait_until<F>(mut condition: F, timeout: Timeout) -> Result<(), TimedOut> where F: FnMut() -> bool {
+    loop {
+        if condition() {
+            return Ok(());
+        }
+        if timeout.is_expired() {
+            return Err(TimedOut);
+        }
+        x86_64::instructions::hlt();
+    }
+}
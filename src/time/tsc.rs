@@ -0,0 +1,39 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use x86_64::instructions::interrupts;
+
+use crate::time::pit;
+
+const CALIBRATE_MS: u32 = 10;
+
+static TSC_HZ: AtomicU64 = AtomicU64::new(0);
+
+pub fn read() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+/// 以 PIT channel 2 为基准测量 TSC 频率
+pub fn calibrate() -> u64 {
+    let hz = interrupts::without_interrupts(|| unsafe {
+        pit::start_oneshot(CALIBRATE_MS);
+        let start = read();
+        while !pit::oneshot_expired() {
+            core::hint::spin_loop();
+        }
+        (read() - start) * (1000 / CALIBRATE_MS as u64)
+    });
+    TSC_HZ.store(hz, Ordering::SeqCst);
+    hz
+}
+
+/// 未校准时为 0
+pub fn frequency() -> u64 {
+    TSC_HZ.load(Ordering::Relaxed)
+}
+
+pub fn cycles_to_nanos(cycles: u64) -> u64 {
+    match frequency() {
+        0 => 0,
+        hz => (cycles as u128 * 1_000_000_000 / hz as u128) as u64,
+    }
+}
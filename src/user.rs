@@ -0,0 +1,210 @@
+use core::arch::global_asm;
+use core::ptr::addr_of_mut;
+
+use x86_64::instructions::interrupts;
+use x86_64::structures::idt::{InterruptStackFrame, InterruptStackFrameValue};
+use x86_64::structures::paging::PageTableFlags;
+use x86_64::VirtAddr;
+
+use crate::{gdt, println, process};
+use crate::mem::address_space::{USER_MMAP_BASE, USER_STACK_SIZE, USER_STACK_TOP};
+use crate::process::signal;
+use crate::syscall::{self, Errno, SyscallFrame};
+
+pub use crate::mem::address_space::{is_user_address, is_user_pointer};
+
+// enter_user(rip, rsp, cs, ss):构造中断返回帧(RFLAGS 为 IF | 保留位),清空通用寄存器后 iretq 进入 ring 3
+// resume_user(frame):从 SyscallFrame 恢复通用寄存器,其末尾 5 项即是中断返回帧
+// interrupt_return:中断处理函数返回后以 ring 0 进入,此时通用寄存器仍是用户态的值;
+// 在内核栈上按 SyscallFrame 布局保存现场并递送信号,再 iretq 回到 ring 3,不破坏 rcx 与 r11
+global_asm!(r#"
+.global mongo_os_enter_user
+mongo_os_enter_user:
+    push rcx
+    push rsi
+    push 0x202
+    push rdx
+    push rdi
+    xor eax, eax
+    xor ebx, ebx
+    xor ecx, ecx
+    xor edx, edx
+    xor esi, esi
+    xor edi, edi
+    xor ebp, ebp
+    xor r8d, r8d
+    xor r9d, r9d
+    xor r10d, r10d
+    xor r11d, r11d
+    xor r12d, r12d
+    xor r13d, r13d
+    xor r14d, r14d
+    xor r15d, r15d
+    iretq
+
+.global mongo_os_resume_user
+mongo_os_resume_user:
+    mov rsp, rdi
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbp
+    pop rbx
+    pop r9
+    pop r8
+    pop r10
+    pop rdx
+    pop rsi
+    pop rdi
+    pop rax
+    xor ecx, ecx
+    xor r11d, r11d
+    iretq
+
+.global mongo_os_interrupt_return
+mongo_os_interrupt_return:
+    mov rsp, [rip + mongo_os_tss + 4]
+    push qword ptr [rip + mongo_os_interrupted_frame + 32]
+    push qword ptr [rip + mongo_os_interrupted_frame + 24]
+    push qword ptr [rip + mongo_os_interrupted_frame + 16]
+    push qword ptr [rip + mongo_os_interrupted_frame + 8]
+    push qword ptr [rip + mongo_os_interrupted_frame]
+    push rax
+    push rdi
+    push rsi
+    push rdx
+    push r10
+    push r8
+    push r9
+    push rbx
+    push rbp
+    push r12
+    push r13
+    push r14
+    push r15
+    mov rdi, rsp
+    call mongo_os_interrupt_return_dispatch
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbp
+    pop rbx
+    pop r9
+    pop r8
+    pop r10
+    pop rdx
+    pop rsi
+    pop rdi
+    pop rax
+    iretq
+"#);
+
+/// 进入用户态时的 RFLAGS:IF 与保留位 1
+pub const INITIAL_RFLAGS: u64 = 0x202;
+
+/// 被中断的用户态返回帧(rip, cs, rflags, rsp, ss),只在关中断时写入并立即由 interrupt_return 取走
+#[export_name = "mongo_os_interrupted_frame"]
+static mut INTERRUPTED_FRAME: [u64; 5] = [0; 5];
+
+extern "C" {
+    fn mongo_os_enter_user(rip: u64, rsp: u64, cs: u64, ss: u64) -> !;
+    fn mongo_os_resume_user(frame: *const SyscallFrame) -> !;
+    fn mongo_os_interrupt_return();
+}
+
+/// 在当前进程的 mmap 区分配 `len` 字节(按页取整)的清零内存
+pub fn map_anonymous(len: u64, writable: bool, executable: bool) -> Result<VirtAddr, Errno> {
+    let mut flags = PageTableFlags::empty();
+    if writable {
+        flags |= PageTableFlags::WRITABLE;
+    }
+    if !executable {
+        flags |= PageTableFlags::NO_EXECUTE;
+    }
+    // 分配并清零大量页面,不持有进程表锁
+    process::with_address_space(|space| space.map_anonymous(len, flags).map_err(|_| Errno::ENOMEM))?
+}
+
+/// 检查 [addr, addr+len) 位于 mmap 区内
+pub fn check_mmap_range(addr: VirtAddr, len: u64) -> Result<(), Errno> {
+    let end = addr.as_u64().checked_add(len).ok_or(Errno::EINVAL)?;
+    if addr.as_u64() < USER_MMAP_BASE || end > USER_STACK_TOP - USER_STACK_SIZE {
+        return Err(Errno::EINVAL);
+    }
+    Ok(())
+}
+
+/// 解除 mmap 区中的映射,未映射的页被忽略
+pub fn unmap(addr: VirtAddr, len: u64) -> Result<(), Errno> {
+    check_mmap_range(addr, len)?;
+    process::with_address_space(|space| space.unmap(addr, len))
+}
+
+/// 从当前内核线程切换到 ring 3,中断与异常经 TSS 回到该线程的内核栈
+pub unsafe fn enter(entry: VirtAddr, stack_top: VirtAddr) -> ! {
+    x86_64::instructions::interrupts::disable();
+    let code = gdt::user_code_selector().0 as u64;
+    let data = gdt::user_data_selector().0 as u64;
+    mongo_os_enter_user(entry.as_u64(), stack_top.as_u64(), code, data)
+}
+
+/// 按保存的现场返回 ring 3,fork 出的子进程由此从系统调用返回
+pub unsafe fn resume(frame: &SyscallFrame) -> ! {
+    x86_64::instructions::interrupts::disable();
+    let frame = *frame;
+    mongo_os_resume_user(&frame)
+}
+
+pub fn from_user_mode(stack_frame: &InterruptStackFrame) -> bool {
+    stack_frame.code_segment & 0b11 == 3
+}
+
+/// 中断或异常返回 ring 3 前调用,须在处理函数的最后:有可递送的信号时改为先返回到内核的 `interrupt_return`,
+/// 在硬件中断之外以完整的寄存器现场递送信号,处理函数与默认动作都在那里执行
+pub fn return_from_interrupt(stack_frame: &mut InterruptStackFrame) {
+    if !from_user_mode(stack_frame) || !signal::has_deliverable() {
+        return;
+    }
+    interrupts::disable();
+    let interrupted = **stack_frame;
+    unsafe {
+        *addr_of_mut!(INTERRUPTED_FRAME) = [
+            interrupted.instruction_pointer.as_u64(),
+            interrupted.code_segment,
+            interrupted.cpu_flags,
+            interrupted.stack_pointer.as_u64(),
+            interrupted.stack_segment,
+        ];
+        // 以关中断的 ring 0 返回,到达 interrupt_return 之前不会被打断
+        stack_frame.as_mut().write(InterruptStackFrameValue {
+            instruction_pointer: VirtAddr::new(mongo_os_interrupt_return as unsafe extern "C" fn() as usize as u64),
+            code_segment: gdt::kernel_code_selector().0 as u64,
+            cpu_flags: 0x2,
+            stack_pointer: gdt::kernel_stack(),
+            stack_segment: gdt::kernel_data_selector().0 as u64,
+        });
+    }
+}
+
+#[no_mangle]
+extern "C" fn mongo_os_interrupt_return_dispatch(frame: &mut SyscallFrame) {
+    interrupts::enable();
+    signal::deliver(frame);
+    syscall::check_return_address(frame);
+    interrupts::disable();
+}
+
+/// 用户态触发的异常转为发给当前进程的信号 `sig`,返回 false 表示异常发生在内核
+///
+/// 有处理函数时在返回用户态前执行,否则按默认动作结束进程。
+pub fn raise_on_fault(name: &str, sig: u32, stack_frame: &mut InterruptStackFrame) -> bool {
+    if !from_user_mode(stack_frame) {
+        return false;
+    }
+    println!("USER FAULT: {} at {:?} in process {:?}", name, stack_frame.instruction_pointer, process::current());
+    signal::force(sig);
+    return_from_interrupt(stack_frame);
+    true
+}
@@ -1,9 +1,10 @@
 use core::fmt;
 
 use lazy_static::lazy_static;
-use spin::Mutex;
 use volatile::Volatile;
 
+use crate::sync::SpinLock;
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -123,7 +124,7 @@ impl fmt::Write for Writer {
 
 pub const VGA_PHYS_ADDR:u64 = 0xb8000;
 lazy_static! {
-    pub static ref WRITER: Mutex<Writer> = Mutex::new(
+    pub static ref WRITER: SpinLock<Writer> = SpinLock::new(
     Writer {
         column_position: 0,
         color_code: ColorCode::new(Color::Yellow, Color::Black),
@@ -146,10 +147,6 @@ macro_rules! println {
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
     use core::fmt::Write;
-    use x86_64::instructions::interrupts;
-    interrupts::without_interrupts(||{
-        // 允许闭包在无中断环境下运行
-        // 避免deadlock
-        WRITER.lock().write_fmt(args).unwrap();
-    });
+    // WRITER 持锁期间关中断,中断处理函数中打印不会死锁
+    WRITER.lock().write_fmt(args).unwrap();
 }